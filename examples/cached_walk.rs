@@ -0,0 +1,26 @@
+//! Run this example twice, the second run lists `.` from the cache:
+//! ```no_rust
+//! cargo run --example cached_walk
+//! ```
+use itfs::CachedWalk;
+
+fn main() {
+    let cache = std::env::temp_dir().join("itfs-cached-walk-example");
+    let mut walk = CachedWalk::new(".", &cache).unwrap();
+
+    let mut files = 0;
+    for path in walk.by_ref() {
+        match path {
+            Ok(_) => files += 1,
+            Err(e) => println!("error: {}", e),
+        }
+    }
+
+    println!(
+        "{} files, {} directories read, {} directories from the cache",
+        files,
+        walk.dirs_read(),
+        walk.dirs_cached()
+    );
+    walk.save().unwrap();
+}
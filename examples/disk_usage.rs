@@ -0,0 +1,35 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example disk_usage
+//! ```
+use itfs::{ComponentFilter, ComponentFilterOperationType, DiskUsage, ReadDirRecursive};
+
+fn main() {
+    // leave the build output out of the count
+    let walk = ComponentFilter::new(
+        ReadDirRecursive::new(".").unwrap(),
+        "target",
+        ComponentFilterOperationType::Exclude,
+    );
+
+    let mut usage = DiskUsage::new(".").max_depth(2);
+    usage.extend(walk);
+
+    let total = usage.total();
+    println!(
+        "{} files, {} bytes ({} bytes on disk)",
+        total.files, total.apparent, total.allocated
+    );
+
+    println!("largest subtrees:");
+    for dir in usage.largest(5) {
+        println!(
+            "{:>10} {:>6} files  {:?}",
+            dir.allocated, dir.files, dir.path
+        );
+    }
+
+    for error in usage.errors() {
+        println!("error: {}", error);
+    }
+}
@@ -0,0 +1,36 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example faulty_fs
+//! ```
+use std::io::ErrorKind;
+
+use itfs::{prelude::*, DiskUsage, FaultyFs, FsOp, ReadDirRecursive, StdFs};
+
+fn main() {
+    // one in ten files of the real `./src` can not be inspected, the same ones on every run
+    let fs = FaultyFs::new(StdFs)
+        .fail_randomly(FsOp::SymlinkMetadata, ErrorKind::Other, 0.1)
+        .seed(7)
+        .fail("./src/ext", FsOp::ReadDir, ErrorKind::PermissionDenied);
+
+    let mut errors = vec![];
+    let files = ReadDirRecursive::with_fs("./src", &fs)
+        .unwrap()
+        .collect_errors(&mut errors)
+        .count();
+    println!("walker: {} files, {} errors", files, errors.len());
+    for e in &errors {
+        println!("  {}", e);
+    }
+
+    // consumers read the metadata through the same file system, which fails the same way
+    let mut usage = DiskUsage::new("./src").with_fs(&fs);
+    usage.extend(ReadDirRecursive::with_fs("./src", &fs).unwrap());
+    println!(
+        "disk usage: {} bytes, {} errors",
+        usage.total().apparent,
+        usage.errors().len()
+    );
+
+    println!("{} errors injected in total", fs.injected());
+}
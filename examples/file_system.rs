@@ -0,0 +1,58 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example file_system
+//! ```
+use std::{cell::Cell, io, path::Path};
+
+use itfs::{FileSystem, FindDirsWithComponent, ReadDirRecursive, StdFs};
+
+/// A [FileSystem] that prints every directory read and otherwise defers to the disk.
+struct Logged {
+    reads: Cell<usize>,
+}
+
+impl FileSystem for Logged {
+    type Metadata = <StdFs as FileSystem>::Metadata;
+    type DirEntry = <StdFs as FileSystem>::DirEntry;
+    type ReadDir = <StdFs as FileSystem>::ReadDir;
+    type File = <StdFs as FileSystem>::File;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir> {
+        self.reads.set(self.reads.get() + 1);
+        println!("read_dir {}", path.display());
+        StdFs.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Self::Metadata> {
+        StdFs.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Self::Metadata> {
+        StdFs.symlink_metadata(path)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<std::path::PathBuf> {
+        StdFs.read_link(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Self::File> {
+        StdFs.open(path)
+    }
+}
+
+fn main() {
+    let fs = Logged {
+        reads: Cell::new(0),
+    };
+
+    // the walkers borrow the file system, so it can be inspected afterwards
+    let files = ReadDirRecursive::with_fs("./src", &fs).unwrap().count();
+    println!("{} files, {} directories read", files, fs.reads.get());
+
+    let found = FindDirsWithComponent::with_fs("./src", "ext", &fs)
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    println!("{:?}", found);
+}
@@ -0,0 +1,28 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example find_ancestors
+//! ```
+use itfs::{FindAncestors, Marker};
+
+fn main() {
+    // nearest directory containing a `Cargo.toml`, without leaving the home directory
+    let nearest = FindAncestors::new(".", Marker::name("Cargo.toml"))
+        .unwrap()
+        .stop_at_home()
+        .next();
+    dbg!(nearest);
+
+    // every ancestor containing a markdown file, staying on the same filesystem
+    for found in FindAncestors::new(".", Marker::glob("*.md"))
+        .unwrap()
+        .same_filesystem()
+    {
+        dbg!(found.unwrap());
+    }
+
+    // every ancestor that is the root of a git repository
+    let repos = FindAncestors::new(".", Marker::predicate(|dir| dir.join(".git").is_dir()))
+        .unwrap()
+        .collect::<Vec<_>>();
+    dbg!(repos);
+}
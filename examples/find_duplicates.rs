@@ -0,0 +1,23 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example find_duplicates
+//! ```
+use itfs::{ComponentFilter, ComponentFilterOperationType, FindDuplicates, ReadDirRecursive};
+
+fn main() {
+    let rdr = ReadDirRecursive::new(".").unwrap();
+    let files = ComponentFilter::new(rdr, ".git", ComponentFilterOperationType::Exclude);
+
+    // leave empty files out, they would all be reported as duplicates of each other
+    for group in FindDuplicates::new(files).min_size(1) {
+        match group {
+            Ok(group) => {
+                println!("{} files of {} bytes:", group.paths.len(), group.size);
+                for path in group.paths {
+                    println!("    {:?}", path);
+                }
+            }
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example fixture
+//! ```
+use itfs::{prelude::*, Fixture, ReadDirRecursive};
+
+fn main() {
+    let fixture = Fixture::from_tree(
+        "
+        src/
+          main.rs = fn main() {}
+          util.rs [mtime=86400]
+        target/ [mode=700]
+          debug/
+            app [mode=755]
+        README.md = # App\\n
+        ",
+    )
+    .unwrap();
+
+    println!("fixture at {}", fixture.path().display());
+    for path in ReadDirRecursive::new(fixture.path())
+        .unwrap()
+        .exclude_component("target")
+        .to_paths()
+        .drop_errors()
+    {
+        println!("{}", path.display());
+    }
+
+    fixture.assert_listing(&[
+        "README.md",
+        "src/main.rs",
+        "src/util.rs",
+        "target/debug/app",
+    ]);
+    // the directory is removed here
+}
@@ -0,0 +1,43 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example fs_iterator_ext
+//! ```
+use std::ffi::OsStr;
+
+use itfs::{hash::Sha256, prelude::*, ReadDirRecursive, WalkStats};
+
+fn main() {
+    let extensions = vec![OsStr::new("rs")];
+    let stats = WalkStats::new();
+    let mut errors = vec![];
+
+    // the same chain as nesting `ErrorCollector(HashContents::new(EntryToPath(...)))`
+    let digests = ReadDirRecursive::new(".")
+        .unwrap()
+        .with_stats(&stats)
+        .exclude_component("target")
+        .exclude_component(".git")
+        .allow_extensions(&extensions)
+        .to_paths()
+        .hash_contents(Sha256::new())
+        .collect_errors(&mut errors)
+        .track(&stats);
+
+    for (path, digest) in digests {
+        println!("{}  {}", digest, path.display());
+    }
+
+    println!("{}", stats);
+    println!("{} errors", errors.len());
+
+    // paths of the examples, as they would be under `dist`
+    let mapped = ReadDirRecursive::new("./examples")
+        .unwrap()
+        .drop_errors()
+        .re_root("./examples", "dist");
+
+    for pair in mapped.take(3) {
+        let (from, to) = pair.unwrap();
+        println!("{:?} -> {:?}", from, to);
+    }
+}
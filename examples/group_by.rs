@@ -0,0 +1,31 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example group_by
+//! ```
+use itfs::{ComponentFilter, ComponentFilterOperationType, GroupBy, ReadDirRecursive};
+
+fn main() {
+    let walk = || {
+        ComponentFilter::new(
+            ReadDirRecursive::new(".").unwrap(),
+            "target",
+            ComponentFilterOperationType::Exclude,
+        )
+    };
+
+    let mut by_extension = GroupBy::extension_with(&["tar.gz", "d.ts"]);
+    by_extension.extend(walk());
+    println!("{}", by_extension);
+
+    let mut by_depth = GroupBy::depth(".").samples(1);
+    by_depth.extend(walk());
+    println!("{}", by_depth);
+
+    // any key works, here whether the file name is all lowercase
+    let mut by_case = GroupBy::custom(|path, _| {
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        Some((name == name.to_lowercase()).to_string())
+    });
+    by_case.extend(walk());
+    print!("{}", by_case);
+}
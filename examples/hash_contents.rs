@@ -0,0 +1,52 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example hash_contents
+//! ```
+use itfs::{
+    hash::{ContentHasher, Crc32, Digest, Sha256, XxHash64},
+    EntryToPath, HashContents, ReadDirRecursive, ResultFilter,
+};
+
+/// A custom hasher: the plain sum of all bytes.
+struct ByteSum(u64);
+
+impl ContentHasher for ByteSum {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0 = bytes
+            .iter()
+            .fold(self.0, |sum, b| sum.wrapping_add(*b as u64));
+    }
+
+    fn finish_reset(&mut self) -> Digest {
+        let sum = std::mem::take(&mut self.0);
+        Digest(sum.to_be_bytes().to_vec())
+    }
+}
+
+fn main() {
+    // a `sha256sum` style manifest
+    let files = EntryToPath(ResultFilter(ReadDirRecursive::new("./src").unwrap()));
+    for result in HashContents::new(files, Sha256::new()) {
+        let (path, digest) = result.unwrap();
+        println!("{}  {}", digest, path.display());
+    }
+
+    // the iterator can also receive `Result` items, errors are passed through
+    let files = ReadDirRecursive::new("./examples").unwrap();
+    for result in HashContents::new(files, Crc32::new()) {
+        match result {
+            Ok((path, digest)) => println!("crc32 {} {:?}", digest, path),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+
+    let files = ResultFilter(ReadDirRecursive::new("./examples").unwrap());
+    for (path, digest) in ResultFilter(HashContents::new(files, XxHash64::new())) {
+        println!("xxh64 {} {:?}", digest, path);
+    }
+
+    let files = ResultFilter(ReadDirRecursive::new("./examples").unwrap());
+    for (path, digest) in ResultFilter(HashContents::new(files, ByteSum(0))) {
+        println!("sum {} {:?}", digest, path);
+    }
+}
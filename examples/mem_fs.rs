@@ -0,0 +1,42 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example mem_fs
+//! ```
+use std::io;
+
+use itfs::{prelude::*, FsOp, MemFs, ReadDirRecursive};
+
+fn main() {
+    let mut fs = MemFs::from_tree(
+        "
+        project/
+          Cargo.toml = [package]
+          src/
+            lib.rs = pub mod walk;
+            walk.rs
+          target/ [mode=300]
+            debug/app
+          docs/guide.md
+          latest -> docs/guide.md
+        ",
+    )
+    .unwrap();
+
+    // the metadata of this file can not be read
+    fs.fail(
+        "project/src/walk.rs",
+        FsOp::SymlinkMetadata,
+        io::ErrorKind::Other,
+    );
+
+    // always the same order, entries are listed sorted by name
+    for item in ReadDirRecursive::with_fs("project", &fs)
+        .unwrap()
+        .to_paths()
+    {
+        match item {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => println!("error: {}", e),
+        }
+    }
+}
@@ -1,8 +1,6 @@
 use std::ffi::OsStr;
 
-use itfs::{
-    ext::allow_extensions_ext::AllowExtensionsExt, read_dir_recursive, EntryToPath, ResultFilter,
-};
+use itfs::{prelude::*, read_dir_recursive, EntryToPath, ResultFilter};
 
 fn main() {
     let root_path = ".";
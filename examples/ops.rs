@@ -0,0 +1,47 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example ops
+//! ```
+use itfs::{
+    ops::{self, OpOptions, RemoveEntries},
+    AllowExtensions, ErrorCollector, Traverse,
+};
+use std::ffi::OsStr;
+
+fn main() {
+    let scratch = std::env::temp_dir().join("itfs-ops-example");
+
+    // cp -r ./src <scratch>
+    let mut errors = vec![];
+    let copy = ops::copy_recursive("./src", &scratch, OpOptions::default()).unwrap();
+    for copied in ErrorCollector(copy, &mut errors) {
+        println!("copied {:?}", copied);
+    }
+
+    // delete only the `.rs` files, keeping the directories. Dry run first.
+    let allowed = vec![OsStr::new("rs")];
+    for options in [OpOptions::dry_run(), OpOptions::default()] {
+        let walk = AllowExtensions(Traverse::new(&scratch).unwrap().post_order(), &allowed);
+        for removed in ErrorCollector(RemoveEntries::new(walk, &scratch, options), &mut errors) {
+            println!(
+                "{} {:?}",
+                if options.dry_run {
+                    "would remove"
+                } else {
+                    "removed"
+                },
+                removed
+            );
+        }
+    }
+
+    // rm -r <scratch>
+    let remove = ops::remove_recursive(&scratch, OpOptions::default()).unwrap();
+    for removed in ErrorCollector(remove, &mut errors) {
+        println!("removed {:?}", removed);
+    }
+
+    for error in errors {
+        eprintln!("error: {}", error);
+    }
+}
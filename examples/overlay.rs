@@ -0,0 +1,20 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example overlay
+//! ```
+use itfs::Overlay;
+
+fn main() {
+    // configuration layers, from lowest to highest priority. Missing roots are skipped.
+    let roots = ["/usr/share/app", "/etc/app", "./examples", "./src"];
+
+    for entry in Overlay::new(&roots).include_shadowed() {
+        match entry {
+            Ok(entry) if entry.shadowed => {
+                println!("  (shadowed) {:?} in {}", entry.relative, roots[entry.root])
+            }
+            Ok(entry) => println!("{:?} from {}", entry.relative, roots[entry.root]),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
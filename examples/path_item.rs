@@ -0,0 +1,56 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example path_item
+//! ```
+use std::{
+    ffi::OsStr,
+    io,
+    path::{Path, PathBuf},
+};
+
+use itfs::{
+    prelude::*, ComponentFilter, ComponentFilterOperationType, EntryToPath, MaybeOk, PathItem,
+};
+
+/// Works the same for any item type, `Result` or not.
+fn print_all<T: MaybeOk, I: Iterator<Item = T>>(title: &str, iter: I) {
+    println!("{}:", title);
+    for item in iter {
+        match item.as_inner() {
+            Some(inner) => println!("  {}", inner.path().display()),
+            None => println!("  <error>"),
+        }
+    }
+}
+
+fn main() {
+    let extensions = vec![OsStr::new("rs")];
+
+    // borrowed paths
+    let paths = [Path::new("src/lib.rs"), Path::new("target/x.rs")];
+    let iter = ComponentFilter::new(
+        paths.iter().copied(),
+        "target",
+        ComponentFilterOperationType::Exclude,
+    );
+    print_all("&Path", iter);
+
+    // boxed paths
+    let boxed: Vec<Box<Path>> = vec![Path::new("README.md").into(), Path::new("a.rs").into()];
+    print_all("Box<Path>", boxed.into_iter().allow_extensions(&extensions));
+
+    // results of paths, errors pass through every adaptor untouched
+    let results: Vec<io::Result<PathBuf>> = vec![
+        Ok(PathBuf::from("src/main.rs")),
+        Err(io::ErrorKind::PermissionDenied.into()),
+        Ok(PathBuf::from("notes.txt")),
+    ];
+    print_all(
+        "Result<PathBuf>",
+        results.into_iter().allow_extensions(&extensions),
+    );
+
+    // `EntryToPath` turns any of them into owned paths
+    let owned: Vec<PathBuf> = EntryToPath(paths.iter().copied()).collect();
+    println!("{:?}", owned);
+}
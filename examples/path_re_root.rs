@@ -1,28 +1,38 @@
 use std::fs::read_dir;
 
-use itfs::{PathReRoot, ResultFilter};
+use itfs::{prelude::*, PathReRoot, ReRootRule, Unmatched};
 
 fn main() {
-    // build an iterator over the [PathBuf] items on both folders "./examples" and "./src"
+    // build an iterator over the [DirEntry] items on both folders "./examples" and "./src"
     // respectively
-    let fs_iter = ResultFilter(
+    let fs_iter = || {
         read_dir("./examples")
             .unwrap()
-            .chain(read_dir("./src").unwrap()),
-    )
-    .map(|e| e.path());
-
-    // build an iterator that replaces the root of the [PathBuf] items above
-    let fs_iter = PathReRoot {
-        inner_iter: fs_iter,
-        strip_prefix: "./examples",
-        replace_by: "./x/y/z",
+            .chain(read_dir("./src").unwrap())
     };
 
-    for (original, result) in fs_iter {
+    // build an iterator that replaces the root of the paths of the items above
+    let re_rooted = PathReRoot::new(fs_iter(), "./examples", "./x/y/z");
+
+    for result in re_rooted {
+        match result {
+            Ok((original, re_rooted)) => println!("{:?} => {:?}", original, re_rooted),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    // map the paths with a list of rules, the first one that matches wins, and skip
+    // those that no rule matches
+    let rules = vec![
+        ReRootRule::new("src/ext/*.rs", "docs/api/ext/*.md").unwrap(),
+        ReRootRule::new("src/**/*.rs", "docs/api/**/*.md").unwrap(),
+        ReRootRule::new("examples/*.rs", "target/examples/*").unwrap(),
+    ];
+
+    for result in fs_iter().re_root_with(rules).unmatched(Unmatched::Drop) {
         match result {
-            Ok(re_rooted) => println!("{:?} => {:?}", original, re_rooted),
-            Err(e) => println!("{:?} => {}", original, e.to_string()),
+            Ok((original, mapped)) => println!("{:?} => {:?}", original, mapped),
+            Err(e) => println!("{}", e),
         }
     }
 }
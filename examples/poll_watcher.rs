@@ -0,0 +1,28 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example poll_watcher
+//! ```
+//! Then create, edit, rename or delete files under `./src` (press Ctrl+C to stop).
+use std::time::Duration;
+
+use itfs::{ComponentFilter, ComponentFilterOperationType, PollWatcher, WatchEvent};
+
+fn main() {
+    let watcher = PollWatcher::new("./src")
+        .unwrap()
+        .interval(Duration::from_millis(500))
+        .debounce(Duration::from_millis(300))
+        .walk_with(|walk| {
+            ComponentFilter::new(walk, "target", ComponentFilterOperationType::Exclude)
+        });
+
+    for event in watcher {
+        match event {
+            Ok(WatchEvent::Created(path)) => println!("created  {:?}", path),
+            Ok(WatchEvent::Modified(path)) => println!("modified {:?}", path),
+            Ok(WatchEvent::Removed(path)) => println!("removed  {:?}", path),
+            Ok(WatchEvent::Renamed { from, to }) => println!("renamed  {:?} -> {:?}", from, to),
+            Err(e) => println!("error    {}", e),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example rename_plan
+//! ```
+use itfs::{EntryToPath, ReadDirRecursive, RenamePlan, ResultFilter};
+
+fn main() {
+    let paths = ResultFilter(EntryToPath(ReadDirRecursive::new("./src").unwrap()));
+    let plan = RenamePlan::new(paths, "{parent}/{stem:kebab}_{n:02}.{ext:upper}").unwrap();
+
+    for (from, to) in &plan.renames {
+        println!("{} -> {}", from.display(), to.display());
+    }
+
+    for conflict in &plan.conflicts {
+        println!("conflict: {}", conflict);
+    }
+
+    // the order in which the renames could be applied safely
+    match plan.steps() {
+        Ok(steps) => println!("{} steps", steps.len()),
+        Err(e) => println!("can not be applied: {}", e),
+    }
+}
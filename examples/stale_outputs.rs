@@ -0,0 +1,36 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example stale_outputs
+//! ```
+use std::fs;
+
+use itfs::{ReRootRule, StaleOutputs};
+
+fn main() {
+    let out = std::env::temp_dir().join("itfs-stale-outputs-example");
+    let rules = vec![ReRootRule::prefix("./src", &out)];
+
+    // "generate" the output of the first half of the sources, plus one without a source
+    fs::create_dir_all(out.join("ext")).unwrap();
+    for (i, verdict) in StaleOutputs::new("./src", rules.clone())
+        .unwrap()
+        .enumerate()
+    {
+        if i % 2 == 0 {
+            fs::write(verdict.unwrap().output, "generated").unwrap();
+        }
+    }
+    fs::write(out.join("deleted_module.rs"), "generated").unwrap();
+
+    let mut stale = StaleOutputs::new("./src", rules).unwrap();
+    for verdict in stale.by_ref() {
+        let verdict = verdict.unwrap();
+        println!("{:?} {:?}", verdict.freshness, verdict.source);
+    }
+
+    for orphan in stale.orphans(&out).unwrap() {
+        println!("orphan {:?}", orphan);
+    }
+
+    fs::remove_dir_all(out).unwrap();
+}
@@ -0,0 +1,33 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example sync_plan
+//! ```
+use itfs::{Compare, SyncOptions, SyncPlan};
+
+fn main() {
+    let destination = std::env::temp_dir().join("itfs-sync-plan-example");
+
+    let options = SyncOptions {
+        compare: Compare::new(),
+        delete: true,
+    };
+
+    // first run: everything has to be created
+    let plan = SyncPlan::new("./src", &destination, options).unwrap();
+    println!("--- dry run ---");
+    plan.dry_run(std::io::stdout()).unwrap();
+
+    println!("--- execute ---");
+    for (op, result) in plan.execute() {
+        match result {
+            Ok(()) => println!("done    {}", op),
+            Err(e) => println!("FAILED  {} ({})", op, e),
+        }
+    }
+
+    // second run: the destination is already in sync
+    let plan = SyncPlan::new("./src", &destination, options).unwrap();
+    println!("in sync: {}", plan.is_empty());
+
+    std::fs::remove_dir_all(destination).unwrap();
+}
@@ -0,0 +1,44 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example tar -- <dir with .tar files>
+//! ```
+use std::{ffi::OsStr, fs::File, io::BufReader};
+
+use itfs::{prelude::*, FileSystem, FsMetadata, ReadDirRecursive, StdFs, TarFs, TarReader};
+
+fn main() {
+    let root = std::env::args().nth(1).unwrap_or_else(|| ".".into());
+    let extensions = vec![OsStr::new("rs"), OsStr::new("md")];
+
+    // the archives under `root` are walked as directories
+    let fs = TarFs::new(StdFs);
+    for path in ReadDirRecursive::with_fs(&root, &fs)
+        .unwrap()
+        .exclude_component("target")
+        .allow_extensions(&extensions)
+        .to_paths()
+        .drop_errors()
+        .filter(|path| {
+            path.components()
+                .any(|c| c.as_os_str().to_string_lossy().ends_with(".tar"))
+        })
+    {
+        let len = fs.metadata(&path).map(|m| m.len()).unwrap_or_default();
+        println!("{:>8} {}", len, path.display());
+    }
+
+    // or read a single archive member by member
+    if let Some(archive) = std::env::args().nth(2) {
+        for entry in TarReader::new(BufReader::new(File::open(archive).unwrap())) {
+            match entry {
+                Ok(entry) => println!(
+                    "{:?} {:o} {}",
+                    entry.entry_type,
+                    entry.mode,
+                    entry.path.display()
+                ),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example top_n
+//! ```
+use std::time::SystemTime;
+
+use itfs::{ReadDirRecursive, TopN};
+
+fn main() {
+    let mut largest = TopN::largest(5);
+    largest.extend(ReadDirRecursive::new(".").unwrap());
+    println!("largest:");
+    for file in largest.into_sorted_vec() {
+        println!("{:>10} {:?}", file.key, file.path);
+    }
+
+    let mut newest = TopN::newest(5);
+    newest.extend(ReadDirRecursive::new(".").unwrap());
+    println!("newest:");
+    for file in newest.into_sorted_vec() {
+        let age = SystemTime::now()
+            .duration_since(file.key)
+            .unwrap_or_default();
+        println!("{:>8}s ago {:?}", age.as_secs(), file.path);
+    }
+
+    // any key works, here the files with the longest names
+    let mut longest = TopN::by_key(3, |path, _| path.file_name().map(|n| n.len()));
+    longest.extend(ReadDirRecursive::new(".").unwrap());
+    println!("longest names:");
+    for file in longest.into_sorted_vec() {
+        println!("{:>4} {:?}", file.key, file.path);
+    }
+}
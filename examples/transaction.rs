@@ -0,0 +1,56 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example transaction
+//! ```
+use itfs::{Transaction, TxOp};
+
+fn main() {
+    let dir = std::env::temp_dir().join("itfs-transaction-example");
+    let journal = dir.join(".journal");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // a previous run may have been interrupted: undo whatever it left halfway
+    if let Some(pending) = Transaction::recover(&journal).unwrap() {
+        println!("rolling back {} operations", pending.completed());
+        pending.rollback().unwrap();
+    }
+
+    std::fs::write(dir.join("config.toml"), "old").unwrap();
+    std::fs::write(dir.join("config.toml.new"), "new").unwrap();
+
+    // this one fails on its last operation, so nothing changes
+    let mut tx = Transaction::new(&journal).unwrap();
+    tx.push(TxOp::CreateDir(dir.join("archive")))
+        .push(TxOp::Copy {
+            from: dir.join("config.toml"),
+            to: dir.join("archive/config.toml"),
+        })
+        .push(TxOp::Rename {
+            from: dir.join("does-not-exist"),
+            to: dir.join("config.toml"),
+        });
+    match tx.commit() {
+        Ok(()) => println!("committed"),
+        Err(e) => println!("{}", e),
+    }
+    println!("archive exists: {}", dir.join("archive").exists());
+
+    // this one succeeds
+    let mut tx = Transaction::new(&journal).unwrap();
+    tx.push(TxOp::CreateDir(dir.join("archive")))
+        .push(TxOp::Copy {
+            from: dir.join("config.toml"),
+            to: dir.join("archive/config.toml"),
+        })
+        .push(TxOp::Rename {
+            from: dir.join("config.toml.new"),
+            to: dir.join("config.toml"),
+        });
+    tx.commit().unwrap();
+    println!(
+        "config.toml: {:?}",
+        std::fs::read_to_string(dir.join("config.toml")).unwrap()
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,26 @@
+//! Run this example:
+//! ```no_rust
+//! cargo run --example tree_diff
+//! ```
+use itfs::{Change, Compare, TreeDiff};
+
+fn main() {
+    let diff = TreeDiff::new("./examples", "./src")
+        .unwrap()
+        .compare(Compare {
+            content: true,
+            ..Compare::new()
+        });
+
+    for entry in diff {
+        match entry {
+            Ok(entry) => match entry.change {
+                Change::Added => println!("+ {:?}", entry.relative),
+                Change::Removed => println!("- {:?}", entry.relative),
+                Change::TypeChanged => println!("T {:?}", entry.relative),
+                Change::Modified(what) => println!("M {:?} {:?}", entry.relative, what),
+            },
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
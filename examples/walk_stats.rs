@@ -0,0 +1,30 @@
+//! Run this example:
+//!
+//! ```no_rust
+//! cargo run --example walk_stats
+//! ```
+use std::time::Duration;
+
+use itfs::{ComponentFilter, ComponentFilterOperationType, ReadDirRecursive, WalkStats};
+
+fn main() {
+    // print a progress line at most every 10 milliseconds
+    let stats = WalkStats::new().with_progress(Duration::from_millis(10), |s| {
+        eprintln!("progress: {}", s);
+    });
+
+    let rdr = ReadDirRecursive::new(".").unwrap().with_stats(&stats);
+    let iter = ComponentFilter::new(rdr, "target", ComponentFilterOperationType::Exclude);
+
+    // `track` counts what reaches the end of the chain, so the stats can tell how many
+    // entries were dropped by `ComponentFilter`
+    for entry in stats.track(iter) {
+        match entry {
+            Ok(entry) => println!("{:?}", entry.path()),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+
+    println!("summary: {}", stats);
+    println!("errors by kind: {:?}", stats.errors_by_kind());
+}
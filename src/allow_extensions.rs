@@ -1,13 +1,17 @@
 //! Export the `struct` [`AllowExtensions`]. Only will let through entries which extensions
-/// are in a list of "allowed" ones.
-use std::ffi::OsStr;
-use std::fs::DirEntry;
-use std::io::Error;
-use std::path::PathBuf;
+//! are in a list of "allowed" ones.
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
 
-/// Map an iterator over items of either type [`Result<DirEntry>`] or [`DirEntry`] or [`PathBuf`],
-/// into one equivalent that will only let through entries which file extensions are in a list of
-/// "allowed" ones.
+use crate::MaybeOk;
+
+/// Map an iterator over items of either type [`DirEntry`][std::fs::DirEntry] or
+/// [`PathBuf`][std::path::PathBuf] (or any other [PathItem][crate::PathItem]) or `Result`s of
+/// those, into one equivalent that will only let through entries which file extensions are in a
+/// list of "allowed" ones.
+///
+/// Extensions may have several parts, such as `tar.gz`, which `x.tar.gz` has along with `gz`.
+/// As with [Path::extension], a leading `.` does not start an extension: `.bashrc` has none.
 ///
 /// This iterator does not filter any [Result::Err] coming from the inner iterator.
 /// Those items will still pass the filter.
@@ -18,74 +22,104 @@ pub struct AllowExtensions<'a, T, I: Iterator<Item = T>>(
     pub &'a Vec<&'a OsStr>,
 );
 
-/// Supports iterators over items of type `Result<DirEntry, Error>`
-impl<I: Iterator<Item = Result<DirEntry, Error>>> Iterator
-    for AllowExtensions<'_, Result<DirEntry, Error>, I>
-{
-    type Item = Result<DirEntry, Error>;
+impl<T, I: Iterator<Item = T>> AllowExtensions<'_, T, I> {
+    fn allowed(&self, path: &Path) -> bool {
+        self.1.iter().any(|ext| has_extension(path, ext))
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.0.next() {
-                Some(Ok(entry)) => match entry.path().extension() {
-                    Some(ext) => {
-                        if self.1.contains(&ext) {
-                            break Some(Ok(entry)); // pass ok
-                        }
+/// Returns `true` if `path` has the extension `ext`. Single part extensions, the empty one
+/// included, are compared with [Path::extension]. Multi part ones (`tar.gz`) must end the
+/// file name with something before them.
+pub(crate) fn has_extension(path: &Path, ext: &OsStr) -> bool {
+    if !ext.as_encoded_bytes().contains(&b'.') {
+        return path.extension() == Some(ext);
+    }
 
-                        continue; // extension not allowed
-                    }
-                    None => continue, // no extension
-                },
-                Some(Err(err)) => {
-                    break Some(Err(err)); // errors pass ok
-                }
-                None => {
-                    break None; // self.0 reached the end
-                }
-            }
-        }
+    match (path.file_name().and_then(|n| n.to_str()), ext.to_str()) {
+        (Some(name), Some(ext)) => name
+            .strip_suffix(ext)
+            .and_then(|n| n.strip_suffix('.'))
+            .is_some_and(|stem| !stem.is_empty()),
+        // names that are not valid unicode can only have single part extensions
+        _ => false,
     }
 }
 
-/// Supports iterators over items of type [DirEntry]
-impl<I: Iterator<Item = DirEntry>> Iterator for AllowExtensions<'_, DirEntry, I> {
+/// The extension of `path`: the longest of the `compounds` (multi part extensions such as
+/// `tar.gz`) it has, or else [Path::extension].
+pub(crate) fn extension_key(path: &Path, compounds: &[OsString]) -> Option<OsString> {
+    compounds
+        .iter()
+        .filter(|ext| has_extension(path, ext))
+        .max_by_key(|ext| ext.len())
+        .cloned()
+        .or_else(|| path.extension().map(OsStr::to_os_string))
+}
+
+/// Supports iterators over any [MaybeOk] items
+impl<T: MaybeOk, I: Iterator<Item = T>> Iterator for AllowExtensions<'_, T, I> {
     type Item = I::Item;
+
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             match self.0.next() {
-                Some(entry) => match entry.path().extension() {
-                    Some(ext) => {
-                        if self.1.contains(&ext) {
-                            break Some(entry);
-                        }
-                        continue; // extension not allowed
+                Some(item) => {
+                    if item.is_err_or(|path| self.allowed(path)) {
+                        break Some(item); // pass ok, errors pass ok too
                     }
-                    None => continue, // no extension
-                },
-                None => break None, // self.0 reached the end
+
+                    continue; // extension not allowed
+                }
+                None => {
+                    break None; // self.0 reached the end
+                }
             }
         }
     }
 }
 
-/// Supports iterators over items of type [PathBuf]
-impl<I: Iterator<Item = PathBuf>> Iterator for AllowExtensions<'_, PathBuf, I> {
-    type Item = I::Item;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.0.next() {
-                Some(entry) => match entry.extension() {
-                    Some(ext) => {
-                        if self.1.contains(&ext) {
-                            break Some(entry);
-                        }
-                        continue; // extension not allowed
-                    }
-                    None => continue, // no extension
-                },
-                None => break None, // self.0 reached the end
-            }
+#[cfg(test)]
+mod test {
+    use std::{
+        ffi::{OsStr, OsString},
+        path::{Path, PathBuf},
+    };
+
+    use super::{extension_key, has_extension, AllowExtensions};
+
+    #[test]
+    fn extensions() {
+        let subjects = [
+            ("a/x.rs", "rs", true),
+            ("x.tar.gz", "gz", true),
+            ("x.tar.gz", "tar.gz", true),
+            ("x.tar.gz", "ar.gz", false),
+            ("tar.gz", "tar.gz", false),
+            (".tar.gz", "tar.gz", false),
+            (".bashrc", "bashrc", false),
+            ("x.", "", true),
+            ("Makefile", "", false),
+        ];
+
+        for (path, ext, expect) in subjects {
+            assert_eq!(
+                has_extension(Path::new(path), ext.as_ref()),
+                expect,
+                "{path} {ext}"
+            );
         }
+
+        let compounds = [OsString::from("gz"), OsString::from("tar.gz")];
+        let key = |path: &str| extension_key(Path::new(path), &compounds);
+        assert_eq!(key("x.tar.gz"), Some("tar.gz".into()));
+        assert_eq!(key("x.d.ts"), Some("ts".into()));
+        assert_eq!(key("Makefile"), None);
+        assert_eq!(key(".bashrc"), None);
+
+        let paths = ["a.tar.gz", "b.gz", "c.rs", "d"].map(PathBuf::from);
+        let allowed = vec![OsStr::new("tar.gz"), OsStr::new("rs")];
+        let kept: Vec<_> = AllowExtensions(paths.iter(), &allowed).collect();
+        assert_eq!(kept, vec![&paths[0], &paths[2]]);
     }
 }
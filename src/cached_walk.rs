@@ -0,0 +1,347 @@
+//! Export the `struct` [`CachedWalk`]. Recursive walk equivalent to
+//! [ReadDirRecursive][crate::ReadDirRecursive] that keeps the listing of every directory in a
+//! cache file and only reads again the directories that changed since the previous run.
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::transaction::{decode_path, encode_path};
+
+/// First line of every cache file.
+const HEADER: &str = "itfs-walk-cache 1";
+
+/// Modification times this close to the start of the walk are not trusted: the directory
+/// could still change within the same tick of the clock of the file system.
+const RACY: Duration = Duration::from_secs(2);
+
+/// The listing of a directory, as stored in the cache.
+#[derive(Debug, Clone)]
+struct Listing {
+    /// Modification time of the directory, [None] if it can not be trusted.
+    mtime: Option<SystemTime>,
+    /// Entries in the order [fs::read_dir] returned them, with whether they are directories.
+    entries: Vec<(OsString, bool)>,
+}
+
+/// An item of the directory being walked.
+enum Item {
+    Entry(PathBuf, bool),
+    Error(io::Error),
+}
+
+/// Recursive iterator over the files of a directory, yielding the same paths, in the same
+/// order, as [ReadDirRecursive][crate::ReadDirRecursive] mapped with
+/// [EntryToPath][crate::EntryToPath].
+///
+/// The listing of every directory is kept in a cache file along with the modification time of
+/// the directory, which changes whenever an entry is added, removed or renamed in it. On the
+/// next run, a directory whose modification time did not change is not read again: its cached
+/// listing is replayed instead, which costs a single `stat` per directory. Call
+/// [CachedWalk::save] once the walk is done to write the updated cache.
+///
+/// A cache file that can not be read is ignored and everything is read again. Directories
+/// modified right before the walk started are always read again on the next run, since their
+/// modification time alone does not prove they did not change afterwards.
+///
+/// ## Example
+/// ```
+/// use itfs::CachedWalk;
+///
+/// let cache = std::env::temp_dir().join("itfs-cached-walk-doc");
+/// let mut walk = CachedWalk::new("./src", &cache).unwrap();
+///
+/// for path in walk.by_ref() {
+///     println!("{:?}", path.unwrap());
+/// }
+///
+/// println!("{} directories read, {} from the cache", walk.dirs_read(), walk.dirs_cached());
+/// walk.save().unwrap();
+/// # std::fs::remove_file(cache).unwrap();
+/// ```
+pub struct CachedWalk {
+    cache_file: PathBuf,
+    started: SystemTime,
+    /// Listings loaded from the cache file.
+    old: HashMap<PathBuf, Listing>,
+    /// Listings of the directories visited by this walk, to be saved.
+    new: HashMap<PathBuf, Listing>,
+    /// Items of the directory being walked.
+    current: VecDeque<Item>,
+    /// Directories found but not visited yet.
+    pending_dirs: Vec<PathBuf>,
+    dirs_read: usize,
+    dirs_cached: usize,
+}
+
+impl CachedWalk {
+    /// Create a new instance of [CachedWalk] for the given path, using the given cache file.
+    /// This operation will fail if the root directory can not be read.
+    pub fn new<P: AsRef<Path>, C: AsRef<Path>>(path: P, cache_file: C) -> io::Result<Self> {
+        let cache_file = cache_file.as_ref().to_path_buf();
+        let mut walk = CachedWalk {
+            old: load(&cache_file).unwrap_or_default(),
+            cache_file,
+            started: SystemTime::now(),
+            new: HashMap::new(),
+            current: VecDeque::new(),
+            pending_dirs: vec![],
+            dirs_read: 0,
+            dirs_cached: 0,
+        };
+
+        walk.enter(path.as_ref().to_path_buf())?;
+        Ok(walk)
+    }
+
+    /// Number of directories read from the file system so far.
+    pub fn dirs_read(&self) -> usize {
+        self.dirs_read
+    }
+
+    /// Number of directories whose listing came from the cache so far.
+    pub fn dirs_cached(&self) -> usize {
+        self.dirs_cached
+    }
+
+    /// Write the listings of the directories visited so far to the cache file. Directories
+    /// not visited (because the walk was not finished) will be read again on the next run.
+    pub fn save(&self) -> io::Result<()> {
+        // write aside and rename, so an interrupted save never leaves a truncated cache
+        let mut temp = self.cache_file.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let mut out = BufWriter::new(fs::File::create(&temp)?);
+        writeln!(out, "{}", HEADER)?;
+        for (dir, listing) in &self.new {
+            let (secs, nanos) = match listing.mtime.map(|t| t.duration_since(UNIX_EPOCH)) {
+                Some(Ok(d)) => (d.as_secs().to_string(), d.subsec_nanos().to_string()),
+                _ => ("-".to_string(), "-".to_string()),
+            };
+            writeln!(out, "D\t{}\t{}\t{}", encode_path(dir)?, secs, nanos)?;
+            for (name, is_dir) in &listing.entries {
+                let kind = if *is_dir { "d" } else { "f" };
+                writeln!(out, "{}\t{}", kind, encode_path(Path::new(name))?)?;
+            }
+        }
+        out.into_inner()?.sync_all()?;
+
+        fs::rename(temp, &self.cache_file)
+    }
+
+    /// Load the listing of a directory, from the cache if it did not change, and make it the
+    /// directory being walked.
+    fn enter(&mut self, dir: PathBuf) -> io::Result<()> {
+        let mtime = fs::metadata(&dir)?.modified().ok();
+
+        if let Some(listing) = self.old.remove(&dir) {
+            if listing.mtime.is_some() && listing.mtime == mtime {
+                self.dirs_cached += 1;
+                self.current = listing
+                    .entries
+                    .iter()
+                    .map(|(name, is_dir)| Item::Entry(dir.join(name), *is_dir))
+                    .collect();
+                self.new.insert(dir, listing);
+                return Ok(());
+            }
+        }
+
+        let read_dir = fs::read_dir(&dir)?;
+        self.dirs_read += 1;
+
+        let mut entries = vec![];
+        let mut complete = true;
+        for entry in read_dir {
+            // `DirEntry::file_type` does not follow symlinks, like the `DirEntry::metadata`
+            // used by `ReadDirRecursive`
+            match entry.and_then(|e| Ok((e.file_name(), e.file_type()?.is_dir()))) {
+                Ok((name, is_dir)) => {
+                    self.current.push_back(Item::Entry(dir.join(&name), is_dir));
+                    entries.push((name, is_dir));
+                }
+                Err(e) => {
+                    self.current.push_back(Item::Error(e));
+                    complete = false;
+                }
+            }
+        }
+
+        if complete {
+            let trusted = mtime.filter(|t| *t + RACY < self.started);
+            let listing = Listing {
+                mtime: trusted,
+                entries,
+            };
+            self.new.insert(dir, listing);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for CachedWalk {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.current.pop_front() {
+                // directories are visited once the current one is done
+                Some(Item::Entry(path, true)) => {
+                    self.pending_dirs.push(path);
+                    continue;
+                }
+                Some(Item::Entry(path, false)) => break Some(Ok(path)),
+                Some(Item::Error(e)) => break Some(Err(e)),
+                None => match self.pending_dirs.pop() {
+                    Some(dir) => match self.enter(dir) {
+                        Ok(()) => continue,
+                        Err(e) => break Some(Err(e)),
+                    },
+                    None => break None,
+                },
+            }
+        }
+    }
+}
+
+/// Read a cache file. Any problem makes the whole cache unusable.
+fn load(cache_file: &Path) -> io::Result<HashMap<PathBuf, Listing>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid walk cache");
+    let mut lines = BufReader::new(fs::File::open(cache_file)?).lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Err(invalid());
+    }
+
+    let mut cache = HashMap::new();
+    let mut current: Option<(PathBuf, Listing)> = None;
+
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields.as_slice() {
+            ["D", dir, secs, nanos] => {
+                if let Some((dir, listing)) = current.take() {
+                    cache.insert(dir, listing);
+                }
+                let mtime = match (*secs, *nanos) {
+                    ("-", "-") => None,
+                    (secs, nanos) => {
+                        let secs = secs.parse().map_err(|_| invalid())?;
+                        let nanos = nanos
+                            .parse()
+                            .ok()
+                            .filter(|nanos| *nanos < 1_000_000_000)
+                            .ok_or_else(invalid)?;
+                        let mtime = UNIX_EPOCH.checked_add(Duration::new(secs, nanos));
+                        Some(mtime.ok_or_else(invalid)?)
+                    }
+                };
+                let listing = Listing {
+                    mtime,
+                    entries: vec![],
+                };
+                current = Some((decode_path(dir)?, listing));
+            }
+            [kind @ ("d" | "f"), name] => match &mut current {
+                Some((_, listing)) => listing
+                    .entries
+                    .push((decode_path(name)?.into_os_string(), *kind == "d")),
+                None => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        }
+    }
+
+    if let Some((dir, listing)) = current {
+        cache.insert(dir, listing);
+    }
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{load, CachedWalk, HEADER};
+    use crate::{EntryToPath, Fixture, ReadDirRecursive};
+
+    // directories can only be opened to set their modification time on unix
+    #[cfg(unix)]
+    #[test]
+    fn same_output_as_read_dir_recursive() {
+        let dir = std::env::temp_dir().join(format!("itfs-cached-walk-{}", std::process::id()));
+        let root = dir.join("root");
+        let cache = dir.join("cache");
+        let _ = fs::remove_dir_all(&dir);
+        for sub in ["a/b", "a/c", "d"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        for file in ["x", "a/y", "a/b/z", "a/c/w", "d/v"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+
+        // pretend everything was created long ago, so the cache trusts it
+        let age = |path: PathBuf| {
+            let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+            fs::File::open(path).unwrap().set_modified(old).unwrap();
+        };
+        for sub in ["", "a", "a/b", "a/c", "d"] {
+            age(root.join(sub));
+        }
+
+        let expected = || -> Vec<PathBuf> {
+            EntryToPath(ReadDirRecursive::new(&root).unwrap())
+                .map(|p| p.unwrap())
+                .collect()
+        };
+        let walk = |reads: usize| {
+            let mut walk = CachedWalk::new(&root, &cache).unwrap();
+            let paths: Vec<PathBuf> = walk.by_ref().map(|p| p.unwrap()).collect();
+            assert_eq!(paths, expected());
+            assert_eq!(walk.dirs_read(), reads);
+            walk.save().unwrap();
+        };
+
+        // nothing cached, then everything cached
+        walk(5);
+        walk(0);
+
+        // adding a file changes the modification time of its directory only
+        fs::write(root.join("a/c/new"), "new").unwrap();
+        walk(1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn garbage_cache() {
+        let fixture = Fixture::from_tree("root/\n  a/\n    x = x\n").unwrap();
+        let (root, cache) = (fixture.join("root"), fixture.join("cache"));
+
+        let garbage = [
+            format!("{}\nD\troot\t18446744073709551615\t0\n", HEADER),
+            format!("{}\nD\troot\t0\t4000000000\n", HEADER),
+            format!("{}\nD\troot\tx\t0\n", HEADER),
+            format!("{}\nf\tx\n", HEADER),
+            "not a cache\n".to_string(),
+        ];
+        for content in garbage {
+            fs::write(&cache, &content).unwrap();
+            assert!(load(&cache).is_err(), "{:?}", content);
+
+            // the cache is ignored and everything is read again
+            let mut walk = CachedWalk::new(&root, &cache).unwrap();
+            let paths: Vec<PathBuf> = walk.by_ref().map(|p| p.unwrap()).collect();
+            assert_eq!(paths, vec![root.join("a/x")]);
+            assert_eq!(walk.dirs_read(), 2);
+        }
+
+        fs::write(&cache, format!("{}\nD\troot\t-\t-\nd\ta\n", HEADER)).unwrap();
+        assert_eq!(load(&cache).unwrap()[&PathBuf::from("root")].mtime, None);
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers to read and compare file contents shared by the adaptors that look inside files.
+use std::{fs, io, io::Read, path::Path};
+
+/// Size of the chunks used when streaming file contents.
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Add the path to the message of an [io::Error], keeping its kind, so errors reported
+/// by a consumer of many files say which file failed.
+pub(crate) fn with_path(path: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+/// [fs::File::open] with the path attached to the error.
+pub(crate) fn open(path: &Path) -> io::Result<fs::File> {
+    fs::File::open(path).map_err(|e| with_path(path, e))
+}
+
+/// Fill as much of `buf` as possible, stopping only at the end of the file.
+/// Returns the number of bytes read.
+pub(crate) fn read_full(file: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Compare the contents of two files byte by byte.
+pub(crate) fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut file_a, mut file_b) = (open(a)?, open(b)?);
+    let (mut buf_a, mut buf_b) = (vec![0; CHUNK_SIZE], vec![0; CHUNK_SIZE]);
+
+    loop {
+        let read_a = read_full(&mut file_a, &mut buf_a).map_err(|e| with_path(a, e))?;
+        let read_b = read_full(&mut file_b, &mut buf_b).map_err(|e| with_path(b, e))?;
+
+        if read_a != read_b || buf_a[..read_a] != buf_b[..read_b] {
+            break Ok(false);
+        }
+        if read_a == 0 {
+            break Ok(true);
+        }
+    }
+}
+
+/// Identifies the file an entry points to, so hard links to the same inode can be detected.
+/// Always [None] on platforms without inodes.
+#[cfg(unix)]
+pub(crate) fn inode_key(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn inode_key(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,305 @@
+//! Export the `struct` [`DiskUsage`]. Consumes the output of a walker and adds up, for every
+//! directory, the size and number of the files under it, like `du` does.
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{FileSystem, FsMetadata, MaybeOk, PathItem, ReadDirRecursive, StdFs};
+
+/// Totals of a directory, including everything under it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirUsage {
+    pub path: PathBuf,
+    /// Sum of the lengths of the files.
+    pub apparent: u64,
+    /// Space the files take on disk.
+    pub allocated: u64,
+    pub files: u64,
+}
+
+/// Per directory totals of apparent size, allocated size and number of files, computed from
+/// the files yielded by a walker.
+///
+/// Feed it with [Extend::extend] from an iterator over items of type
+/// [DirEntry][std::fs::DirEntry], `Result<DirEntry>`, [PathBuf], `Result<PathBuf>` or any
+/// other [PathItem] (errors are kept aside, see
+/// [DiskUsage::errors]), or use [DiskUsage::scan] to walk a directory with
+/// [ReadDirRecursive]. Every file counts towards all the directories between it and the root.
+/// Files are never followed if they are symlinks, and files hard linked more than once are
+/// only counted the first time. The space taken by the directories themselves is not counted.
+///
+/// The metadata of the files is read from the disk by default. Use [DiskUsage::with_fs] to
+/// read it from any other [FileSystem], such as the one the walker runs over.
+///
+/// ## Example
+/// ```
+/// use itfs::DiskUsage;
+///
+/// let usage = DiskUsage::scan("./src").unwrap().max_depth(1);
+/// println!("total: {} bytes in {} files", usage.total().apparent, usage.total().files);
+///
+/// for dir in usage.largest(3) {
+///     println!("{:>10} {:?}", dir.allocated, dir.path);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DiskUsage<F: FileSystem = StdFs> {
+    /// The file system the metadata of the files is read from.
+    fs: F,
+    root: PathBuf,
+    max_depth: Option<usize>,
+    dirs: BTreeMap<PathBuf, DirUsage>,
+    /// Inodes already counted.
+    seen: HashSet<(u64, u64)>,
+    errors: Vec<io::Error>,
+}
+
+impl DiskUsage {
+    /// Create an empty [DiskUsage] for the directory `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        let root = root.as_ref().to_path_buf();
+        let mut dirs = BTreeMap::new();
+        dirs.insert(
+            root.clone(),
+            DirUsage {
+                path: root.clone(),
+                ..Default::default()
+            },
+        );
+
+        DiskUsage {
+            fs: StdFs,
+            root,
+            max_depth: None,
+            dirs,
+            seen: HashSet::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Walk `root` with [ReadDirRecursive] and add up everything under it. This operation
+    /// will fail if the initial call to [std::fs::read_dir] fails.
+    pub fn scan<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        let mut usage = DiskUsage::new(&root);
+        usage.extend(ReadDirRecursive::new(root)?);
+        Ok(usage)
+    }
+}
+
+impl<F: FileSystem> DiskUsage<F> {
+    /// Read the metadata of the files from the given [FileSystem] instead of the disk. The
+    /// totals added so far are kept.
+    pub fn with_fs<G: FileSystem>(self, fs: G) -> DiskUsage<G> {
+        DiskUsage {
+            fs,
+            root: self.root,
+            max_depth: self.max_depth,
+            dirs: self.dirs,
+            seen: self.seen,
+            errors: self.errors,
+        }
+    }
+
+    /// Only keep totals for directories up to `depth` levels below the root (the root itself
+    /// is at depth 0). Deeper files still count towards their ancestors within that depth.
+    /// Can be used before or after adding files.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        let root = &self.root;
+        self.dirs.retain(|path, _| depth_of(root, path) <= depth);
+        self
+    }
+
+    /// Add a single file given its metadata, which should not follow symlinks. Directories
+    /// are ignored, so walkers that also yield them can feed a [DiskUsage] too.
+    pub fn add<M: FsMetadata>(&mut self, path: &Path, meta: &M) {
+        if meta.is_dir() {
+            return;
+        }
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => {
+                self.errors.push(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not under {}", path.display(), self.root.display()),
+                ));
+                return;
+            }
+        };
+
+        if let Some(key) = meta.inode() {
+            if !self.seen.insert(key) {
+                return; // another link to a file already counted
+            }
+        }
+
+        let (apparent, allocated) = (meta.len(), meta.allocated());
+        for ancestor in relative.parent().into_iter().flat_map(Path::ancestors) {
+            let depth = ancestor.components().count();
+            if self.max_depth.is_some_and(|max| depth > max) {
+                continue;
+            }
+
+            let dir = match ancestor.as_os_str().is_empty() {
+                true => self.root.clone(),
+                false => self.root.join(ancestor),
+            };
+            let usage = self.dirs.entry(dir).or_insert_with_key(|dir| DirUsage {
+                path: dir.clone(),
+                ..Default::default()
+            });
+            usage.apparent += apparent;
+            usage.allocated += allocated;
+            usage.files += 1;
+        }
+    }
+
+    /// Totals of the root directory.
+    pub fn total(&self) -> &DirUsage {
+        &self.dirs[&self.root]
+    }
+
+    /// Totals of a directory, if any file was found under it.
+    pub fn get<P: AsRef<Path>>(&self, dir: P) -> Option<&DirUsage> {
+        self.dirs.get(dir.as_ref())
+    }
+
+    /// Totals of every directory, sorted by path, the root first.
+    pub fn dirs(&self) -> impl Iterator<Item = &DirUsage> {
+        self.dirs.values()
+    }
+
+    /// The `n` subtrees of the root taking the most space on disk, largest first. Nested
+    /// subtrees are listed on their own, so a directory and its parent can both show up.
+    pub fn largest(&self, n: usize) -> Vec<&DirUsage> {
+        let mut dirs: Vec<&DirUsage> = self.dirs.values().filter(|d| d.path != self.root).collect();
+        dirs.sort_by(|a, b| b.allocated.cmp(&a.allocated).then(a.path.cmp(&b.path)));
+        dirs.truncate(n);
+        dirs
+    }
+
+    /// Errors found while adding files: errors yielded by the walker, files whose metadata
+    /// could not be read and files outside of the root.
+    pub fn errors(&self) -> &[io::Error] {
+        &self.errors
+    }
+
+    fn add_result(&mut self, item: io::Result<(PathBuf, F::Metadata)>) {
+        match item {
+            Ok((path, meta)) => self.add(&path, &meta),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+/// Number of components between `root` and `path`.
+fn depth_of(root: &Path, path: &Path) -> usize {
+    path.strip_prefix(root)
+        .map(|relative| relative.components().count())
+        .unwrap_or(0)
+}
+
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<F: FileSystem, T> Extend<T> for DiskUsage<F>
+where
+    T: MaybeOk<Error = io::Error>,
+{
+    fn extend<J: IntoIterator<Item = T>>(&mut self, iter: J) {
+        for item in iter {
+            let item = item.into_result().and_then(|item| {
+                let meta = self.fs.item_metadata(&item)?;
+                Ok((item.into_path_buf(), meta))
+            });
+            self.add_result(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        borrow::Cow,
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::DiskUsage;
+    use crate::{prelude::*, MemFs, ReadDirRecursive};
+
+    // hard links are only detected on unix
+    #[cfg(unix)]
+    #[test]
+    fn totals() {
+        let dir = std::env::temp_dir().join(format!("itfs-disk-usage-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+        fs::write(dir.join("top"), [0; 10]).unwrap();
+        fs::write(dir.join("a/one"), [0; 100]).unwrap();
+        fs::write(dir.join("a/b/two"), [0; 1000]).unwrap();
+        fs::write(dir.join("c/three"), [0; 50]).unwrap();
+        // a second link to the same file is not counted again
+        fs::hard_link(dir.join("a/b/two"), dir.join("c/link")).unwrap();
+
+        let usage = DiskUsage::scan(&dir).unwrap();
+        let apparent = |path: &str| usage.get(dir.join(path)).map(|u| u.apparent);
+        assert_eq!(usage.total().apparent, 1160);
+        assert_eq!(usage.total().files, 4);
+        assert_eq!(apparent("a"), Some(1100));
+        assert_eq!(apparent("a/b"), Some(1000));
+        assert!(matches!(apparent("c"), Some(50) | Some(1050)));
+        assert!(usage.errors().is_empty());
+
+        let usage = usage.max_depth(1);
+        assert_eq!(usage.get(dir.join("a/b")), None);
+        assert_eq!(usage.get(dir.join("a")).map(|u| u.files), Some(2));
+        assert_eq!(usage.largest(1).len(), 1);
+
+        // directories yielded along with the files are not counted
+        let mut usage = DiskUsage::new(&dir);
+        usage.extend(fs::read_dir(&dir).unwrap());
+        assert_eq!((usage.total().files, usage.total().apparent), (1, 10));
+        assert!(usage.errors().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn other_file_system() {
+        let mut fs = MemFs::new();
+        fs.file("root/a/one", [0; 100]).unwrap();
+        fs.file("root/two", [0; 10]).unwrap();
+
+        // paths are looked up in the given file system, not on the disk
+        let mut usage = DiskUsage::new("root").with_fs(&fs);
+        usage.extend(ReadDirRecursive::with_fs("root", &fs).unwrap().to_paths());
+        assert_eq!((usage.total().files, usage.total().apparent), (2, 110));
+        assert_eq!(usage.get("root/a").map(|u| u.apparent), Some(100));
+        assert!(usage.errors().is_empty());
+    }
+
+    #[test]
+    fn item_metadata() {
+        /// An item that carries the metadata of another file than the one it points at.
+        struct Carried(PathBuf, fs::Metadata);
+
+        impl PathItem for Carried {
+            fn path(&self) -> Cow<'_, Path> {
+                Cow::Borrowed(&self.0)
+            }
+
+            fn symlink_metadata(&self) -> io::Result<fs::Metadata> {
+                Ok(self.1.clone())
+            }
+        }
+
+        // the metadata of the item is used, the path is not looked up again
+        let meta = fs::symlink_metadata("Cargo.toml").unwrap();
+        let mut usage = DiskUsage::new("missing");
+        usage.extend([Carried(PathBuf::from("missing/file"), meta.clone())]);
+        assert!(usage.errors().is_empty());
+        assert_eq!(usage.total().apparent, meta.len());
+    }
+}
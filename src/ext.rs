@@ -1,3 +1,4 @@
 //! Extension modules that implement traits that extends pre existing types with new functionality.
 
 pub mod allow_extensions_ext;
+pub mod fs_iterator_ext;
@@ -1,26 +1,34 @@
-//! Extension that adds the method `only_extensions` to any iterator over items
-//! of type [DirEntry], `Result<DirEntry>` and [PathBuf]
+//! Extension that adds the method `allow_extensions` to any iterator over items
+//! of type [DirEntry][std::fs::DirEntry], `Result<DirEntry>`, [PathBuf][std::path::PathBuf],
+//! `Result<PathBuf>` or any other [PathItem][crate::PathItem]
+//!
+//! Deprecated, [FsIteratorExt][crate::ext::fs_iterator_ext::FsIteratorExt] has the same
+//! method and both cannot be in scope at once.
 
-use std::{ffi::OsStr, fs::DirEntry, path::PathBuf};
+use std::ffi::OsStr;
 
-use crate::AllowExtensions;
+use crate::{AllowExtensions, MaybeOk};
 
+#[deprecated(
+    since = "0.3.0",
+    note = "use `FsIteratorExt::allow_extensions` from `itfs::prelude` instead"
+)]
 pub trait AllowExtensionsExt<'a, T>: Iterator<Item = T> + Sized {
     /// Returns an iterator equivalent to the original but that skips/drops entries which
     /// file extension is NOT in the provided list of allowed ones.
     ///
     /// The resulting iterator won't change the type of the items coming from the original iterator.
     fn allow_extensions(self, extensions: &'a Vec<&'a OsStr>) -> AllowExtensions<'a, T, Self> {
-        return AllowExtensions(self, extensions);
+        AllowExtensions(self, extensions)
     }
 }
 
-/// Implementation for iterators over items of the type [DirEntry]
-impl<I> AllowExtensionsExt<'_, DirEntry> for I where I: Iterator<Item = DirEntry> {}
-/// Implementation for iterators over items of the type [PathBuf]
-impl<I> AllowExtensionsExt<'_, PathBuf> for I where I: Iterator<Item = PathBuf> {}
-/// Implementation for iterators over items of the type `Result<PathBuf>`
-impl<I, E> AllowExtensionsExt<'_, Result<DirEntry, E>> for I where
-    I: Iterator<Item = Result<DirEntry, E>>
+/// Implementation for iterators over any [MaybeOk] items: [DirEntry][std::fs::DirEntry],
+/// `Result<DirEntry>`, [PathBuf][std::path::PathBuf], `Result<PathBuf>`, ...
+#[allow(deprecated)]
+impl<I, T> AllowExtensionsExt<'_, T> for I
+where
+    I: Iterator<Item = T>,
+    T: MaybeOk,
 {
 }
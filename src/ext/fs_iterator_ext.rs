@@ -0,0 +1,137 @@
+//! Extension that adds a method for every adaptor of this crate to any iterator, so they can
+//! be chained instead of nested. Import it with `use itfs::prelude::*`.
+
+use std::{ffi::OsStr, path::Path};
+
+use crate::{
+    hash::ContentHasher, AllowExtensions, ComponentFilter, ComponentFilterOperationType,
+    EntryToPath, ErrorCollector, FindDuplicates, HashContents, MaybeOk, PathReRoot, ReRootRule,
+    ResultFilter, Tracked, WalkStats,
+};
+
+/// Method chaining access to the adaptors of this crate. Every method is a shorthand for
+/// building the adaptor of the same name around `self`.
+///
+/// The methods that filter or map paths accept any [MaybeOk] item (see
+/// [PathItem][crate::PathItem]), the ones about errors accept any `Result`.
+///
+/// ## Example
+/// ```
+/// use itfs::{prelude::*, ReadDirRecursive};
+/// use std::ffi::OsStr;
+///
+/// let extensions = vec![OsStr::new("rs")];
+/// let mut errors = vec![];
+///
+/// let sources: Vec<_> = ReadDirRecursive::new(".")
+///     .unwrap()
+///     .exclude_component("target")
+///     .allow_extensions(&extensions)
+///     .to_paths()
+///     .collect_errors(&mut errors)
+///     .collect();
+///
+/// assert!(sources.iter().any(|p| p.ends_with("src/lib.rs")));
+/// ```
+pub trait FsIteratorExt: Iterator + Sized {
+    /// Skip the items with a path component equal to `component`. See [ComponentFilter].
+    fn exclude_component<'a, R>(self, component: &'a R) -> ComponentFilter<'a, Self::Item, Self>
+    where
+        R: AsRef<OsStr> + ?Sized,
+        Self::Item: MaybeOk,
+    {
+        ComponentFilter::new(self, component, ComponentFilterOperationType::Exclude)
+    }
+
+    /// Only keep the items with a path component equal to `component`. See
+    /// [ComponentFilter].
+    fn include_component<'a, R>(self, component: &'a R) -> ComponentFilter<'a, Self::Item, Self>
+    where
+        R: AsRef<OsStr> + ?Sized,
+        Self::Item: MaybeOk,
+    {
+        ComponentFilter::new(self, component, ComponentFilterOperationType::Include)
+    }
+
+    /// Only keep the items with one of the given extensions. See [AllowExtensions].
+    fn allow_extensions<'a>(
+        self,
+        extensions: &'a Vec<&'a OsStr>,
+    ) -> AllowExtensions<'a, Self::Item, Self>
+    where
+        Self::Item: MaybeOk,
+    {
+        AllowExtensions(self, extensions)
+    }
+
+    /// Turn every item into its path, leaving errors untouched. See [EntryToPath].
+    fn to_paths(self) -> EntryToPath<Self::Item, Self>
+    where
+        Self::Item: MaybeOk,
+    {
+        EntryToPath(self)
+    }
+
+    /// Discard the [Err] items. See [ResultFilter].
+    fn drop_errors<T, E>(self) -> ResultFilter<T, E, Self>
+    where
+        Self: Iterator<Item = Result<T, E>>,
+    {
+        ResultFilter(self)
+    }
+
+    /// Move the [Err] items into `errors`. See [ErrorCollector].
+    fn collect_errors<T, E>(self, errors: &mut Vec<E>) -> ErrorCollector<'_, T, E, Self>
+    where
+        Self: Iterator<Item = Result<T, E>>,
+    {
+        ErrorCollector(self, errors)
+    }
+
+    /// Pair every path with the same path with `strip_prefix` replaced by `replace_by`. See
+    /// [PathReRoot].
+    fn re_root<P: AsRef<Path>, R: AsRef<Path>>(
+        self,
+        strip_prefix: P,
+        replace_by: R,
+    ) -> PathReRoot<Self::Item, Self>
+    where
+        Self::Item: MaybeOk,
+    {
+        PathReRoot::new(self, strip_prefix, replace_by)
+    }
+
+    /// Pair every path with the path produced by the first of the `rules` that matches it.
+    /// See [PathReRoot].
+    fn re_root_with(self, rules: Vec<ReRootRule>) -> PathReRoot<Self::Item, Self>
+    where
+        Self::Item: MaybeOk,
+    {
+        PathReRoot::with_rules(self, rules)
+    }
+
+    /// Pair every path with the digest of the contents of the file. See [HashContents].
+    fn hash_contents<H: ContentHasher>(self, hasher: H) -> HashContents<Self::Item, Self, H>
+    where
+        Self::Item: MaybeOk,
+    {
+        HashContents::new(self, hasher)
+    }
+
+    /// Consume the files and yield the groups of them with identical contents. See
+    /// [FindDuplicates].
+    fn find_duplicates(self) -> FindDuplicates<Self::Item, Self>
+    where
+        Self::Item: MaybeOk,
+    {
+        FindDuplicates::new(self)
+    }
+
+    /// Count the items that make it to this point of the chain. See [WalkStats::track].
+    fn track(self, stats: &WalkStats) -> Tracked<Self> {
+        stats.track(self)
+    }
+}
+
+/// Implementation for every iterator. Each method has its own requirements on the items.
+impl<I: Iterator> FsIteratorExt for I {}
@@ -0,0 +1,363 @@
+//! Export the `struct` [`FaultyFs`]. Wraps a [FileSystem] and makes chosen operations fail on
+//! chosen paths, whole subtrees or a seeded random share of the paths, to test how pipelines
+//! handle errors that are hard to reproduce for real.
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{find_ancestors::normalize, FileSystem, FsDirEntry, FsOp, PathItem};
+
+/// Which paths a fault applies to.
+#[derive(Debug, Clone)]
+enum Scope {
+    Path(PathBuf),
+    Under(PathBuf),
+    /// Probability in `0.0..=1.0`.
+    Random(f64),
+}
+
+#[derive(Debug, Clone)]
+struct Fault {
+    scope: Scope,
+    op: FsOp,
+    kind: io::ErrorKind,
+}
+
+/// Decides which calls fail. Shared by the file system and the iterators it returns.
+#[derive(Debug, Clone)]
+struct Injector {
+    faults: Arc<Vec<Fault>>,
+    seed: u64,
+    injected: Arc<AtomicUsize>,
+}
+
+impl Injector {
+    /// The kind of error `op` must fail with on `path`, if any.
+    fn fault(&self, op: FsOp, path: &Path) -> Option<io::ErrorKind> {
+        let path = normalize(path);
+        self.faults
+            .iter()
+            .filter(|fault| fault.op == op)
+            .find(|fault| match &fault.scope {
+                Scope::Path(p) => *p == path,
+                Scope::Under(dir) => path.starts_with(dir),
+                Scope::Random(probability) => chance(self.seed, op, &path) < *probability,
+            })
+            .map(|fault| fault.kind)
+    }
+
+    fn error(&self, kind: io::ErrorKind, op: FsOp, path: &Path) -> io::Error {
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        io::Error::new(
+            kind,
+            format!(
+                "{}: injected {:?} failure of {:?}",
+                path.display(),
+                kind,
+                op
+            ),
+        )
+    }
+
+    fn check(&self, op: FsOp, path: &Path) -> io::Result<()> {
+        match self.fault(op, path) {
+            Some(kind) => Err(self.error(kind, op, path)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A [FileSystem] that forwards every call to another one, except for those it was told to
+/// fail. Faults are chosen by operation ([FsOp]) and:
+///
+/// - path, with [FaultyFs::fail],
+/// - subtree, with [FaultyFs::fail_under],
+/// - a random share of the paths, with [FaultyFs::fail_randomly]. The draw only depends on
+///   the [seed][FaultyFs::seed], the operation and the path, so the same paths fail on every
+///   run and in every order.
+///
+/// Entries yielded by [FileSystem::read_dir] fail to give their metadata when
+/// [FsOp::SymlinkMetadata] is set to fail on their path, which is how a file removed between
+/// the listing of its directory and the `stat` that follows shows up. They fail the same way
+/// through [PathItem::symlink_metadata], the metadata the consumers of this crate (such as
+/// [DiskUsage][crate::DiskUsage]) read, so wrapping [StdFs][crate::StdFs] tests them too.
+///
+/// Paths are compared without their `.` components and with every `..` removed along with the
+/// component before it, so `./a`, `b/../a` and `a` are the same path.
+///
+/// ## Example
+/// ```
+/// use itfs::{prelude::*, FaultyFs, FsOp, MemFs, ReadDirRecursive};
+/// use std::io::ErrorKind;
+///
+/// let tree = MemFs::from_tree("locked/secret\ngone\nbroken\nok").unwrap();
+/// let fs = FaultyFs::new(tree)
+///     // EACCES listing a directory
+///     .fail("locked", FsOp::ReadDir, ErrorKind::PermissionDenied)
+///     // ENOENT, the file went away after being listed
+///     .fail("gone", FsOp::SymlinkMetadata, ErrorKind::NotFound)
+///     // EIO reading metadata
+///     .fail("broken", FsOp::SymlinkMetadata, ErrorKind::Other);
+///
+/// let mut errors = vec![];
+/// let paths: Vec<_> = ReadDirRecursive::with_fs("", &fs)
+///     .unwrap()
+///     .to_paths()
+///     .collect_errors(&mut errors)
+///     .collect();
+///
+/// assert_eq!(paths, vec![std::path::PathBuf::from("ok")]);
+/// assert_eq!(errors.len(), 3);
+/// assert_eq!(fs.injected(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaultyFs<F: FileSystem> {
+    inner: F,
+    injector: Injector,
+}
+
+impl<F: FileSystem> FaultyFs<F> {
+    /// Wrap `inner`, with no faults yet.
+    pub fn new(inner: F) -> Self {
+        FaultyFs {
+            inner,
+            injector: Injector {
+                faults: Arc::new(vec![]),
+                seed: 0,
+                injected: Arc::new(AtomicUsize::new(0)),
+            },
+        }
+    }
+
+    /// Make `op` fail with an error of the given kind on `path`.
+    pub fn fail<P: AsRef<Path>>(self, path: P, op: FsOp, kind: io::ErrorKind) -> Self {
+        self.with_fault(Scope::Path(normalize(path.as_ref())), op, kind)
+    }
+
+    /// Make `op` fail with an error of the given kind on `dir` and everything under it.
+    pub fn fail_under<P: AsRef<Path>>(self, dir: P, op: FsOp, kind: io::ErrorKind) -> Self {
+        self.with_fault(Scope::Under(normalize(dir.as_ref())), op, kind)
+    }
+
+    /// Make `op` fail with an error of the given kind on a share of the paths given by
+    /// `probability` (from `0.0` to `1.0`), picked according to the [seed][FaultyFs::seed].
+    pub fn fail_randomly(self, op: FsOp, kind: io::ErrorKind, probability: f64) -> Self {
+        self.with_fault(Scope::Random(probability), op, kind)
+    }
+
+    /// Seed of the paths picked by [FaultyFs::fail_randomly], `0` by default.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.injector.seed = seed;
+        self
+    }
+
+    /// Number of errors injected so far, by this file system and the entries it listed.
+    pub fn injected(&self) -> usize {
+        self.injector.injected.load(Ordering::Relaxed)
+    }
+
+    /// The wrapped file system.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn with_fault(mut self, scope: Scope, op: FsOp, kind: io::ErrorKind) -> Self {
+        Arc::make_mut(&mut self.injector.faults).push(Fault { scope, op, kind });
+        self
+    }
+}
+
+impl<F: FileSystem> FileSystem for FaultyFs<F> {
+    type Metadata = F::Metadata;
+    type DirEntry = FaultyEntry<F::DirEntry>;
+    type ReadDir = FaultyReadDir<F::ReadDir>;
+    type File = F::File;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir> {
+        self.injector.check(FsOp::ReadDir, path)?;
+        Ok(FaultyReadDir {
+            inner: self.inner.read_dir(path)?,
+            injector: self.injector.clone(),
+        })
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<F::Metadata> {
+        self.injector.check(FsOp::Metadata, path)?;
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<F::Metadata> {
+        self.injector.check(FsOp::SymlinkMetadata, path)?;
+        self.inner.symlink_metadata(path)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.injector.check(FsOp::ReadLink, path)?;
+        self.inner.read_link(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<F::File> {
+        self.injector.check(FsOp::Open, path)?;
+        self.inner.open(path)
+    }
+}
+
+/// The entries of a directory of a [FaultyFs].
+#[derive(Debug)]
+pub struct FaultyReadDir<R> {
+    inner: R,
+    injector: Injector,
+}
+
+impl<E: FsDirEntry, R: Iterator<Item = io::Result<E>>> Iterator for FaultyReadDir<R> {
+    type Item = io::Result<FaultyEntry<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.inner.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let fault = self.injector.fault(FsOp::SymlinkMetadata, &entry.path());
+        Some(Ok(FaultyEntry {
+            inner: entry,
+            fault: fault.map(|kind| (kind, self.injector.clone())),
+        }))
+    }
+}
+
+/// An entry of a directory of a [FaultyFs].
+#[derive(Debug)]
+pub struct FaultyEntry<E> {
+    inner: E,
+    /// The error to fail with when asked for metadata.
+    fault: Option<(io::ErrorKind, Injector)>,
+}
+
+impl<E> FaultyEntry<E> {
+    /// The entry of the wrapped file system.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    fn check(&self) -> io::Result<()>
+    where
+        E: PathItem,
+    {
+        match &self.fault {
+            Some((kind, injector)) => {
+                Err(injector.error(*kind, FsOp::SymlinkMetadata, &self.inner.path()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<E: PathItem> PathItem for FaultyEntry<E> {
+    fn path(&self) -> Cow<'_, Path> {
+        self.inner.path()
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        self.inner.into_path_buf()
+    }
+
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        self.check()?;
+        self.inner.symlink_metadata()
+    }
+}
+
+impl<E: FsDirEntry> FsDirEntry for FaultyEntry<E> {
+    type Metadata = E::Metadata;
+
+    fn file_name(&self) -> OsString {
+        self.inner.file_name()
+    }
+
+    fn metadata(&self) -> io::Result<E::Metadata> {
+        self.check()?;
+        self.inner.metadata()
+    }
+}
+
+/// A number in `0.0..1.0` that only depends on its arguments.
+fn chance(seed: u64, op: FsOp, path: &Path) -> f64 {
+    // FNV-1a, then the finalizer of SplitMix64 to spread the bits
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325 ^ seed;
+    let bytes = path.as_os_str().as_encoded_bytes();
+    for byte in [op as u8].iter().chain(bytes) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io, path::PathBuf};
+
+    use super::FaultyFs;
+    use crate::{prelude::*, FsOp, MemFs, ReadDirRecursive};
+
+    fn walk<F: crate::FileSystem>(fs: F) -> (Vec<PathBuf>, Vec<io::Error>)
+    where
+        F::DirEntry: PathItem,
+    {
+        let mut errors = vec![];
+        let paths = ReadDirRecursive::with_fs("root", fs)
+            .unwrap()
+            .to_paths()
+            .collect_errors(&mut errors)
+            .collect();
+        (paths, errors)
+    }
+
+    #[test]
+    fn targeted_and_random() {
+        let mut tree = String::from("root/\n");
+        for dir in 0..10 {
+            for file in 0..10 {
+                tree.push_str(&format!("  d{dir}/f{file}\n"));
+            }
+        }
+        let tree = MemFs::from_tree(&tree).unwrap();
+        assert_eq!(walk(&tree).0.len(), 100);
+
+        let fs = FaultyFs::new(&tree)
+            .fail("./root/d3", FsOp::ReadDir, io::ErrorKind::PermissionDenied)
+            .fail_under("root/d5", FsOp::SymlinkMetadata, io::ErrorKind::NotFound);
+        let (paths, errors) = walk(&fs);
+        // `root/d5` itself fails, so it is not entered
+        assert_eq!(paths.len(), 80);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(fs.injected(), 2);
+        assert!(paths
+            .iter()
+            .all(|p| !p.starts_with("root/d3") && !p.starts_with("root/d5")));
+
+        // the same paths fail on every run, others with another seed
+        let random = |seed| {
+            let fs = FaultyFs::new(&tree)
+                .fail_randomly(FsOp::SymlinkMetadata, io::ErrorKind::Other, 0.2)
+                .seed(seed);
+            walk(&fs).0
+        };
+        let (first, again, other) = (random(1), random(1), random(2));
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        // some directories may fail too, so only a loose bound on the share that fails
+        assert!((40..100).contains(&first.len()), "{}", first.len());
+    }
+}
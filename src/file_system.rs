@@ -0,0 +1,250 @@
+//! Export the `trait` [`FileSystem`]. The operations the walkers need from a file system, so
+//! they can run over something other than the disk, and [`StdFs`], the implementation backed
+//! by [std::fs].
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{contents::inode_key, PathItem};
+
+/// Metadata of a file, as returned by a [FileSystem].
+#[allow(clippy::len_without_is_empty)]
+pub trait FsMetadata {
+    fn is_dir(&self) -> bool;
+    fn is_file(&self) -> bool;
+    fn is_symlink(&self) -> bool;
+    /// Size of the file in bytes.
+    fn len(&self) -> u64;
+    /// Last modification time.
+    fn modified(&self) -> io::Result<SystemTime>;
+    /// `true` if the file can not be written.
+    fn readonly(&self) -> bool;
+    /// Unix permission bits, [None] where there are none.
+    fn mode(&self) -> Option<u32>;
+
+    /// Last access time. Fails with [io::ErrorKind::Unsupported] unless the file system
+    /// keeps track of it.
+    fn accessed(&self) -> io::Result<SystemTime> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "access time not available",
+        ))
+    }
+
+    /// Space taken on disk, which differs from the length for sparse files and because of
+    /// the block size. The length by default.
+    fn allocated(&self) -> u64 {
+        self.len()
+    }
+
+    /// Device and inode number of the file, so hard links to the same file can be detected.
+    /// [None] by default.
+    fn inode(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Numeric id of the owner of the file. [None] by default.
+    fn uid(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// An entry of a directory, as yielded by [FileSystem::read_dir].
+pub trait FsDirEntry: PathItem {
+    type Metadata: FsMetadata;
+
+    /// Name of the entry within its directory.
+    fn file_name(&self) -> OsString;
+
+    /// Metadata of the entry, without following symlinks.
+    fn metadata(&self) -> io::Result<Self::Metadata>;
+}
+
+/// The operations the walkers of this crate need from a file system. [StdFs] implements it
+/// with [std::fs]; other implementations can serve in memory trees, archives or remote
+/// listings to the same walkers and adaptors.
+///
+/// It is also implemented for references, so a walker can borrow a file system instead of
+/// owning it.
+///
+/// ## Example
+/// ```
+/// use itfs::{FileSystem, FsDirEntry, StdFs};
+/// use std::{io, path::Path};
+///
+/// /// Names in a directory of any file system.
+/// fn names<F: FileSystem>(fs: &F, dir: &Path) -> io::Result<Vec<String>> {
+///     fs.read_dir(dir)?
+///         .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+///         .collect()
+/// }
+///
+/// assert!(names(&StdFs, Path::new("./src")).unwrap().contains(&"lib.rs".to_string()));
+/// ```
+pub trait FileSystem {
+    type Metadata: FsMetadata;
+    type DirEntry: FsDirEntry<Metadata = Self::Metadata>;
+    type ReadDir: Iterator<Item = io::Result<Self::DirEntry>>;
+    type File: io::Read;
+
+    /// The entries of a directory, in no particular order.
+    fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir>;
+
+    /// Metadata of a file, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<Self::Metadata>;
+
+    /// Metadata of a file, without following symlinks.
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Self::Metadata>;
+
+    /// Metadata of the file an item points at, without following symlinks. The path is
+    /// looked up with [FileSystem::symlink_metadata] by default; [StdFs] asks the item
+    /// instead, so a [DirEntry][fs::DirEntry] uses its own metadata.
+    fn item_metadata<P: PathItem + ?Sized>(&self, item: &P) -> io::Result<Self::Metadata> {
+        self.symlink_metadata(&item.path())
+    }
+
+    /// The target of a symlink.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Open a file for reading.
+    fn open(&self, path: &Path) -> io::Result<Self::File>;
+}
+
+/// The [FileSystem] of the operating system, through [std::fs].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdFs;
+
+impl FileSystem for StdFs {
+    type Metadata = fs::Metadata;
+    type DirEntry = fs::DirEntry;
+    type ReadDir = fs::ReadDir;
+    type File = fs::File;
+
+    fn read_dir(&self, path: &Path) -> io::Result<fs::ReadDir> {
+        fs::read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<fs::Metadata> {
+        fs::metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<fs::Metadata> {
+        fs::symlink_metadata(path)
+    }
+
+    fn item_metadata<P: PathItem + ?Sized>(&self, item: &P) -> io::Result<fs::Metadata> {
+        item.symlink_metadata()
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<fs::File> {
+        fs::File::open(path)
+    }
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for &F {
+    type Metadata = F::Metadata;
+    type DirEntry = F::DirEntry;
+    type ReadDir = F::ReadDir;
+    type File = F::File;
+
+    fn read_dir(&self, path: &Path) -> io::Result<F::ReadDir> {
+        (**self).read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<F::Metadata> {
+        (**self).metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<F::Metadata> {
+        (**self).symlink_metadata(path)
+    }
+
+    fn item_metadata<P: PathItem + ?Sized>(&self, item: &P) -> io::Result<F::Metadata> {
+        (**self).item_metadata(item)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        (**self).read_link(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<F::File> {
+        (**self).open(path)
+    }
+}
+
+impl FsMetadata for fs::Metadata {
+    fn is_dir(&self) -> bool {
+        self.is_dir()
+    }
+
+    fn is_file(&self) -> bool {
+        self.is_file()
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.is_symlink()
+    }
+
+    fn len(&self) -> u64 {
+        self.len()
+    }
+
+    fn modified(&self) -> io::Result<SystemTime> {
+        self.modified()
+    }
+
+    fn readonly(&self) -> bool {
+        self.permissions().readonly()
+    }
+
+    #[cfg(unix)]
+    fn mode(&self) -> Option<u32> {
+        use std::os::unix::fs::PermissionsExt;
+        Some(self.permissions().mode())
+    }
+
+    #[cfg(not(unix))]
+    fn mode(&self) -> Option<u32> {
+        None
+    }
+
+    fn accessed(&self) -> io::Result<SystemTime> {
+        self.accessed()
+    }
+
+    #[cfg(unix)]
+    fn allocated(&self) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+        // `st_blocks` is always in units of 512 bytes
+        self.blocks() * 512
+    }
+
+    fn inode(&self) -> Option<(u64, u64)> {
+        inode_key(self)
+    }
+
+    #[cfg(unix)]
+    fn uid(&self) -> Option<u32> {
+        use std::os::unix::fs::MetadataExt;
+        Some(MetadataExt::uid(self))
+    }
+}
+
+impl FsDirEntry for fs::DirEntry {
+    type Metadata = fs::Metadata;
+
+    fn file_name(&self) -> OsString {
+        self.file_name()
+    }
+
+    fn metadata(&self) -> io::Result<fs::Metadata> {
+        self.metadata()
+    }
+}
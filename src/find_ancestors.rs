@@ -0,0 +1,293 @@
+//! Export the `struct` [`FindAncestors`]. This iterator climbs the parent directories of a
+//! given path and yields those that contain a given marker (`Cargo.toml`, `.git`, ...).
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{self, Component, Path, PathBuf},
+};
+
+use crate::glob::glob_match_os;
+
+/// What [FindAncestors] looks for inside each visited directory.
+pub enum Marker {
+    /// The directory contains an entry (file, directory or symlink) with exactly this name.
+    Name(OsString),
+    /// The directory contains an entry which name matches this pattern.
+    /// Supports `*`, `?` and `[...]` wildcards.
+    Glob(String),
+    /// The predicate returns `true` when called with the path of the directory.
+    Predicate(Box<dyn Fn(&Path) -> bool + Send>),
+}
+
+impl Marker {
+    /// Shorthand for [Marker::Name] that accepts a regular `&str`.
+    pub fn name<S: AsRef<OsStr> + ?Sized>(name: &S) -> Self {
+        Marker::Name(name.as_ref().to_os_string())
+    }
+
+    /// Shorthand for [Marker::Glob].
+    pub fn glob<S: Into<String>>(pattern: S) -> Self {
+        Marker::Glob(pattern.into())
+    }
+
+    /// Shorthand for [Marker::Predicate].
+    pub fn predicate<F: Fn(&Path) -> bool + Send + 'static>(predicate: F) -> Self {
+        Marker::Predicate(Box::new(predicate))
+    }
+
+    fn is_in(&self, dir: &Path) -> io::Result<bool> {
+        match self {
+            Marker::Name(name) => match fs::symlink_metadata(dir.join(name)) {
+                Ok(_) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e),
+            },
+            Marker::Glob(pattern) => {
+                for entry in fs::read_dir(dir)? {
+                    if glob_match_os(pattern, &entry?.file_name()) {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Marker::Predicate(predicate) => Ok(predicate(dir)),
+        }
+    }
+}
+
+/// Climbs the parent directories of a starting path (the starting path included) and yields
+/// those that contain a given [Marker]. It is the inverse of
+/// [FindDirsWithComponent][crate::FindDirsWithComponent], which searches downwards.
+///
+/// The nearest match is the first item yielded, so use `next()` to get only that one or keep
+/// iterating to get every match up to the root of the filesystem. An error inspecting a
+/// directory is yielded and the search goes on with its parent.
+///
+/// ## Example:
+/// ```
+/// use itfs::{FindAncestors, Marker};
+///
+/// let nearest = FindAncestors::new(".", Marker::name("Cargo.toml"))
+///     .unwrap()
+///     .stop_at_home()
+///     .next();
+///
+/// assert!(nearest.unwrap().unwrap().join("Cargo.toml").is_file());
+/// ```
+pub struct FindAncestors {
+    marker: Marker,
+    /// Directory to inspect on the next call to `next`. [None] once the search is over.
+    current: Option<PathBuf>,
+    /// Last directory to inspect, if [FindAncestors::stop_at_home] was used and the
+    /// starting path is inside the home directory.
+    ceiling: Option<PathBuf>,
+    /// Whether to stop when reaching a directory on a different filesystem.
+    same_filesystem: bool,
+    /// Device of the starting path, resolved lazily when [FindAncestors::same_filesystem] is used.
+    device: Option<u64>,
+}
+
+impl FindAncestors {
+    /// Create a new instance of [FindAncestors] that starts the search at the given path.
+    /// Relative paths are made absolute using the current directory, and `..` components are
+    /// resolved by removing the component before them, without resolving symlinks. This
+    /// operation will fail if that conversion fails.
+    pub fn new<P: AsRef<Path>>(start: P, marker: Marker) -> io::Result<Self> {
+        Ok(FindAncestors {
+            marker,
+            current: Some(normalize(&path::absolute(start)?)),
+            ceiling: None,
+            same_filesystem: false,
+            device: None,
+        })
+    }
+
+    /// Do not climb above the home directory of the current user. It has no effect if the
+    /// starting path is not inside the home directory or if it cannot be determined.
+    pub fn stop_at_home(self) -> Self {
+        match home_dir() {
+            Some(home) => self.stop_at(home),
+            None => self,
+        }
+    }
+
+    /// Do not climb above `dir`. It has no effect if the starting path is not inside `dir`.
+    pub(crate) fn stop_at<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        let dir = dir.as_ref();
+        self.ceiling = match &self.current {
+            Some(start) if start.starts_with(dir) => Some(dir.to_path_buf()),
+            _ => None,
+        };
+        self
+    }
+
+    /// Do not climb into a directory that lives on a different filesystem (device) than
+    /// the starting path. Only supported on unix platforms, it has no effect elsewhere.
+    pub fn same_filesystem(mut self) -> Self {
+        self.same_filesystem = true;
+        self
+    }
+
+    /// Whether the directory is on a different filesystem than the starting path.
+    fn crosses_filesystem(&mut self, dir: &Path) -> io::Result<bool> {
+        if !self.same_filesystem {
+            return Ok(false);
+        }
+
+        match (self.device, device_of(dir)?) {
+            (Some(start), Some(dev)) => Ok(start != dev),
+            (None, dev) => {
+                self.device = dev;
+                Ok(false)
+            }
+            (Some(_), None) => Ok(false),
+        }
+    }
+}
+
+impl Iterator for FindAncestors {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let dir = self.current.take()?;
+
+            // prepare the next directory to visit, unless we are at the ceiling
+            if self.ceiling.as_ref() != Some(&dir) {
+                self.current = dir.parent().map(Path::to_path_buf);
+            }
+
+            match self.crosses_filesystem(&dir) {
+                Ok(true) => {
+                    self.current = None;
+                    break None;
+                }
+                Ok(false) => {}
+                Err(e) => break Some(Err(e)),
+            }
+
+            match self.marker.is_in(&dir) {
+                Ok(true) => break Some(Ok(dir)),
+                Ok(false) => continue,
+                Err(e) => break Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// `path` without `.` components and with every `..` removed along with the component before
+/// it. `..` at the root stays at the root.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Home directory of the current user, taken from the environment.
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var)
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(unix)]
+fn device_of(path: &Path) -> io::Result<Option<u64>> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(Some(fs::metadata(path)?.dev()))
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, path::PathBuf};
+
+    use super::{FindAncestors, Marker};
+    use crate::Fixture;
+
+    #[test]
+    fn markers_and_limits() {
+        let fixture = Fixture::from_tree(
+            "
+            Cargo.toml
+            home/
+              project/
+                Cargo.toml
+                src/
+                  lib.rs
+                  deep/
+            ",
+        )
+        .unwrap();
+        let dir = fixture.path();
+
+        let find = |start: PathBuf, marker: Marker| -> Vec<PathBuf> {
+            FindAncestors::new(start, marker)
+                .unwrap()
+                .map(Result::unwrap)
+                .take_while(|found| found.starts_with(dir))
+                .collect()
+        };
+        let deep = fixture.join("home/project/src/deep");
+
+        assert_eq!(
+            find(deep.clone(), Marker::name("Cargo.toml")),
+            vec![fixture.join("home/project"), dir.to_path_buf()]
+        );
+        assert_eq!(
+            find(deep.clone(), Marker::glob("*.rs")),
+            vec![fixture.join("home/project/src")]
+        );
+        assert_eq!(
+            find(deep.clone(), Marker::predicate(|p| p.ends_with("home"))),
+            vec![fixture.join("home")]
+        );
+
+        // `..` is resolved before climbing, `deep` is not an ancestor of `deep/..`
+        assert_eq!(
+            find(deep.join(".."), Marker::predicate(|_| true))[0],
+            fixture.join("home/project/src")
+        );
+        let parent = env::current_dir().unwrap().parent().unwrap().to_path_buf();
+        let first = FindAncestors::new("..", Marker::predicate(|_| true))
+            .unwrap()
+            .next();
+        assert_eq!(first.unwrap().unwrap(), parent);
+
+        // the search ends at the ceiling, which is still inspected
+        let found: Vec<_> = FindAncestors::new(&deep, Marker::predicate(|_| true))
+            .unwrap()
+            .stop_at(fixture.join("home"))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                deep.clone(),
+                fixture.join("home/project/src"),
+                fixture.join("home/project"),
+                fixture.join("home"),
+            ]
+        );
+
+        // a ceiling the starting path is not inside of has no effect
+        let found = FindAncestors::new(&deep, Marker::predicate(|_| true))
+            .unwrap()
+            .stop_at(fixture.join("other"))
+            .count();
+        assert_eq!(found, deep.ancestors().count());
+    }
+}
@@ -1,12 +1,17 @@
 //! Export the `struct` [`FindDirsWithComponent`]. This iterator recursively searches in the given
 //! path for all directories which name match with one given as parameter.
-use std::{ffi::OsStr, fs, io, path};
+use std::{ffi::OsStr, io, path};
+
+use crate::{FileSystem, FsDirEntry, FsMetadata, PathItem, StdFs, WalkStats};
 
 /// Export the `struct` [`FindDirsWithComponent`]. This iterator recursively searches in the given
 /// path for all directories which name match with one given as parameter. Once it found a match,
 /// it will yield it's path without inspecting the content of such directory. It will however
 /// continue the search in any other found directory that does not match.
 ///
+/// It searches the disk by default. Use [FindDirsWithComponent::with_fs] to search any other
+/// [FileSystem].
+///
 /// ## Example:
 /// ```
 /// use itfs::FindDirsWithComponent;
@@ -18,26 +23,30 @@ use std::{ffi::OsStr, fs, io, path};
 ///     println!("Found folder: '{:?}'", entry.path());
 /// }
 /// ```
-pub struct FindDirsWithComponent<'a> {
+pub struct FindDirsWithComponent<'a, F: FileSystem = StdFs> {
     component: &'a OsStr,
-    /// This field hods the [fs::ReadDir] instance that is currently being iterated.
+    /// This field hods the [std::fs::ReadDir] instance that is currently being iterated.
     ///
-    /// At the beginning, it holds the [fs::ReadDir] iterator of the root directory
+    /// At the beginning, it holds the [std::fs::ReadDir] iterator of the root directory
     /// (given as param) but later, when all entries in the root where consumed (the
-    /// iterator reached the end) it will be replaced by a new instances of [fs::ReadDir]
+    /// iterator reached the end) it will be replaced by a new instances of [std::fs::ReadDir]
     /// as the main iteration continues visiting subdirectories of the root.
-    read_dir: fs::ReadDir,
+    read_dir: F::ReadDir,
     /// Sub Directories are not visited immediately when found. Instead they're
-    /// pushed onto a vector of pending directories/[entries][fs::DirEntry] (this field)
+    /// pushed onto a vector of pending directories/[entries][std::fs::DirEntry] (this field)
     /// and the iteration of the current directory continues with the next entry.
     /// Once that iteration is done, [FindDirsWithComponent] will `pop` one directory from this stack,
-    /// create a new instance of [fs::ReadDir] for it and resume the iteration.
-    pending_dirs: Vec<fs::DirEntry>,
+    /// create a new instance of [std::fs::ReadDir] for it and resume the iteration.
+    pending_dirs: Vec<F::DirEntry>,
+    /// Counters updated while iterating. See [FindDirsWithComponent::with_stats].
+    stats: Option<WalkStats>,
+    /// The file system being searched.
+    fs: F,
 }
 
 impl<'a> FindDirsWithComponent<'a> {
     /// Create a new instance of [FindDirsWithComponent] for the given path. This operation
-    /// will fail if the initial call to [fs::read_dir] fails.
+    /// will fail if the initial call to [std::fs::read_dir] fails.
     ///
     /// ```
     /// use itfs::FindDirsWithComponent;
@@ -47,28 +56,50 @@ impl<'a> FindDirsWithComponent<'a> {
     pub fn new<P: AsRef<path::Path>, R: AsRef<OsStr> + ?Sized>(
         path: P,
         component: &'a R,
+    ) -> io::Result<Self> {
+        FindDirsWithComponent::with_fs(path, component, StdFs)
+    }
+}
+
+impl<'a, F: FileSystem> FindDirsWithComponent<'a, F> {
+    /// Create a new instance of [FindDirsWithComponent] for the given path of the given
+    /// [FileSystem]. This operation will fail if the initial call to [FileSystem::read_dir]
+    /// fails.
+    pub fn with_fs<P: AsRef<path::Path>, R: AsRef<OsStr> + ?Sized>(
+        path: P,
+        component: &'a R,
+        fs: F,
     ) -> io::Result<Self> {
         Ok(FindDirsWithComponent {
             component: component.as_ref(),
             pending_dirs: vec![],
-            read_dir: fs::read_dir(&path)?,
+            read_dir: fs.read_dir(path.as_ref())?,
+            stats: None,
+            fs,
         })
     }
+
+    /// Keep the given [WalkStats] up to date while iterating. The root directory, already
+    /// read by [FindDirsWithComponent::new], is accounted for right away.
+    pub fn with_stats(mut self, stats: &WalkStats) -> Self {
+        stats.record_dir();
+        self.stats = Some(stats.clone());
+        self
+    }
 }
 
 // Implement Iterator for FindDirsWithComponent
-impl Iterator for FindDirsWithComponent<'_> {
-    type Item = io::Result<fs::DirEntry>;
+impl<F: FileSystem> Iterator for FindDirsWithComponent<'_, F> {
+    type Item = io::Result<F::DirEntry>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
+        let item = loop {
             match self.read_dir.next() {
                 Some(Ok(entry)) => match entry.metadata() {
                     Ok(meta) => {
                         if meta.is_dir() {
                             if entry
                                 .path()
-                                .as_path()
                                 .components()
                                 .any(|c| c.as_os_str() == self.component)
                             {
@@ -92,11 +123,15 @@ impl Iterator for FindDirsWithComponent<'_> {
                     // or finish the iteration completely.
                     if let Some(dir_entry) = self.pending_dirs.pop() {
                         let entry_path = dir_entry.path();
-                        match fs::read_dir(&entry_path) {
+                        match self.fs.read_dir(&entry_path) {
                             Ok(read_dir) => {
                                 // throw away the consumed iterator and put the new one in his place
                                 self.read_dir = read_dir;
 
+                                if let Some(stats) = &self.stats {
+                                    stats.record_dir();
+                                }
+
                                 // skip to the next iteration
                                 continue;
                             }
@@ -109,6 +144,15 @@ impl Iterator for FindDirsWithComponent<'_> {
                     break None;
                 }
             }
+        };
+
+        if let (Some(stats), Some(item)) = (&self.stats, &item) {
+            match item {
+                Ok(_) => stats.record_entry(0),
+                Err(e) => stats.record_error(e),
+            }
         }
+
+        item
     }
 }
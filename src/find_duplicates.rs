@@ -0,0 +1,308 @@
+//! Export the `struct` [`FindDuplicates`]. Consumes an iterator over files and yields groups
+//! of files with identical contents.
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    contents::{inode_key, open, read_full, same_contents, with_path},
+    MaybeOk, PathItem,
+};
+
+/// Number of bytes read from the beginning of each candidate to compute its partial hash.
+const PARTIAL_SIZE: usize = 4096;
+
+/// A group of files with identical contents, yielded by [FindDuplicates].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Size in bytes of each one of the files.
+    pub size: u64,
+    /// Paths of the files, sorted. There are always at least two.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Consumes an iterator over items of type [DirEntry][fs::DirEntry], `Result<DirEntry>`,
+/// [PathBuf], `Result<PathBuf>` or any other [PathItem] and yields groups of files with
+/// identical contents.
+///
+/// Files are narrowed down in three rounds, each more expensive than the previous one:
+/// 1. they are grouped by size,
+/// 2. files with the same size are grouped by a hash of their first bytes,
+/// 3. files in the same group are compared byte by byte.
+///
+/// Hard links to the same inode are treated as one file (only the first path seen is kept),
+/// so they are never reported as duplicates of each other. Anything that is not a regular
+/// file (directories, symlinks, ...) is ignored.
+///
+/// The inner iterator is consumed entirely on the first call to `next`. Errors coming from
+/// the inner iterator, as well as errors reading the files, are yielded before the groups,
+/// which come sorted from the biggest files to the smallest. A file that can not be read is
+/// left out of the groups, the files it was compared with are not.
+///
+/// ## Example
+/// ```
+/// use itfs::{FindDuplicates, ReadDirRecursive};
+///
+/// for group in FindDuplicates::new(ReadDirRecursive::new("./src").unwrap()) {
+///     match group {
+///         Ok(group) => println!("{} bytes: {:?}", group.size, group.paths),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+pub struct FindDuplicates<T, I: Iterator<Item = T>> {
+    /// Iterator to be consumed. [None] once it has been.
+    inner: Option<I>,
+    /// Files smaller than this are ignored.
+    min_size: u64,
+    /// Items left to yield.
+    pending: VecDeque<io::Result<DuplicateGroup>>,
+}
+
+impl<T, I: Iterator<Item = T>> FindDuplicates<T, I> {
+    /// Create a new instance of [FindDuplicates] over the given iterator.
+    pub fn new(inner: I) -> Self {
+        FindDuplicates {
+            inner: Some(inner),
+            min_size: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Ignore files smaller than `bytes`. Use `1` to leave empty files out.
+    pub fn min_size(mut self, bytes: u64) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Run the three rounds over the given paths and fill `self.pending`.
+    fn search(&mut self, paths: impl Iterator<Item = io::Result<PathBuf>>) {
+        let mut seen_inodes = HashSet::new();
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+        for path in paths {
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => {
+                    self.pending.push_back(Err(e));
+                    continue;
+                }
+            };
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    self.pending.push_back(Err(with_path(&path, e)));
+                    continue;
+                }
+            };
+
+            if !meta.is_file() || meta.len() < self.min_size {
+                continue;
+            }
+            if let Some(key) = inode_key(&meta) {
+                if !seen_inodes.insert(key) {
+                    continue; // hard link to a file already seen
+                }
+            }
+
+            by_size.entry(meta.len()).or_default().push(path);
+        }
+
+        let mut groups = vec![];
+
+        for (size, paths) in by_size.into_iter().filter(|(_, p)| p.len() > 1) {
+            let mut by_partial: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+            for path in paths {
+                match partial_hash(&path) {
+                    Ok(hash) => by_partial.entry(hash).or_default().push(path),
+                    Err(e) => self.pending.push_back(Err(e)),
+                }
+            }
+
+            for candidates in by_partial.into_values().filter(|p| p.len() > 1) {
+                for mut paths in self.compare(candidates) {
+                    paths.sort();
+                    groups.push(DuplicateGroup { size, paths });
+                }
+            }
+        }
+
+        groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.paths.cmp(&b.paths)));
+        self.pending.extend(groups.into_iter().map(Ok));
+    }
+
+    /// Split the candidates into classes of files with identical contents, keeping only
+    /// the classes with more than one file.
+    fn compare(&mut self, candidates: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
+        let mut classes: Vec<Vec<PathBuf>> = vec![];
+
+        'candidates: for path in candidates {
+            let mut i = 0;
+            while i < classes.len() {
+                match same_contents(&classes[i][0], &path) {
+                    Ok(true) => {
+                        classes[i].push(path);
+                        continue 'candidates;
+                    }
+                    Ok(false) => i += 1,
+                    Err(e) => {
+                        self.pending.push_back(Err(e));
+                        if open(&path).is_err() {
+                            continue 'candidates; // the candidate is the one that failed
+                        }
+                        // the first file of the class failed, the next one stands for it
+                        classes[i].remove(0);
+                        if classes[i].is_empty() {
+                            classes.remove(i);
+                        }
+                    }
+                }
+            }
+            classes.push(vec![path]);
+        }
+
+        classes.retain(|c| c.len() > 1);
+        classes
+    }
+}
+
+/// Hash of the first [PARTIAL_SIZE] bytes of the file.
+fn partial_hash(path: &Path) -> io::Result<u64> {
+    let mut buf = [0; PARTIAL_SIZE];
+    let read = read_full(&mut open(path)?, &mut buf).map_err(|e| with_path(path, e))?;
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&buf[..read]);
+    Ok(hasher.finish())
+}
+
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<T, I> Iterator for FindDuplicates<T, I>
+where
+    T: MaybeOk<Error = io::Error>,
+    I: Iterator<Item = T>,
+{
+    type Item = io::Result<DuplicateGroup>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(inner) = self.inner.take() {
+            self.search(inner.map(|item| item.into_result().map(PathItem::into_path_buf)));
+        }
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io, path::PathBuf};
+
+    use super::{DuplicateGroup, FindDuplicates, PARTIAL_SIZE};
+    use crate::Fixture;
+
+    #[test]
+    fn rounds() {
+        let fixture = Fixture::new().unwrap();
+        let write = |name: &str, contents: &[u8]| fs::write(fixture.join(name), contents).unwrap();
+        let big = |last: u8| {
+            let mut contents = vec![7; PARTIAL_SIZE * 2];
+            contents[PARTIAL_SIZE + 10] = last;
+            contents
+        };
+
+        // same size and first bytes, only the byte by byte comparison tells them apart
+        write("big_a", &big(1));
+        write("big_b", &big(1));
+        write("big_c", &big(2));
+        // same size, the partial hash tells them apart
+        write("abc", b"abc");
+        write("abc_copy", b"abc");
+        write("xyz", b"xyz");
+        // a size of its own
+        write("unique", b"unique");
+        write("empty_a", b"");
+        write("empty_b", b"");
+
+        let paths = || {
+            fs::read_dir(fixture.path())
+                .unwrap()
+                .map(|e| e.unwrap().path())
+        };
+        let groups: Vec<DuplicateGroup> =
+            FindDuplicates::new(paths()).map(Result::unwrap).collect();
+        let group = |size: usize, names: &[&str]| DuplicateGroup {
+            size: size as u64,
+            paths: names.iter().map(|n| fixture.join(n)).collect(),
+        };
+        assert_eq!(
+            groups,
+            vec![
+                group(PARTIAL_SIZE * 2, &["big_a", "big_b"]),
+                group(3, &["abc", "abc_copy"]),
+                group(0, &["empty_a", "empty_b"]),
+            ]
+        );
+
+        // empty files left out, errors of the inner iterator and of the files come first
+        let items = paths()
+            .map(Ok)
+            .chain([Err(io::Error::other("inner")), Ok(fixture.join("missing"))])
+            .collect::<Vec<io::Result<PathBuf>>>();
+        let results: Vec<_> = FindDuplicates::new(items.into_iter()).min_size(1).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].as_ref().is_err_and(|e| e.to_string() == "inner"));
+        assert!(results[1]
+            .as_ref()
+            .is_err_and(|e| e.kind() == io::ErrorKind::NotFound));
+        assert_eq!(results[3].as_ref().unwrap().paths.len(), 2);
+    }
+
+    #[test]
+    fn failed_comparison() {
+        let fixture = Fixture::from_tree("a = same\nb = same").unwrap();
+        let (a, b, missing) = (
+            fixture.join("a"),
+            fixture.join("b"),
+            fixture.join("missing"),
+        );
+        let mut find = FindDuplicates::new(Vec::<PathBuf>::new().into_iter());
+
+        // whichever file fails is left out, and only once
+        for candidates in [
+            vec![missing.clone(), a.clone(), b.clone()],
+            vec![a.clone(), missing.clone(), b.clone()],
+        ] {
+            assert_eq!(find.compare(candidates), vec![vec![a.clone(), b.clone()]]);
+            assert_eq!(find.pending.len(), 1);
+            assert!(find.pending.pop_front().unwrap().is_err());
+        }
+    }
+
+    // hard links are only detected on unix
+    #[cfg(unix)]
+    #[test]
+    fn hard_links() {
+        let fixture = Fixture::from_tree("a = same\nb = same\nc = same").unwrap();
+        fs::hard_link(fixture.join("a"), fixture.join("a_link")).unwrap();
+        fs::hard_link(fixture.join("c"), fixture.join("c_link")).unwrap();
+
+        let mut paths: Vec<PathBuf> = ["a", "a_link", "b", "c_link", "c"]
+            .iter()
+            .map(|n| fixture.join(n))
+            .collect();
+        let groups: Vec<_> = FindDuplicates::new(paths.clone().into_iter())
+            .map(Result::unwrap)
+            .collect();
+        // only the first path seen of each file is kept
+        paths.retain(|p| !p.ends_with("a_link") && !p.ends_with("c"));
+        paths.sort();
+        assert_eq!(groups, vec![DuplicateGroup { size: 4, paths }]);
+
+        // two links to the same file are not duplicates of each other
+        let links = [fixture.join("a"), fixture.join("a_link")];
+        assert_eq!(FindDuplicates::new(links.into_iter()).count(), 0);
+    }
+}
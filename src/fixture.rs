@@ -0,0 +1,448 @@
+//! Export the `struct` [`Fixture`]. A temporary directory filled with a described tree (files
+//! and their contents, directories, symlinks, permissions and modification times) and removed
+//! on drop, plus [`assert_listing`] to check what [ReadDirRecursive] finds in it.
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+use crate::{FileSystem, FsMetadata, MemFs, PathItem, ReadDirRecursive};
+
+/// Fixtures created by this process, to give each one its own directory.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory that is removed, with everything in it, when dropped.
+///
+/// The tree is described with the text format of [MemFs::from_tree], or built as a [MemFs]
+/// and copied with [Fixture::from_fs]. Permissions and modification times are applied after
+/// the content of each file and directory is in place, so unreadable, read only and old
+/// files and directories come out as described. Permissions are only applied on unix, where
+/// they are also restored before the removal so nothing is left behind.
+///
+/// ## Example
+/// ```
+/// use itfs::Fixture;
+///
+/// let fixture = Fixture::from_tree(
+///     "
+///     src/
+///       lib.rs = pub mod a;
+///       a.rs [mtime=0]
+///     lib -> src/lib.rs
+///     ",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(std::fs::read_to_string(fixture.join("lib")).unwrap(), "pub mod a;");
+/// fixture.assert_listing(&["lib", "src/a.rs", "src/lib.rs"]);
+/// ```
+#[derive(Debug)]
+pub struct Fixture {
+    root: PathBuf,
+    keep: bool,
+}
+
+impl Fixture {
+    /// An empty temporary directory.
+    pub fn new() -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+
+        loop {
+            let n = CREATED.fetch_add(1, Ordering::Relaxed);
+            let name = format!("itfs-fixture-{}-{}-{}", std::process::id(), nanos, n);
+            let root = std::env::temp_dir().join(name);
+            match fs::create_dir(&root) {
+                Ok(()) => return Ok(Fixture { root, keep: false }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// A temporary directory with the tree described by `tree`, in the format of
+    /// [MemFs::from_tree].
+    pub fn from_tree(tree: &str) -> io::Result<Self> {
+        Fixture::from_fs(&MemFs::from_tree(tree)?)
+    }
+
+    /// A temporary directory with a copy of the whole tree of `fs`. Symlinks are copied as
+    /// symlinks; absolute targets are taken as relative to the root, the way [MemFs] does.
+    /// The permissions of `fs` and its programmed errors do not get in the way of the copy.
+    pub fn from_fs(fs: &MemFs) -> io::Result<Self> {
+        let fixture = Fixture::new()?;
+        let copy = Copy {
+            readable: fs.unrestricted(),
+            original: fs.without_errors(),
+            root: &fixture.root,
+        };
+        copy.dir(Path::new(""), &fixture.root)?;
+        Ok(fixture)
+    }
+
+    /// Path of the temporary directory.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of something inside the temporary directory.
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Do not remove the directory on drop, to inspect it after a failed test. Returns its
+    /// path.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.root.clone()
+    }
+
+    /// Assert that [ReadDirRecursive] finds exactly the `expected` files in the fixture. See
+    /// [assert_listing].
+    #[track_caller]
+    pub fn assert_listing(&self, expected: &[&str]) {
+        assert_listing(&self.root, expected)
+    }
+}
+
+impl AsRef<Path> for Fixture {
+    fn as_ref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        if !self.keep {
+            make_removable(&self.root);
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// Assert that [ReadDirRecursive] yields exactly the `expected` files under `root`, and no
+/// errors. Paths are relative to `root`, with `/` as separator, and compared sorted, so the
+/// order of `expected` does not matter.
+///
+/// On failure, the panic message lists both sides merged, marking the files that were
+/// expected but not found with `-` and the ones found but not expected with `+`:
+///
+/// ```text
+/// listing of /tmp/itfs-fixture-1 differs from the expected one:
+///   src/a.rs
+/// - src/b.rs
+/// + src/c.rs
+/// ```
+#[track_caller]
+pub fn assert_listing<P: AsRef<Path>>(root: P, expected: &[&str]) {
+    let root = root.as_ref();
+    let mut actual = vec![];
+    let mut errors = vec![];
+
+    match ReadDirRecursive::new(root) {
+        Ok(walk) => {
+            for item in walk {
+                match item {
+                    Ok(entry) => actual.push(relative(root, &entry.path())),
+                    Err(e) => errors.push(e.to_string()),
+                }
+            }
+        }
+        Err(e) => errors.push(e.to_string()),
+    }
+
+    let expected: Vec<String> = expected.iter().map(|p| p.to_string()).collect();
+    if let Some(diff) = listing_diff(actual, expected, &errors) {
+        panic!(
+            "listing of {} differs from the expected one:\n{}",
+            root.display(),
+            diff
+        );
+    }
+}
+
+/// Both listings merged, one line per path, or [None] if they are the same and there are
+/// no errors.
+fn listing_diff(
+    mut actual: Vec<String>,
+    mut expected: Vec<String>,
+    errors: &[String],
+) -> Option<String> {
+    actual.sort();
+    expected.sort();
+    if actual == expected && errors.is_empty() {
+        return None;
+    }
+
+    let mut lines = vec![];
+    let (mut a, mut e) = (actual.iter().peekable(), expected.iter().peekable());
+    loop {
+        let line = match (a.peek(), e.peek()) {
+            (Some(x), Some(y)) if x == y => {
+                e.next();
+                format!("  {}", a.next()?)
+            }
+            (Some(x), Some(y)) if x > y => format!("- {}", e.next()?),
+            (Some(_), _) => format!("+ {}", a.next()?),
+            (None, Some(_)) => format!("- {}", e.next()?),
+            (None, None) => break,
+        };
+        lines.push(line);
+    }
+    lines.extend(errors.iter().map(|e| format!("! {}", e)));
+
+    Some(lines.join("\n"))
+}
+
+/// `path` relative to `root`, with `/` as separator.
+fn relative(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Copies a [MemFs] to the disk.
+struct Copy<'a> {
+    /// Where everything is read from.
+    readable: MemFs,
+    /// Where the metadata to apply is read from.
+    original: MemFs,
+    /// Directory the root of the [MemFs] is copied into.
+    root: &'a Path,
+}
+
+impl Copy<'_> {
+    /// Copy the content of the directory `from` into the existing directory `to`.
+    fn dir(&self, from: &Path, to: &Path) -> io::Result<()> {
+        for entry in self.readable.read_dir(from)? {
+            let source = entry?.path().into_owned();
+            let meta = self.original.symlink_metadata(&source)?;
+            let target = to.join(source.file_name().unwrap_or_default());
+
+            if meta.is_symlink() {
+                let link = self.readable.read_link(&source)?;
+                let link = match link.has_root() {
+                    true => self.root.join(link.strip_prefix("/").unwrap_or(&link)),
+                    false => link,
+                };
+                symlink(&self.readable, &source, &link, &target)?;
+                continue;
+            }
+
+            if meta.is_dir() {
+                fs::create_dir(&target)?;
+                self.dir(&source, &target)?;
+                // once the content is in place, so it does not change the time
+                set_modified_dir(&target, meta.modified()?)?;
+            } else {
+                let mut contents = vec![];
+                self.readable.open(&source)?.read_to_end(&mut contents)?;
+                let file = fs::File::create(&target)?;
+                io::Write::write_all(&mut &file, &contents)?;
+                file.set_modified(meta.modified()?)?;
+            }
+            set_mode(&target, &meta)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn symlink<F: FileSystem>(_fs: &F, _source: &Path, link: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(link, target)
+}
+
+#[cfg(windows)]
+fn symlink<F: FileSystem>(fs: &F, source: &Path, link: &Path, target: &Path) -> io::Result<()> {
+    match fs.metadata(source) {
+        Ok(meta) if meta.is_dir() => std::os::windows::fs::symlink_dir(link, target),
+        _ => std::os::windows::fs::symlink_file(link, target),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink<F: FileSystem>(_fs: &F, _source: &Path, _link: &Path, target: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{}: symlinks are not supported", target.display()),
+    ))
+}
+
+#[cfg(unix)]
+fn set_modified_dir(dir: &Path, modified: SystemTime) -> io::Result<()> {
+    fs::File::open(dir)?.set_modified(modified)
+}
+
+/// Directories can only be opened, to set their modification time, on unix.
+#[cfg(not(unix))]
+fn set_modified_dir(_dir: &Path, _modified: SystemTime) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode<M: FsMetadata>(path: &Path, meta: &M) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    match meta.mode() {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode<M: FsMetadata>(path: &Path, meta: &M) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(meta.readonly());
+    fs::set_permissions(path, permissions)
+}
+
+/// Give the owner full access to every directory under `dir`, so it can be removed.
+#[cfg(unix)]
+fn make_removable(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = fs::set_permissions(dir, fs::Permissions::from_mode(0o700));
+    if let Ok(read_dir) = fs::read_dir(dir) {
+        for entry in read_dir.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                make_removable(&entry.path());
+            }
+        }
+    }
+}
+
+/// Read only files can not be removed on windows.
+#[cfg(not(unix))]
+fn make_removable(dir: &Path) {
+    if let Ok(read_dir) = fs::read_dir(dir) {
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => make_removable(&path),
+                _ => {
+                    if let Ok(meta) = fs::symlink_metadata(&path) {
+                        let mut permissions = meta.permissions();
+                        #[allow(clippy::permissions_set_readonly_false)]
+                        permissions.set_readonly(false);
+                        let _ = fs::set_permissions(&path, permissions);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{listing_diff, Fixture};
+
+    #[test]
+    fn diff() {
+        let strings = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            listing_diff(strings(&["b", "a"]), strings(&["a", "b"]), &[]),
+            None
+        );
+        assert_eq!(
+            listing_diff(
+                strings(&["a", "c", "d"]),
+                strings(&["b", "a", "d", "e"]),
+                &[]
+            )
+            .unwrap(),
+            "  a\n- b\n+ c\n  d\n- e"
+        );
+        assert_eq!(
+            listing_diff(vec![], vec![], &["x: denied".to_string()]).unwrap(),
+            "! x: denied"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn materialize() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = Fixture::from_tree(
+            "
+            docs/ [mtime=3600]
+              guide.md = # Guide\\n
+            locked/ [mode=500]
+              inside
+            script.sh [mode=755 mtime=60] = echo
+            latest -> /docs/guide.md
+            ",
+        )
+        .unwrap();
+        let root = fixture.path().to_path_buf();
+
+        assert_eq!(
+            fs::read_to_string(fixture.join("latest")).unwrap(),
+            "# Guide\n"
+        );
+        assert_eq!(
+            fs::read_link(fixture.join("latest")).unwrap(),
+            fixture.join("docs/guide.md")
+        );
+        let script = fs::metadata(fixture.join("script.sh")).unwrap();
+        assert_eq!(script.permissions().mode() & 0o777, 0o755);
+        assert_eq!(
+            script.modified().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(60)
+        );
+        let docs = fs::metadata(fixture.join("docs")).unwrap();
+        assert_eq!(
+            docs.modified().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(3600)
+        );
+
+        fixture.assert_listing(&["docs/guide.md", "latest", "locked/inside", "script.sh"]);
+
+        // a read only directory does not prevent the removal
+        drop(fixture);
+        assert!(!root.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = Fixture::from_tree(
+            "
+            secret [mode=000] = hidden
+            write_only [mode=200] = x
+            locked/ [mode=000]
+              inside = y
+            search_only/ [mode=300]
+              x
+            ",
+        )
+        .unwrap();
+        let root = fixture.path().to_path_buf();
+        let mode = |path: &str| {
+            let meta = fs::symlink_metadata(fixture.join(path)).unwrap();
+            meta.permissions().mode() & 0o777
+        };
+
+        assert_eq!(mode("secret"), 0o000);
+        assert_eq!(mode("write_only"), 0o200);
+        assert_eq!(mode("locked"), 0o000);
+        assert_eq!(mode("search_only"), 0o300);
+        assert_eq!(fs::metadata(fixture.join("secret")).unwrap().len(), 6);
+
+        drop(fixture);
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,197 @@
+//! Minimal shell-like wildcard matching used to match file names against patterns.
+//!
+//! Supported syntax:
+//! - `*` matches any sequence of characters (including none).
+//! - `?` matches exactly one character.
+//! - `[abc]`, `[a-z]` match one character from the set/range, `[!abc]` negates it.
+//!
+//! Any other character matches itself.
+
+use std::ffi::OsStr;
+
+/// Returns `true` if the whole `name` matches the `pattern`. Names that are not valid
+/// unicode never match.
+pub(crate) fn glob_match_os(pattern: &str, name: &OsStr) -> bool {
+    match name.to_str() {
+        Some(name) => glob_match(pattern, name),
+        None => false,
+    }
+}
+
+/// Returns `true` if the whole `text` matches the `pattern`.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    star_spans(&pattern, &text).is_some()
+}
+
+/// Like [glob_match], but on a match returns what each `*` of the pattern matched, in order.
+/// Every `*` matches as few characters as possible.
+pub(crate) fn glob_captures(pattern: &str, text: &str) -> Option<Vec<String>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let spans = star_spans(&pattern, &text)?;
+    Some(
+        spans
+            .into_iter()
+            .map(|(start, end)| text[start..end].iter().collect())
+            .collect(),
+    )
+}
+
+/// Match the whole `text` against the `pattern` and return the range of `text` matched by
+/// each `*`, or [None] if they do not match.
+///
+/// Stars start empty and, on a mismatch, only the last one seen swallows one more character:
+/// the stars before it never need to grow, since whatever they could match the last one can
+/// match too. That keeps the matching at `O(pattern × text)` steps at worst.
+fn star_spans(pattern: &[char], text: &[char]) -> Option<Vec<(usize, usize)>> {
+    let (mut p, mut t) = (0, 0);
+    let mut spans: Vec<(usize, usize)> = vec![];
+    // position of the last `*` seen in the pattern, its span is the last one of `spans`
+    let mut backtrack: Option<usize> = None;
+
+    loop {
+        match pattern.get(p) {
+            Some('*') => {
+                spans.push((t, t));
+                backtrack = Some(p);
+                p += 1;
+                continue;
+            }
+            Some(_) if t < text.len() => {
+                if let Some(len) = match_one(&pattern[p..], text[t]) {
+                    p += len;
+                    t += 1;
+                    continue;
+                }
+            }
+            None if t == text.len() => return Some(spans),
+            _ => {}
+        }
+
+        // mismatch: let the last `*` swallow one more character, or fail
+        let star = backtrack?;
+        let span = spans.last_mut().expect("the last `*` has a span");
+        if span.1 == text.len() {
+            return None;
+        }
+        span.1 += 1;
+        p = star + 1;
+        t = span.1;
+    }
+}
+
+/// Match `c` against the first element of `pattern`, which is not a `*`. Returns the length
+/// of that element in the pattern if it matched.
+fn match_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern[0] {
+        '?' => Some(1),
+        '[' => match match_class(pattern, c) {
+            Some((true, len)) => Some(len),
+            Some((false, _)) => None,
+            // unterminated class, `[` is taken literally
+            None => (c == '[').then_some(1),
+        },
+        other => (other == c).then_some(1),
+    }
+}
+
+/// Match `c` against the class at the start of `pattern` (which begins with `[`).
+///
+/// Returns whether it matched and the length of the class in the pattern, or `None`
+/// if the class is not terminated.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        match pattern.get(i) {
+            None => return None,
+            // a `]` right after the opening bracket is a literal
+            Some(']') if !first => return Some((matched != negate, i + 1)),
+            Some(&lo) => {
+                if pattern.get(i + 1) == Some(&'-')
+                    && !matches!(pattern.get(i + 2), None | Some(']'))
+                {
+                    let hi = pattern[i + 2];
+                    matched |= lo <= c && c <= hi;
+                    i += 3;
+                } else {
+                    matched |= lo == c;
+                    i += 1;
+                }
+            }
+        }
+        first = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob_captures, glob_match};
+
+    #[test]
+    fn glob_match_fn() {
+        let subjects = [
+            ("Cargo.toml", "Cargo.toml", true),
+            ("Cargo.toml", "cargo.toml", false),
+            ("*.sln", "app.sln", true),
+            ("*.sln", "app.sln.bak", false),
+            ("*", "", true),
+            ("*", "anything", true),
+            ("?at", "cat", true),
+            ("?at", "at", false),
+            ("a*b*c", "aXXbYYc", true),
+            ("a*b*c", "aXXbYY", false),
+            ("*.tar.*", "x.tar.gz", true),
+            ("[abc].rs", "b.rs", true),
+            ("[abc].rs", "d.rs", false),
+            ("[!abc].rs", "d.rs", true),
+            ("[a-c]x", "bx", true),
+            ("[a-c]x", "dx", false),
+            ("[]]", "]", true),
+            ("[a-]", "-", true),
+            ("[oops", "[oops", true),
+            (".editorconfig", ".editorconfig", true),
+        ];
+
+        for (pattern, text, expect) in subjects {
+            assert_eq!(glob_match(pattern, text), expect, "{pattern} ~ {text}");
+        }
+    }
+
+    #[test]
+    fn glob_captures_fn() {
+        let subjects = [
+            ("*.ts", "a.b.ts", Some(vec!["a.b"])),
+            ("*.tar.*", "x.tar.gz", Some(vec!["x", "gz"])),
+            ("?-*", "a-", Some(vec![""])),
+            ("[a-c]*", "bee", Some(vec!["ee"])),
+            ("*.ts", "a.js", None),
+            ("*a*", "banana", Some(vec!["b", "nana"])),
+            ("*", "", Some(vec![""])),
+            ("[oops*", "[oops!", Some(vec!["!"])),
+        ];
+
+        for (pattern, text, expect) in subjects {
+            let expect = expect.map(|v| v.into_iter().map(String::from).collect::<Vec<_>>());
+            assert_eq!(glob_captures(pattern, text), expect, "{pattern} ~ {text}");
+        }
+
+        // would take ages if every `*` was retried with every length
+        let text = "a".repeat(100);
+        assert_eq!(glob_captures(&"*a".repeat(20), &(text.clone() + "b")), None);
+        assert!(!glob_match(&("*a".repeat(20) + "*b"), &text));
+        assert_eq!(
+            glob_captures(&"*a".repeat(20), &text).unwrap()[19],
+            "a".repeat(80)
+        );
+    }
+}
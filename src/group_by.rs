@@ -0,0 +1,330 @@
+//! Export the `struct` [`GroupBy`]. Consumes the output of a walker and sorts the files into
+//! buckets (by extension, parent directory, depth, owner or any key), with counts, total
+//! sizes and sample paths, printable as a table.
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{allow_extensions::extension_key, FileSystem, FsMetadata, MaybeOk, PathItem, StdFs};
+
+/// A bucket of a [GroupBy].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    /// [None] for the files without a value for the key, for example files without an
+    /// extension.
+    pub key: Option<String>,
+    pub count: u64,
+    /// Sum of the lengths of the files.
+    pub size: u64,
+    /// The first few files of the group.
+    pub samples: Vec<PathBuf>,
+}
+
+type CustomKey = Box<dyn FnMut(&Path, &dyn FsMetadata) -> Option<String>>;
+
+/// What [GroupBy] groups files by.
+enum Key {
+    Extension(Vec<OsString>),
+    Parent,
+    Depth(PathBuf),
+    Owner,
+    Custom(CustomKey),
+}
+
+/// Sorts files into buckets, keeping for each one the number of files, their total size and a
+/// few sample paths.
+///
+/// Feed it with [Extend::extend] from an iterator over items of type
+/// [DirEntry][std::fs::DirEntry], `Result<DirEntry>`, [PathBuf], `Result<PathBuf>` or any
+/// other [PathItem] (errors are kept aside, see
+/// [GroupBy::errors]). Metadata never follows symlinks. Files without a value for the key go
+/// to the group with the key [None].
+///
+/// The extension of a file is the last one by default (`gz` for `x.tar.gz`), or the longest of
+/// the multi part extensions given to [GroupBy::extension_with] (`tar.gz`). As with
+/// [Path::extension], a leading `.` does not start an extension: `.bashrc` has none.
+///
+/// The metadata of the files is read from the disk by default. Use [GroupBy::with_fs] to read
+/// it from any other [FileSystem], such as the one the walker runs over.
+///
+/// The [Display][fmt::Display] implementation prints the groups as a table, largest first.
+///
+/// ## Example
+/// ```
+/// use itfs::{GroupBy, ReadDirRecursive};
+///
+/// let mut by_extension = GroupBy::extension();
+/// by_extension.extend(ReadDirRecursive::new("./src").unwrap());
+///
+/// print!("{}", by_extension);
+/// assert!(by_extension.get(Some("rs")).unwrap().count > 0);
+/// ```
+pub struct GroupBy<F: FileSystem = StdFs> {
+    /// The file system the metadata of the files is read from.
+    fs: F,
+    key: Key,
+    max_samples: usize,
+    groups: HashMap<Option<String>, Group>,
+    errors: Vec<io::Error>,
+}
+
+impl GroupBy {
+    fn with_key(key: Key) -> Self {
+        GroupBy {
+            fs: StdFs,
+            key,
+            max_samples: 3,
+            groups: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Group files by extension.
+    pub fn extension() -> Self {
+        GroupBy::with_key(Key::Extension(vec![]))
+    }
+
+    /// Group files by extension, recognizing the given multi part extensions, such as
+    /// `tar.gz` or `d.ts`.
+    pub fn extension_with<S: AsRef<OsStr>>(compounds: &[S]) -> Self {
+        let compounds = compounds
+            .iter()
+            .map(|c| c.as_ref().to_os_string())
+            .collect();
+        GroupBy::with_key(Key::Extension(compounds))
+    }
+
+    /// Group files by the directory that contains them.
+    pub fn parent() -> Self {
+        GroupBy::with_key(Key::Parent)
+    }
+
+    /// Group files by how deep they are under `root`: files right in `root` are at depth 1.
+    /// Files outside of `root` have no key.
+    pub fn depth<P: AsRef<Path>>(root: P) -> Self {
+        GroupBy::with_key(Key::Depth(root.as_ref().to_path_buf()))
+    }
+
+    /// Group files by the numeric id of their owner. Files have no owner when their metadata
+    /// has no user id, see [FsMetadata::uid].
+    pub fn owner() -> Self {
+        GroupBy::with_key(Key::Owner)
+    }
+
+    /// Group files by the key returned by the given function.
+    pub fn custom<F>(key: F) -> Self
+    where
+        F: FnMut(&Path, &dyn FsMetadata) -> Option<String> + 'static,
+    {
+        GroupBy::with_key(Key::Custom(Box::new(key)))
+    }
+}
+
+impl<F: FileSystem> GroupBy<F> {
+    /// Read the metadata of the files from the given [FileSystem] instead of the disk. The
+    /// groups made so far are kept.
+    pub fn with_fs<G: FileSystem>(self, fs: G) -> GroupBy<G> {
+        GroupBy {
+            fs,
+            key: self.key,
+            max_samples: self.max_samples,
+            groups: self.groups,
+            errors: self.errors,
+        }
+    }
+
+    /// Keep up to `n` sample paths per group (3 by default).
+    pub fn samples(mut self, n: usize) -> Self {
+        self.max_samples = n;
+        self
+    }
+
+    /// Add a single file given its metadata.
+    pub fn add<M: FsMetadata>(&mut self, path: PathBuf, metadata: &M) {
+        let key = match &mut self.key {
+            Key::Extension(compounds) => {
+                extension_key(&path, compounds).map(|ext| ext.to_string_lossy().into_owned())
+            }
+            Key::Parent => path.parent().map(|p| p.display().to_string()),
+            Key::Depth(root) => path
+                .strip_prefix(root)
+                .ok()
+                .map(|relative| relative.components().count().to_string()),
+            Key::Owner => metadata.uid().map(|uid| uid.to_string()),
+            Key::Custom(key) => key(&path, metadata),
+        };
+
+        let group = self.groups.entry(key).or_insert_with_key(|key| Group {
+            key: key.clone(),
+            count: 0,
+            size: 0,
+            samples: vec![],
+        });
+        group.count += 1;
+        group.size += metadata.len();
+        if group.samples.len() < self.max_samples {
+            group.samples.push(path);
+        }
+    }
+
+    /// A single group, [None] standing for the files without a value for the key.
+    pub fn get(&self, key: Option<&str>) -> Option<&Group> {
+        self.groups.get(&key.map(String::from))
+    }
+
+    /// Every group, largest total size first, then by key.
+    pub fn groups(&self) -> Vec<&Group> {
+        let mut groups: Vec<&Group> = self.groups.values().collect();
+        groups.sort_by(|a, b| b.size.cmp(&a.size).then(a.key.cmp(&b.key)));
+        groups
+    }
+
+    /// Errors found while adding files: errors yielded by the walker and files whose
+    /// metadata could not be read.
+    pub fn errors(&self) -> &[io::Error] {
+        &self.errors
+    }
+
+    fn add_result(&mut self, item: io::Result<(PathBuf, F::Metadata)>) {
+        match item {
+            Ok((path, meta)) => self.add(path, &meta),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+/// One row per group, largest first. The files without a value for the key are listed as
+/// `(none)`:
+///
+/// ```text
+/// KEY  FILES  SIZE  SAMPLES
+/// rs      30  2867  ./src/lib.rs, ./src/rdr.rs, ./src/hash.rs
+/// ```
+impl<F: FileSystem> fmt::Display for GroupBy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups = self.groups();
+        let width = |header: &str, cell: &dyn Fn(&Group) -> usize| {
+            groups
+                .iter()
+                .map(|g| cell(g))
+                .fold(header.len(), usize::max)
+        };
+        let label = |group: &Group| group.key.as_deref().unwrap_or("(none)").to_string();
+        let key_w = width("KEY", &|g| label(g).chars().count());
+        let count_w = width("FILES", &|g| g.count.to_string().len());
+        let size_w = width("SIZE", &|g| g.size.to_string().len());
+
+        writeln!(
+            f,
+            "{:key_w$}  {:>count_w$}  {:>size_w$}  SAMPLES",
+            "KEY", "FILES", "SIZE"
+        )?;
+        for group in groups {
+            let samples: Vec<String> = group
+                .samples
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            writeln!(
+                f,
+                "{:key_w$}  {:>count_w$}  {:>size_w$}  {}",
+                label(group),
+                group.count,
+                group.size,
+                samples.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<F: FileSystem, T> Extend<T> for GroupBy<F>
+where
+    T: MaybeOk<Error = io::Error>,
+{
+    fn extend<J: IntoIterator<Item = T>>(&mut self, iter: J) {
+        for item in iter {
+            let item = item.into_result().and_then(|item| {
+                let meta = self.fs.item_metadata(&item)?;
+                Ok((item.into_path_buf(), meta))
+            });
+            self.add_result(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{ffi::OsStr, path::PathBuf};
+
+    use super::GroupBy;
+    use crate::{prelude::*, MemFs, ReadDirRecursive};
+
+    #[test]
+    fn other_file_system() {
+        let mut fs = MemFs::new();
+        fs.file("src/lib.rs", [0; 10]).unwrap();
+        fs.file("src/a.rs", [0; 5]).unwrap();
+        fs.file("README.md", [0; 100]).unwrap();
+
+        let mut by_extension = GroupBy::extension().with_fs(&fs);
+        by_extension.extend(ReadDirRecursive::with_fs("", &fs).unwrap().to_paths());
+        assert!(by_extension.errors().is_empty());
+        let rs = by_extension.get(Some("rs")).unwrap();
+        assert_eq!((rs.count, rs.size), (2, 15));
+        assert_eq!(by_extension.get(Some("md")).unwrap().size, 100);
+        assert!(by_extension
+            .to_string()
+            .starts_with("KEY  FILES  SIZE  SAMPLES\nmd "));
+
+        let mut by_size =
+            GroupBy::custom(|_, meta| Some((meta.len() >= 10).to_string())).with_fs(&fs);
+        by_size.extend(ReadDirRecursive::with_fs("", &fs).unwrap());
+        assert_eq!(by_size.get(Some("true")).unwrap().count, 2);
+        assert_eq!(by_size.get(Some("false")).unwrap().count, 1);
+    }
+
+    #[test]
+    fn extensions() {
+        // every file is in the group of an extension that AllowExtensions lets through
+        let mut fs = MemFs::new();
+        for file in [
+            "x.tar.gz", "y.gz", "z.d.ts", "w.ts", "v.", "Makefile", ".bashrc",
+        ] {
+            fs.file(file, "").unwrap();
+        }
+        let mut by_extension = GroupBy::extension_with(&["tar.gz", "d.ts"]).with_fs(&fs);
+        by_extension.extend(ReadDirRecursive::with_fs("", &fs).unwrap());
+        let mut keys = vec![];
+        for group in by_extension.groups() {
+            let Some(key) = &group.key else { continue };
+            let allowed = vec![OsStr::new(key)];
+            for sample in &group.samples {
+                let mut paths = [sample].into_iter().allow_extensions(&allowed);
+                assert!(paths.next().is_some(), "{} {:?}", key, sample);
+            }
+            keys.push((key.as_str(), group.count));
+        }
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![("", 1), ("d.ts", 1), ("gz", 1), ("tar.gz", 1), ("ts", 1)]
+        );
+
+        // a file named `(none)` does not end up with the files without an extension
+        let mut fs = MemFs::new();
+        fs.file("Makefile", "").unwrap();
+        fs.file("x.(none)", "").unwrap();
+        let mut by_extension = GroupBy::extension().with_fs(&fs);
+        by_extension.extend(ReadDirRecursive::with_fs("", &fs).unwrap());
+        assert_eq!(
+            by_extension.get(None).unwrap().samples,
+            vec![PathBuf::from("Makefile")]
+        );
+        assert_eq!(by_extension.get(Some("(none)")).unwrap().count, 1);
+    }
+}
@@ -0,0 +1,447 @@
+//! Digest implementations used by [HashContents][crate::HashContents], and the
+//! [ContentHasher] trait to plug your own.
+//!
+//! Everything is implemented in this crate, so it stays free of dependencies:
+//! - [Sha256]: cryptographic, 32 bytes digests.
+//! - [Crc32]: the IEEE CRC-32 used by zip, gzip and png, 4 bytes digests.
+//! - [XxHash64]: fast and non cryptographic, 8 bytes digests.
+
+use std::fmt;
+
+/// The result of hashing some content.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digest(pub Vec<u8>);
+
+impl Digest {
+    /// The raw bytes of the digest.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The digest as a string of lowercase hexadecimal digits.
+    pub fn to_hex(&self) -> String {
+        format!("{:x}", self)
+    }
+}
+
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays the digest in lowercase hexadecimal.
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+/// A streaming hasher. Content is fed in chunks with [ContentHasher::update] and the digest
+/// is obtained with [ContentHasher::finish_reset], which also leaves the hasher ready to
+/// hash new content. That way a single hasher can be reused for many files.
+pub trait ContentHasher {
+    /// Feed more content into the hasher.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Return the digest of all the content fed so far and reset the hasher to its initial state.
+    fn finish_reset(&mut self) -> Digest;
+
+    /// Hash the given content in one go.
+    fn digest(&mut self, bytes: &[u8]) -> Digest {
+        self.update(bytes);
+        self.finish_reset()
+    }
+}
+
+/// SHA-256, as specified by FIPS 180-4.
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    total_len: u64,
+}
+
+const SHA256_INIT: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Sha256 {
+            state: SHA256_INIT,
+            block: [0; 64],
+            block_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (i, word) in self.block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(bytes.len() as u64);
+
+        while !bytes.is_empty() {
+            let take = (64 - self.block_len).min(bytes.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&bytes[..take]);
+            self.block_len += take;
+            bytes = &bytes[take..];
+
+            if self.block_len == 64 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    fn finish_reset(&mut self) -> Digest {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        // padding: a single 1 bit, zeros, and the length in bits on the last 8 bytes
+        self.block[self.block_len] = 0x80;
+        self.block[self.block_len + 1..].fill(0);
+        if self.block_len >= 56 {
+            self.compress();
+            self.block.fill(0);
+        }
+        self.block[56..].copy_from_slice(&bit_len.to_be_bytes());
+        self.compress();
+
+        let digest = self.state.iter().flat_map(|s| s.to_be_bytes()).collect();
+        *self = Self::new();
+        Digest(digest)
+    }
+}
+
+/// CRC-32 (IEEE 802.3, reflected polynomial `0xEDB88320`). The digest bytes are big endian,
+/// so its hexadecimal form reads like the usual `u32` representation.
+#[derive(Clone)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32 { crc: 0xFFFFFFFF }
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentHasher for Crc32 {
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.crc = CRC32_TABLE[((self.crc ^ *byte as u32) & 0xFF) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn finish_reset(&mut self) -> Digest {
+        let crc = self.crc ^ 0xFFFFFFFF;
+        *self = Self::new();
+        Digest(crc.to_be_bytes().to_vec())
+    }
+}
+
+/// XXH64, the 64 bits variant of the xxHash algorithm. The digest bytes are big endian, so
+/// its hexadecimal form matches the canonical representation used by the `xxhsum` tool.
+#[derive(Clone)]
+pub struct XxHash64 {
+    seed: u64,
+    acc: [u64; 4],
+    buf: [u8; 32],
+    buf_len: usize,
+    total_len: u64,
+}
+
+const XXH_P1: u64 = 0x9E3779B185EBCA87;
+const XXH_P2: u64 = 0xC2B2AE3D27D4EB4F;
+const XXH_P3: u64 = 0x165667B19E3779F9;
+const XXH_P4: u64 = 0x85EBCA77C2B2AE63;
+const XXH_P5: u64 = 0x27D4EB2F165667C5;
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(XXH_P2))
+        .rotate_left(31)
+        .wrapping_mul(XXH_P1)
+}
+
+fn xxh_merge(hash: u64, acc: u64) -> u64 {
+    (hash ^ xxh_round(0, acc))
+        .wrapping_mul(XXH_P1)
+        .wrapping_add(XXH_P4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+impl XxHash64 {
+    /// Create a hasher with a seed of `0`.
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        XxHash64 {
+            seed,
+            acc: [
+                seed.wrapping_add(XXH_P1).wrapping_add(XXH_P2),
+                seed.wrapping_add(XXH_P2),
+                seed,
+                seed.wrapping_sub(XXH_P1),
+            ],
+            buf: [0; 32],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn stripe(acc: &mut [u64; 4], stripe: &[u8]) {
+        for (i, lane) in acc.iter_mut().enumerate() {
+            *lane = xxh_round(*lane, read_u64(&stripe[i * 8..]));
+        }
+    }
+}
+
+impl Default for XxHash64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentHasher for XxHash64 {
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(bytes.len() as u64);
+
+        // complete a previously buffered stripe first
+        if self.buf_len > 0 {
+            let take = (32 - self.buf_len).min(bytes.len());
+            self.buf[self.buf_len..self.buf_len + take].copy_from_slice(&bytes[..take]);
+            self.buf_len += take;
+            bytes = &bytes[take..];
+
+            if self.buf_len < 32 {
+                return;
+            }
+            Self::stripe(&mut self.acc, &self.buf);
+            self.buf_len = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(32);
+        for stripe in &mut stripes {
+            Self::stripe(&mut self.acc, stripe);
+        }
+
+        let rest = stripes.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buf_len = rest.len();
+    }
+
+    fn finish_reset(&mut self) -> Digest {
+        let [v1, v2, v3, v4] = self.acc;
+
+        let mut hash = if self.total_len >= 32 {
+            let hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            [v1, v2, v3, v4].into_iter().fold(hash, xxh_merge)
+        } else {
+            self.seed.wrapping_add(XXH_P5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.buf[..self.buf_len];
+        while rest.len() >= 8 {
+            hash ^= xxh_round(0, read_u64(rest));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(XXH_P1)
+                .wrapping_add(XXH_P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= (read_u32(rest) as u64).wrapping_mul(XXH_P1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(XXH_P2)
+                .wrapping_add(XXH_P3);
+            rest = &rest[4..];
+        }
+        for byte in rest {
+            hash ^= (*byte as u64).wrapping_mul(XXH_P5);
+            hash = hash.rotate_left(11).wrapping_mul(XXH_P1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(XXH_P2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(XXH_P3);
+        hash ^= hash >> 32;
+
+        *self = Self::with_seed(self.seed);
+        Digest(hash.to_be_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ContentHasher, Crc32, Sha256, XxHash64};
+
+    /// Hash the content in one go, then byte by byte and in uneven chunks, checking
+    /// that the three results are the expected one.
+    fn check<H: ContentHasher>(hasher: &mut H, content: &[u8], expect: &str) {
+        assert_eq!(hasher.digest(content).to_hex(), expect);
+
+        for byte in content {
+            hasher.update(&[*byte]);
+        }
+        assert_eq!(hasher.finish_reset().to_hex(), expect);
+
+        for chunk in content.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finish_reset().to_hex(), expect);
+    }
+
+    #[test]
+    fn sha256() {
+        let subjects: [(&[u8], &str); 4] = [
+            (
+                b"",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                b"abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                &[b'a'; 1000],
+                "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3",
+            ),
+        ];
+
+        for (content, expect) in subjects {
+            check(&mut Sha256::new(), content, expect);
+        }
+    }
+
+    #[test]
+    fn crc32() {
+        check(&mut Crc32::new(), b"", "00000000");
+        check(&mut Crc32::new(), b"123456789", "cbf43926");
+        check(
+            &mut Crc32::new(),
+            b"The quick brown fox jumps over the lazy dog",
+            "414fa339",
+        );
+    }
+
+    #[test]
+    fn xxhash64() {
+        check(&mut XxHash64::new(), b"", "ef46db3751d8e999");
+        check(&mut XxHash64::new(), b"a", "d24ec4f1a98c6e5b");
+        check(&mut XxHash64::new(), b"abc", "44bc2cf5ad770999");
+        check(
+            &mut XxHash64::new(),
+            b"Nobody inspects the spammish repetition",
+            "fbcea83c8a378bf1",
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Export the `struct` [`HashContents`]. Maps an iterator over files into one over pairs of
+//! path and [Digest] of the file contents.
+use std::{io, io::Read, path::PathBuf};
+
+use crate::{
+    contents::{open, with_path, CHUNK_SIZE},
+    hash::{ContentHasher, Digest},
+    MaybeOk, PathItem,
+};
+
+/// Maps an iterator over items of type [DirEntry][std::fs::DirEntry], `Result<DirEntry>`,
+/// [PathBuf], `Result<PathBuf>` or any other [PathItem] into one over items of type
+/// `Result<(PathBuf, Digest)>`, where the [Digest] is computed over the contents of the file
+/// with the given [ContentHasher].
+///
+/// Files are streamed through a buffer that is allocated once and reused for every file, and
+/// so is the hasher. Any [Err] coming from the inner iterator is yielded as is, and so are
+/// errors opening or reading a file (with its path added to the message).
+///
+/// ## Example
+/// ```
+/// use itfs::{hash::Sha256, EntryToPath, HashContents, ReadDirRecursive};
+///
+/// let files = EntryToPath(ReadDirRecursive::new("./src").unwrap());
+///
+/// for result in HashContents::new(files, Sha256::new()) {
+///     let (path, digest) = result.unwrap();
+///     println!("{}  {}", digest, path.display());
+/// }
+/// ```
+pub struct HashContents<T, I: Iterator<Item = T>, H: ContentHasher> {
+    /// Iterator over the files to hash
+    pub inner: I,
+    /// The hasher, reset after every file
+    pub hasher: H,
+    /// Buffer used to read the files
+    buf: Vec<u8>,
+}
+
+impl<T, I: Iterator<Item = T>, H: ContentHasher> HashContents<T, I, H> {
+    /// Create a new instance of [HashContents] that hashes the files yielded by `inner`
+    /// with `hasher`.
+    pub fn new(inner: I, hasher: H) -> Self {
+        HashContents {
+            inner,
+            hasher,
+            buf: vec![0; CHUNK_SIZE],
+        }
+    }
+
+    fn hash(&mut self, path: PathBuf) -> io::Result<(PathBuf, Digest)> {
+        let mut file = open(&path)?;
+
+        loop {
+            match file.read(&mut self.buf) {
+                Ok(0) => break,
+                Ok(n) => self.hasher.update(&self.buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // leave the hasher clean for the next file
+                    self.hasher.finish_reset();
+                    return Err(with_path(&path, e));
+                }
+            }
+        }
+
+        let digest = self.hasher.finish_reset();
+        Ok((path, digest))
+    }
+}
+
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<T, I, H> Iterator for HashContents<T, I, H>
+where
+    T: MaybeOk<Error = io::Error>,
+    I: Iterator<Item = T>,
+    H: ContentHasher,
+{
+    type Item = io::Result<(PathBuf, Digest)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next()?.into_result() {
+            Ok(item) => Some(self.hash(item.into_path_buf())),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
@@ -1,22 +1,73 @@
 //! Rust iterator adaptors useful when iterating over the file system.
 
 mod allow_extensions;
+mod cached_walk;
 mod component_filter;
+mod contents;
+mod disk_usage;
 mod entry_to_path;
 mod error_collector;
+mod faulty_fs;
+mod file_system;
+mod find_ancestors;
 mod find_dirs_with_component;
+mod find_duplicates;
+mod fixture;
+mod glob;
+mod group_by;
+mod hash_contents;
+mod mem_fs;
+mod overlay;
+mod path_item;
 mod path_reroot;
+mod poll_watcher;
 mod rdr;
+mod rename_plan;
 mod result_filter;
+mod stale_outputs;
+mod sync_plan;
+mod tar;
+mod tar_fs;
+mod top_n;
+mod transaction;
+mod traverse;
+mod tree_diff;
+mod walk_stats;
 
 pub mod ext;
+pub mod hash;
+pub mod ops;
+pub mod prelude;
 
 pub use allow_extensions::AllowExtensions;
+pub use cached_walk::CachedWalk;
 pub use component_filter::{ComponentFilter, ComponentFilterOperationType};
+pub use disk_usage::{DirUsage, DiskUsage};
 pub use entry_to_path::EntryToPath;
 pub use error_collector::ErrorCollector;
+pub use faulty_fs::{FaultyEntry, FaultyFs, FaultyReadDir};
+pub use file_system::{FileSystem, FsDirEntry, FsMetadata, StdFs};
+pub use find_ancestors::{FindAncestors, Marker};
 pub use find_dirs_with_component::FindDirsWithComponent;
-pub use path_reroot::PathReRoot;
+pub use find_duplicates::{DuplicateGroup, FindDuplicates};
+pub use fixture::{assert_listing, Fixture};
+pub use group_by::{Group, GroupBy};
+pub use hash_contents::HashContents;
+pub use mem_fs::{FsOp, MemEntry, MemFileType, MemFs, MemMetadata};
+pub use overlay::{Overlay, OverlayEntry};
+pub use path_item::{MaybeOk, PathItem};
+pub use path_reroot::{NoRuleMatches, PathReRoot, ReRootRule, Unmatched};
+pub use poll_watcher::{PollWatcher, WatchEvent};
 pub use rdr::read_dir_recursive;
 pub use rdr::ReadDirRecursive;
+pub use rename_plan::{Conflict, RenamePlan};
 pub use result_filter::ResultFilter;
+pub use stale_outputs::{Freshness, StaleOutputs, Verdict};
+pub use sync_plan::{SyncExecute, SyncOp, SyncOptions, SyncPlan};
+pub use tar::{TarEntry, TarEntryType, TarReader};
+pub use tar_fs::{TarFs, TarFsEntry, TarFsFile, TarFsMetadata, TarFsReadDir};
+pub use top_n::{KeyFn, Ranked, TopN};
+pub use transaction::{Transaction, TxError, TxOp};
+pub use traverse::{Order, Traverse};
+pub use tree_diff::{Change, Compare, DiffEntry, TreeDiff};
+pub use walk_stats::{Tracked, WalkStats};
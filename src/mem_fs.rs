@@ -0,0 +1,813 @@
+//! Export the `struct` [`MemFs`]. A [FileSystem] kept in memory, with files, directories,
+//! symlinks, permissions, modification times and errors programmed on chosen paths, that can
+//! be built from a textual description of the tree.
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    ffi::{OsStr, OsString},
+    fs::Metadata,
+    io,
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+    vec,
+};
+
+use crate::{FileSystem, FsDirEntry, FsMetadata, PathItem, TarEntryType, TarReader};
+
+/// Symlinks followed while resolving a single path before giving up.
+const MAX_LINKS: usize = 40;
+
+/// An operation of a [FileSystem], used to choose which ones fail in [MemFs::fail].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FsOp {
+    ReadDir,
+    Metadata,
+    SymlinkMetadata,
+    ReadLink,
+    Open,
+}
+
+/// The type of a file of a [MemFs].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemFileType {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// Metadata of a file of a [MemFs].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemMetadata {
+    pub file_type: MemFileType,
+    /// Length of the contents of a file, of the target of a symlink, 0 for directories.
+    pub len: u64,
+    /// Unix permission bits.
+    pub mode: u32,
+    pub modified: SystemTime,
+}
+
+impl FsMetadata for MemMetadata {
+    fn is_dir(&self) -> bool {
+        self.file_type == MemFileType::Dir
+    }
+
+    fn is_file(&self) -> bool {
+        self.file_type == MemFileType::File
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.file_type == MemFileType::Symlink
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn modified(&self) -> io::Result<SystemTime> {
+        Ok(self.modified)
+    }
+
+    fn readonly(&self) -> bool {
+        self.mode & 0o222 == 0
+    }
+
+    fn mode(&self) -> Option<u32> {
+        Some(self.mode)
+    }
+}
+
+/// An entry of a directory of a [MemFs], yielded by [MemFs::read_dir].
+#[derive(Debug, Clone)]
+pub struct MemEntry {
+    path: PathBuf,
+    name: OsString,
+    /// Metadata taken when the directory was read, or the error getting it.
+    metadata: Result<MemMetadata, io::ErrorKind>,
+}
+
+impl PathItem for MemEntry {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(&self.path)
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        self.path
+    }
+
+    /// There is no [std::fs::Metadata] for files that only exist in memory, so this always
+    /// fails with [io::ErrorKind::Unsupported]. Use [FsDirEntry::metadata] instead.
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: only exists in memory", self.path.display()),
+        ))
+    }
+}
+
+impl FsDirEntry for MemEntry {
+    type Metadata = MemMetadata;
+
+    fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    fn metadata(&self) -> io::Result<MemMetadata> {
+        self.metadata
+            .clone()
+            .map_err(|kind| error(kind, &self.path, "programmed error"))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    File(Vec<u8>),
+    Dir(BTreeMap<OsString, Node>),
+    Symlink(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: Kind,
+    mode: u32,
+    modified: SystemTime,
+}
+
+impl Node {
+    fn new(kind: Kind) -> Self {
+        let mode = match kind {
+            Kind::File(_) => 0o644,
+            Kind::Dir(_) => 0o755,
+            Kind::Symlink(_) => 0o777,
+        };
+        Node {
+            kind,
+            mode,
+            modified: UNIX_EPOCH,
+        }
+    }
+
+    fn metadata(&self) -> MemMetadata {
+        let (file_type, len) = match &self.kind {
+            Kind::File(contents) => (MemFileType::File, contents.len() as u64),
+            Kind::Dir(_) => (MemFileType::Dir, 0),
+            Kind::Symlink(target) => (MemFileType::Symlink, target.as_os_str().len() as u64),
+        };
+        MemMetadata {
+            file_type,
+            len,
+            mode: self.mode,
+            modified: self.modified,
+        }
+    }
+}
+
+/// A [FileSystem] that only exists in memory, for tests that should neither touch the disk
+/// nor depend on the order in which it lists directories.
+///
+/// Paths are relative to the root of the [MemFs]: a leading `/` and any `.` component are
+/// ignored, so `/src`, `./src` and `src` are the same directory. Entries of a directory are
+/// always listed sorted by name. Parent directories are created as needed when adding files.
+///
+/// Files are created with mode `644`, directories with `755` and everything with a
+/// modification time of [UNIX_EPOCH], until changed with [MemFs::mode] and [MemFs::modified].
+/// Directories without the read permission of the owner can not be listed, files without it
+/// can not be opened. Any operation can also be made to fail on a chosen path, see
+/// [MemFs::fail].
+///
+/// The tree can also be described as text, see [MemFs::from_tree].
+///
+/// ## Example
+/// ```
+/// use itfs::{prelude::*, MemFs, ReadDirRecursive};
+/// use std::path::PathBuf;
+///
+/// let mut fs = MemFs::new();
+/// fs.file("src/lib.rs", "pub mod a;").unwrap();
+/// fs.file("src/a.rs", "").unwrap();
+/// fs.symlink("lib", "src/lib.rs").unwrap();
+///
+/// let paths: Vec<PathBuf> = ReadDirRecursive::with_fs(".", &fs)
+///     .unwrap()
+///     .to_paths()
+///     .map(Result::unwrap)
+///     .collect();
+///
+/// assert_eq!(paths, vec![PathBuf::from("./lib"), "./src/a.rs".into(), "./src/lib.rs".into()]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemFs {
+    root: Node,
+    /// Programmed errors, by path and operation. [None] stands for every operation.
+    errors: HashMap<(Vec<OsString>, Option<FsOp>), io::ErrorKind>,
+}
+
+impl Default for MemFs {
+    fn default() -> Self {
+        MemFs::new()
+    }
+}
+
+impl MemFs {
+    /// An empty file system.
+    pub fn new() -> Self {
+        MemFs {
+            root: Node::new(Kind::Dir(BTreeMap::new())),
+            errors: HashMap::new(),
+        }
+    }
+
+    /// Build a file system from a textual description of the tree, one entry per line:
+    ///
+    /// - `name/` is a directory,
+    /// - `name` is an empty file and `name = text` a file with contents (`\n`, `\t` and `\\`
+    ///   are unescaped),
+    /// - `name -> target` is a symlink,
+    /// - attributes can follow the name: `name [mode=600 mtime=1700000000]`, with the mode in
+    ///   octal and the modification time in seconds since the epoch,
+    /// - names can have several components, parent directories are created as needed.
+    ///
+    /// ```text
+    /// src/
+    ///   lib.rs = pub mod a;
+    ///   a.rs
+    /// lib -> src/lib.rs
+    /// secret/ [mode=700]
+    /// docs/guide/intro.md
+    /// ```
+    ///
+    /// Entries indented below a directory go inside it. Empty lines and lines starting with
+    /// `#` are skipped. Fails with [io::ErrorKind::InvalidData] naming the line that could not
+    /// be understood.
+    ///
+    /// ```
+    /// use itfs::{FileSystem, MemFs};
+    /// use std::{io::Read, path::Path};
+    ///
+    /// let fs = MemFs::from_tree(
+    ///     "
+    ///     docs/
+    ///       readme.md = hello
+    ///     latest -> docs/readme.md
+    ///     ",
+    /// )
+    /// .unwrap();
+    ///
+    /// let mut contents = String::new();
+    /// fs.open(Path::new("latest")).unwrap().read_to_string(&mut contents).unwrap();
+    /// assert_eq!(contents, "hello");
+    /// ```
+    pub fn from_tree(tree: &str) -> io::Result<Self> {
+        let mut fs = MemFs::new();
+        // directories that may still get children, with their indentation
+        let mut parents: Vec<(usize, PathBuf)> = vec![];
+
+        for (n, line) in tree.lines().enumerate() {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let invalid = |why: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}: {:?}", n + 1, why, line),
+                )
+            };
+
+            let indent = line.len() - content.len();
+            while parents.last().is_some_and(|(i, _)| *i >= indent) {
+                parents.pop();
+            }
+            let parent = parents.last().map(|(_, p)| p.clone()).unwrap_or_default();
+
+            let (head, tail) = match (content.find(" -> "), content.find(" = ")) {
+                (Some(l), Some(e)) if l < e => (&content[..l], Some(("->", &content[l + 4..]))),
+                (_, Some(e)) => (&content[..e], Some(("=", &content[e + 3..]))),
+                (Some(l), None) => (&content[..l], Some(("->", &content[l + 4..]))),
+                (None, None) => (content.trim_end(), None),
+            };
+            let (name, attributes) = match head.strip_suffix(']').and_then(|h| h.rfind(" [")) {
+                Some(at) => (head[..at].trim_end(), &head[at + 2..head.len() - 1]),
+                None => (head.trim_end(), ""),
+            };
+            if name.is_empty() || name == "/" {
+                return Err(invalid("missing name"));
+            }
+            let path = parent.join(name.trim_end_matches('/'));
+
+            match (name.ends_with('/'), tail) {
+                (true, None) => {
+                    fs.dir(&path)?;
+                    parents.push((indent, path.clone()));
+                }
+                (false, None) => {
+                    fs.file(&path, "")?;
+                }
+                (false, Some(("=", contents))) => {
+                    fs.file(&path, unescape(contents))?;
+                }
+                (false, Some((_, target))) => {
+                    fs.symlink(&path, target.trim())?;
+                }
+                (true, Some(_)) => return Err(invalid("directories have no contents")),
+            }
+
+            for attribute in attributes.split_whitespace() {
+                match attribute.split_once('=') {
+                    Some(("mode", mode)) => {
+                        let mode = u32::from_str_radix(mode, 8).map_err(|_| invalid("bad mode"))?;
+                        fs.mode(&path, mode)?;
+                    }
+                    Some(("mtime", secs)) => {
+                        let secs: u64 = secs.parse().map_err(|_| invalid("bad mtime"))?;
+                        fs.modified(&path, UNIX_EPOCH + Duration::from_secs(secs))?;
+                    }
+                    _ => return Err(invalid("unknown attribute")),
+                }
+            }
+        }
+
+        Ok(fs)
+    }
+
+    /// Build a file system with the members of a tar archive, read with [TarReader]. Hard
+    /// links become copies of the member they point to. Devices, fifos and members with `..`
+    /// in their path are left out. A member that appears twice replaces the earlier one.
+    pub fn from_tar<R: io::Read>(reader: R) -> io::Result<Self> {
+        let mut fs = MemFs::new();
+
+        for entry in TarReader::new(reader).contents(true) {
+            let entry = entry?;
+            let path = &entry.path;
+            if path.components().any(|c| c == Component::ParentDir) {
+                continue;
+            }
+
+            match (entry.entry_type, entry.link_name) {
+                (TarEntryType::File, _) => {
+                    fs.file(path, entry.contents.unwrap_or_default())?;
+                }
+                (TarEntryType::Dir, _) => {
+                    fs.dir(path)?;
+                }
+                (TarEntryType::Symlink, Some(target)) => {
+                    fs.symlink(path, target)?;
+                }
+                (TarEntryType::HardLink, Some(target)) => {
+                    let mut contents = vec![];
+                    io::Read::read_to_end(&mut fs.open(&target)?, &mut contents)?;
+                    fs.file(path, contents)?;
+                }
+                _ => continue,
+            }
+            fs.mode(path, entry.mode)?;
+            fs.modified(path, entry.modified)?;
+        }
+
+        Ok(fs)
+    }
+
+    /// Create a file with the given contents, or replace the contents of an existing one.
+    pub fn file<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &mut self,
+        path: P,
+        contents: C,
+    ) -> io::Result<&mut Self> {
+        let contents = contents.as_ref().to_vec();
+        match self.node_mut(path.as_ref()) {
+            // keep the mode and modification time of an existing file
+            Ok(Node {
+                kind: Kind::File(old),
+                ..
+            }) => *old = contents,
+            _ => self.insert(path.as_ref(), Node::new(Kind::File(contents)))?,
+        }
+        Ok(self)
+    }
+
+    /// Create a directory, along with its parents. Existing directories are left as they are.
+    pub fn dir<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut Self> {
+        let steps = steps(path.as_ref());
+        self.dir_mut(&steps, path.as_ref())?;
+        Ok(self)
+    }
+
+    /// Create a symlink pointing to `target`. Relative targets are relative to the directory
+    /// of the symlink, absolute ones to the root of the [MemFs].
+    pub fn symlink<P: AsRef<Path>, T: AsRef<Path>>(
+        &mut self,
+        path: P,
+        target: T,
+    ) -> io::Result<&mut Self> {
+        let node = Node::new(Kind::Symlink(target.as_ref().to_path_buf()));
+        self.insert(path.as_ref(), node)?;
+        Ok(self)
+    }
+
+    /// Set the unix permission bits of a file. Symlinks are not followed.
+    pub fn mode<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> io::Result<&mut Self> {
+        self.node_mut(path.as_ref())?.mode = mode;
+        Ok(self)
+    }
+
+    /// Set the modification time of a file. Symlinks are not followed.
+    pub fn modified<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        modified: SystemTime,
+    ) -> io::Result<&mut Self> {
+        self.node_mut(path.as_ref())?.modified = modified;
+        Ok(self)
+    }
+
+    /// Make the operation `op` fail with an error of the given kind whenever it is called
+    /// with `path`, which does not need to exist. Listing the parent of `path` still works,
+    /// but the metadata of its entry fails when the operation is [FsOp::SymlinkMetadata].
+    pub fn fail<P: AsRef<Path>>(&mut self, path: P, op: FsOp, kind: io::ErrorKind) -> &mut Self {
+        self.errors.insert((steps(path.as_ref()), Some(op)), kind);
+        self
+    }
+
+    /// Make every operation fail with an error of the given kind whenever it is called with
+    /// `path`. See [MemFs::fail].
+    pub fn fail_all<P: AsRef<Path>>(&mut self, path: P, kind: io::ErrorKind) -> &mut Self {
+        self.errors.insert((steps(path.as_ref()), None), kind);
+        self
+    }
+
+    /// Remove a file, a symlink or a whole directory.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut Self> {
+        let path = path.as_ref();
+        let mut steps = steps(path);
+        let name = steps.pop().ok_or_else(|| error_invalid(path))?;
+        match self.dir_at(&steps, path)?.remove(&name) {
+            Some(_) => Ok(self),
+            None => Err(error(io::ErrorKind::NotFound, path, "not found")),
+        }
+    }
+
+    /// A copy without the programmed errors.
+    pub(crate) fn without_errors(&self) -> MemFs {
+        MemFs {
+            root: self.root.clone(),
+            errors: HashMap::new(),
+        }
+    }
+
+    /// A copy without the programmed errors and with every file readable, writable and
+    /// searchable by its owner, to read all of the tree whatever its permissions.
+    pub(crate) fn unrestricted(&self) -> MemFs {
+        fn grant(node: &mut Node) {
+            node.mode |= 0o700;
+            if let Kind::Dir(entries) = &mut node.kind {
+                entries.values_mut().for_each(grant);
+            }
+        }
+
+        let mut fs = self.without_errors();
+        grant(&mut fs.root);
+        fs
+    }
+
+    /// The programmed error for `op` on `path`, if any.
+    fn programmed(&self, op: FsOp, path: &Path) -> io::Result<()> {
+        let steps = steps(path);
+        let kind = self
+            .errors
+            .get(&(steps.clone(), Some(op)))
+            .or_else(|| self.errors.get(&(steps, None)));
+        match kind {
+            Some(kind) => Err(error(*kind, path, "programmed error")),
+            None => Ok(()),
+        }
+    }
+
+    /// Find the node of `path`, following symlinks on the way, and the last component too
+    /// if `follow` is `true`.
+    fn lookup(&self, path: &Path, follow: bool) -> io::Result<&Node> {
+        let mut pending: VecDeque<OsString> = steps(path).into();
+        let mut resolved: Vec<OsString> = vec![];
+        let mut links = 0;
+
+        while let Some(step) = pending.pop_front() {
+            if step == ".." {
+                resolved.pop();
+                continue;
+            }
+
+            let node = self.child(&resolved, &step, path)?;
+            resolved.push(step);
+
+            if let Kind::Symlink(target) = &node.kind {
+                if !pending.is_empty() || follow {
+                    links += 1;
+                    if links > MAX_LINKS {
+                        return Err(error(
+                            io::ErrorKind::InvalidInput,
+                            path,
+                            "too many levels of symbolic links",
+                        ));
+                    }
+                    resolved.pop();
+                    if target.has_root() {
+                        resolved.clear();
+                    }
+                    for step in steps(target).into_iter().rev() {
+                        pending.push_front(step);
+                    }
+                }
+            }
+        }
+
+        self.walk(&resolved, path)
+    }
+
+    /// The entry `name` of the directory at `dir`, which has no symlinks.
+    fn child(&self, dir: &[OsString], name: &OsStr, path: &Path) -> io::Result<&Node> {
+        match &self.walk(dir, path)?.kind {
+            Kind::Dir(entries) => entries
+                .get(name)
+                .ok_or_else(|| error(io::ErrorKind::NotFound, path, "not found")),
+            _ => Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+        }
+    }
+
+    /// The node at `steps`, which have no symlinks.
+    fn walk(&self, steps: &[OsString], path: &Path) -> io::Result<&Node> {
+        let mut node = &self.root;
+        for step in steps {
+            node = match &node.kind {
+                Kind::Dir(entries) => entries
+                    .get(step)
+                    .ok_or_else(|| error(io::ErrorKind::NotFound, path, "not found"))?,
+                _ => return Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+            };
+        }
+        Ok(node)
+    }
+
+    /// The node of `path`, without following symlinks.
+    fn node_mut(&mut self, path: &Path) -> io::Result<&mut Node> {
+        let mut node = &mut self.root;
+        for step in steps(path) {
+            node = match &mut node.kind {
+                Kind::Dir(entries) => entries
+                    .get_mut(&step)
+                    .ok_or_else(|| error(io::ErrorKind::NotFound, path, "not found"))?,
+                _ => return Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+            };
+        }
+        Ok(node)
+    }
+
+    /// The entries of the directory at `steps`, which must exist.
+    fn dir_at(
+        &mut self,
+        steps: &[OsString],
+        path: &Path,
+    ) -> io::Result<&mut BTreeMap<OsString, Node>> {
+        match &mut self.node_mut(&steps.iter().collect::<PathBuf>())?.kind {
+            Kind::Dir(entries) => Ok(entries),
+            _ => Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+        }
+    }
+
+    /// The entries of the directory at `steps`, creating it and its parents if needed.
+    fn dir_mut(
+        &mut self,
+        steps: &[OsString],
+        path: &Path,
+    ) -> io::Result<&mut BTreeMap<OsString, Node>> {
+        let mut entries = match &mut self.root.kind {
+            Kind::Dir(entries) => entries,
+            _ => unreachable!("the root is always a directory"),
+        };
+        for step in steps {
+            let node = entries
+                .entry(step.clone())
+                .or_insert_with(|| Node::new(Kind::Dir(BTreeMap::new())));
+            entries = match &mut node.kind {
+                Kind::Dir(entries) => entries,
+                _ => return Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+            };
+        }
+        Ok(entries)
+    }
+
+    /// Put `node` at `path`, replacing whatever was there.
+    fn insert(&mut self, path: &Path, node: Node) -> io::Result<()> {
+        let mut steps = steps(path);
+        let name = steps.pop().ok_or_else(|| error_invalid(path))?;
+        self.dir_mut(&steps, path)?.insert(name, node);
+        Ok(())
+    }
+}
+
+impl FileSystem for MemFs {
+    type Metadata = MemMetadata;
+    type DirEntry = MemEntry;
+    type ReadDir = vec::IntoIter<io::Result<MemEntry>>;
+    type File = io::Cursor<Vec<u8>>;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir> {
+        self.programmed(FsOp::ReadDir, path)?;
+        let node = self.lookup(path, true)?;
+        let entries = match &node.kind {
+            Kind::Dir(entries) => entries,
+            _ => return Err(error(io::ErrorKind::NotADirectory, path, "not a directory")),
+        };
+        if node.mode & 0o400 == 0 {
+            return Err(error(
+                io::ErrorKind::PermissionDenied,
+                path,
+                "permission denied",
+            ));
+        }
+
+        let entries: Vec<io::Result<MemEntry>> = entries
+            .iter()
+            .map(|(name, node)| {
+                let path = path.join(name);
+                let metadata = match self.programmed(FsOp::SymlinkMetadata, &path) {
+                    Ok(()) => Ok(node.metadata()),
+                    Err(e) => Err(e.kind()),
+                };
+                Ok(MemEntry {
+                    path,
+                    name: name.clone(),
+                    metadata,
+                })
+            })
+            .collect();
+        Ok(entries.into_iter())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<MemMetadata> {
+        self.programmed(FsOp::Metadata, path)?;
+        Ok(self.lookup(path, true)?.metadata())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<MemMetadata> {
+        self.programmed(FsOp::SymlinkMetadata, path)?;
+        Ok(self.lookup(path, false)?.metadata())
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.programmed(FsOp::ReadLink, path)?;
+        match &self.lookup(path, false)?.kind {
+            Kind::Symlink(target) => Ok(target.clone()),
+            _ => Err(error(io::ErrorKind::InvalidInput, path, "not a symlink")),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Self::File> {
+        self.programmed(FsOp::Open, path)?;
+        let node = self.lookup(path, true)?;
+        match &node.kind {
+            Kind::File(_) if node.mode & 0o400 == 0 => Err(error(
+                io::ErrorKind::PermissionDenied,
+                path,
+                "permission denied",
+            )),
+            Kind::File(contents) => Ok(io::Cursor::new(contents.clone())),
+            _ => Err(error(io::ErrorKind::IsADirectory, path, "is a directory")),
+        }
+    }
+}
+
+/// The components of `path` that lead somewhere from the root: without the root, the
+/// prefix and `.` components. `..` is kept, to be resolved along with symlinks.
+fn steps(path: &Path) -> Vec<OsString> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            _ => None,
+        })
+        .collect()
+}
+
+fn error(kind: io::ErrorKind, path: &Path, message: &str) -> io::Error {
+    io::Error::new(kind, format!("{}: {}", path.display(), message))
+}
+
+fn error_invalid(path: &Path) -> io::Error {
+    error(io::ErrorKind::InvalidInput, path, "not a file name")
+}
+
+/// Undo the escapes allowed in the contents of the files of [MemFs::from_tree].
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => out.push('\n'),
+            ('\\', Some('t')) => out.push('\t'),
+            ('\\', Some('\\')) => out.push('\\'),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, Read},
+        path::{Path, PathBuf},
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{FsOp, MemFs};
+    use crate::{EntryToPath, FileSystem, FsMetadata, ReadDirRecursive};
+
+    fn walk(fs: &MemFs, root: &str) -> Vec<Result<PathBuf, io::ErrorKind>> {
+        EntryToPath(ReadDirRecursive::with_fs(root, fs).unwrap())
+            .map(|r| r.map_err(|e| e.kind()))
+            .collect()
+    }
+
+    #[test]
+    fn from_tree() {
+        let fs = MemFs::from_tree(
+            "
+            # comment
+            src/
+              lib.rs = line\\none
+              bin/ [mode=700 mtime=60]
+                tool.rs
+            docs/a/b.md
+            link -> src/lib.rs
+            loop -> loop
+            ",
+        )
+        .unwrap();
+
+        let read = |path: &str| {
+            let mut s = String::new();
+            fs.open(Path::new(path))?.read_to_string(&mut s)?;
+            Ok::<_, io::Error>(s)
+        };
+        assert_eq!(read("src/lib.rs").unwrap(), "line\none");
+        assert_eq!(read("./link").unwrap(), "line\none");
+        assert_eq!(
+            fs.read_link(Path::new("link")).unwrap(),
+            Path::new("src/lib.rs")
+        );
+        assert!(fs.symlink_metadata(Path::new("link")).unwrap().is_symlink());
+        assert!(fs.metadata(Path::new("loop")).is_err());
+
+        let bin = fs.metadata(Path::new("/src/bin")).unwrap();
+        assert_eq!(bin.mode, 0o700);
+        assert_eq!(bin.modified, UNIX_EPOCH + Duration::from_secs(60));
+
+        let expected: Vec<Result<PathBuf, io::ErrorKind>> = [
+            "./link",
+            "./loop",
+            "./src/lib.rs",
+            "./src/bin/tool.rs",
+            "./docs/a/b.md",
+        ]
+        .iter()
+        .map(|p| Ok(PathBuf::from(p)))
+        .collect();
+        assert_eq!(walk(&fs, "."), expected);
+
+        assert!(MemFs::from_tree("a/ = x").is_err());
+        assert!(MemFs::from_tree("a [size=1]").is_err());
+    }
+
+    #[test]
+    fn errors_and_permissions() {
+        let mut fs = MemFs::from_tree("a/x\nb/y\nc/z\nsecret [mode=200]").unwrap();
+        fs.mode("b", 0o300).unwrap();
+        fs.fail("c/z", FsOp::SymlinkMetadata, io::ErrorKind::Other);
+
+        assert_eq!(
+            walk(&fs, ""),
+            vec![
+                Ok(PathBuf::from("secret")),
+                Err(io::ErrorKind::Other),
+                Err(io::ErrorKind::PermissionDenied),
+                Ok(PathBuf::from("a/x")),
+            ]
+        );
+        assert_eq!(
+            fs.open(Path::new("secret")).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        fs.fail_all("a", io::ErrorKind::TimedOut);
+        assert_eq!(
+            fs.metadata(Path::new("a")).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+        assert!(fs.metadata(Path::new("a/x")).is_ok());
+
+        fs.remove("a").unwrap();
+        assert!(fs.metadata(Path::new("a/x")).is_err());
+    }
+}
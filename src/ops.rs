@@ -0,0 +1,745 @@
+//! Recursive filesystem operations (copy, move, delete and change permissions) driven by the
+//! output of a walker.
+//!
+//! Each operation is an iterator adaptor over items of type `Result<DirEntry>`, usually coming
+//! from [Traverse] (possibly narrowed down with other adaptors of this crate), that performs
+//! the operation one entry at a time. They all yield items of type `Result<PathBuf, OpError>`,
+//! where the [PathBuf] is the path that was acted upon, so failures can be gathered with
+//! [ErrorCollector][crate::ErrorCollector] while the rest of the entries keep being processed.
+//!
+//! The `*_recursive` functions build the whole pipeline for a directory, root included.
+//!
+//! Symlinks are treated as entries of their own: they are removed, copied or moved, never
+//! what they point to. On top of that, an entry is refused (with an [OpError]) if any of the
+//! directories between the root and the entry is a symlink, so paths coming from a walker
+//! that followed symlinks can not make an operation escape the tree. Both behaviors can be
+//! lifted with [OpOptions::follow_symlinks].
+//!
+//! With [OpOptions::dry_run] nothing is touched, but every entry is still yielded as if it
+//! was processed.
+//!
+//! ## Example
+//! ```
+//! use itfs::{ops, ErrorCollector};
+//!
+//! let mut errors = vec![];
+//! let copy = ops::copy_recursive("./src", "./target/ops-doc", ops::OpOptions::dry_run()).unwrap();
+//!
+//! for copied in ErrorCollector(copy, &mut errors) {
+//!     println!("would copy {:?}", copied);
+//! }
+//!
+//! assert!(errors.is_empty());
+//! ```
+
+use std::{
+    error, fmt,
+    fs::{self, DirEntry},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{path_reroot::path_re_root, Traverse};
+
+/// Options shared by every operation of this module.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpOptions {
+    /// Do not touch anything, only yield what would be done.
+    pub dry_run: bool,
+    /// Follow symlinks instead of treating them as entries of their own, and accept entries
+    /// reached through a symlinked directory.
+    pub follow_symlinks: bool,
+}
+
+impl OpOptions {
+    /// Options with [OpOptions::dry_run] set.
+    pub fn dry_run() -> Self {
+        OpOptions {
+            dry_run: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Failure of an operation on a given path.
+#[derive(Debug)]
+pub struct OpError {
+    /// The path that could not be processed. For errors coming from the inner iterator,
+    /// which do not say what failed, this is the root of the operation.
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl error::Error for OpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Checks that no directory between a root and an entry is a symlink.
+struct SymlinkGuard {
+    root: PathBuf,
+    enabled: bool,
+    /// Last parent directory found to be safe. Walkers yield siblings one after the other,
+    /// so this saves most of the checks.
+    verified: Option<PathBuf>,
+}
+
+impl SymlinkGuard {
+    fn new(root: &Path, options: &OpOptions) -> Self {
+        SymlinkGuard {
+            root: root.to_path_buf(),
+            enabled: !options.follow_symlinks,
+            verified: None,
+        }
+    }
+
+    fn check(&mut self, path: &Path) -> io::Result<()> {
+        let parent = match path.parent() {
+            Some(parent) if self.enabled => parent,
+            _ => return Ok(()),
+        };
+        if self.verified.as_deref() == Some(parent) {
+            return Ok(());
+        }
+
+        let mut dir = parent;
+        while dir != self.root && dir.starts_with(&self.root) {
+            if fs::symlink_metadata(dir)?.is_symlink() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("refusing to cross the symlink {}", dir.display()),
+                ));
+            }
+            dir = match dir.parent() {
+                Some(dir) => dir,
+                None => break,
+            };
+        }
+
+        self.verified = Some(parent.to_path_buf());
+        Ok(())
+    }
+}
+
+/// Map a path under `from` onto `to`.
+fn re_root(path: &Path, from: &Path, to: &Path) -> io::Result<PathBuf> {
+    path_re_root(path, from, to).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not under {}: {}", path.display(), from.display(), e),
+        )
+    })
+}
+
+/// Wrap the result of processing `path` into the item yielded by the adaptors.
+fn item(path: PathBuf, result: io::Result<PathBuf>) -> Result<PathBuf, OpError> {
+    result.map_err(|error| OpError { path, error })
+}
+
+/// Removes every entry yielded by the inner iterator. Directories are removed with
+/// [fs::remove_dir], so they must be empty by the time they come: use a post-order walk.
+pub struct RemoveEntries<I: Iterator<Item = io::Result<DirEntry>>> {
+    inner: I,
+    options: OpOptions,
+    guard: SymlinkGuard,
+    /// Also remove the root once the inner iterator is exhausted.
+    remove_root: bool,
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> RemoveEntries<I> {
+    pub fn new<P: AsRef<Path>>(inner: I, root: P, options: OpOptions) -> Self {
+        RemoveEntries {
+            inner,
+            options,
+            guard: SymlinkGuard::new(root.as_ref(), &options),
+            remove_root: false,
+        }
+    }
+
+    fn remove(&mut self, path: &Path) -> io::Result<PathBuf> {
+        self.guard.check(path)?;
+        if !self.options.dry_run {
+            match fs::symlink_metadata(path)?.is_dir() {
+                true => fs::remove_dir(path)?,
+                false => fs::remove_file(path)?,
+            }
+        }
+        Ok(path.to_path_buf())
+    }
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> Iterator for RemoveEntries<I> {
+    type Item = Result<PathBuf, OpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next() {
+            Some(Ok(entry)) => {
+                let path = entry.path();
+                let result = self.remove(&path);
+                Some(item(path, result))
+            }
+            Some(Err(error)) => Some(Err(OpError {
+                path: self.guard.root.clone(),
+                error,
+            })),
+            None if self.remove_root => {
+                self.remove_root = false;
+                let root = self.guard.root.clone();
+                let result = match self.options.dry_run {
+                    true => Ok(root.clone()),
+                    false => fs::remove_dir(&root).map(|_| root.clone()),
+                };
+                Some(item(root, result))
+            }
+            None => None,
+        }
+    }
+}
+
+/// Copies every entry yielded by the inner iterator from under the `from` root to the same
+/// relative path under the `to` root (see [PathReRoot][crate::PathReRoot]). Missing parent
+/// directories are created as needed, with default permissions, so the entries may come in
+/// any order. Files keep their permissions and modification time.
+pub struct CopyEntries<I: Iterator<Item = io::Result<DirEntry>>> {
+    inner: I,
+    to: PathBuf,
+    options: OpOptions,
+    guard: SymlinkGuard,
+    /// Create the `to` root before anything else.
+    create_root: bool,
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> CopyEntries<I> {
+    pub fn new<F: AsRef<Path>, T: AsRef<Path>>(
+        inner: I,
+        from: F,
+        to: T,
+        options: OpOptions,
+    ) -> Self {
+        CopyEntries {
+            inner,
+            to: to.as_ref().to_path_buf(),
+            options,
+            guard: SymlinkGuard::new(from.as_ref(), &options),
+            create_root: false,
+        }
+    }
+
+    fn copy(&mut self, path: &Path) -> io::Result<PathBuf> {
+        self.guard.check(path)?;
+        let dest = re_root(path, &self.guard.root, &self.to)?;
+        if self.options.dry_run {
+            return Ok(dest);
+        }
+
+        let meta = match self.options.follow_symlinks {
+            true => fs::metadata(path)?,
+            false => fs::symlink_metadata(path)?,
+        };
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if meta.is_dir() {
+            match fs::create_dir(&dest) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                _ => {}
+            }
+        } else if meta.is_symlink() {
+            symlink(&fs::read_link(path)?, &dest)?;
+        } else {
+            copy_file(path, &dest, &meta)?;
+        }
+
+        Ok(dest)
+    }
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> Iterator for CopyEntries<I> {
+    type Item = Result<PathBuf, OpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.create_root {
+            self.create_root = false;
+            if !self.options.dry_run {
+                if let Err(error) = fs::create_dir_all(&self.to) {
+                    return Some(Err(OpError {
+                        path: self.to.clone(),
+                        error,
+                    }));
+                }
+            }
+        }
+
+        match self.inner.next()? {
+            Ok(entry) => {
+                let path = entry.path();
+                let result = self.copy(&path);
+                Some(item(path, result))
+            }
+            Err(error) => Some(Err(OpError {
+                path: self.guard.root.clone(),
+                error,
+            })),
+        }
+    }
+}
+
+/// Moves every entry yielded by the inner iterator from under the `from` root to the same
+/// relative path under the `to` root. Files and symlinks are renamed, or copied and removed
+/// when the destination is on another filesystem. Directories are created in the destination
+/// and removed from the source once empty, so use a post-order walk.
+pub struct MoveEntries<I: Iterator<Item = io::Result<DirEntry>>> {
+    inner: I,
+    to: PathBuf,
+    options: OpOptions,
+    guard: SymlinkGuard,
+    /// Try to rename the whole root at once before going entry by entry.
+    rename_root: bool,
+    /// Move the root itself once the inner iterator is exhausted.
+    move_root: bool,
+    /// Set once the whole root was renamed, or the attempt failed.
+    done: bool,
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> MoveEntries<I> {
+    pub fn new<F: AsRef<Path>, T: AsRef<Path>>(
+        inner: I,
+        from: F,
+        to: T,
+        options: OpOptions,
+    ) -> Self {
+        MoveEntries {
+            inner,
+            to: to.as_ref().to_path_buf(),
+            options,
+            guard: SymlinkGuard::new(from.as_ref(), &options),
+            rename_root: false,
+            move_root: false,
+            done: false,
+        }
+    }
+
+    fn move_entry(&mut self, path: &Path) -> io::Result<PathBuf> {
+        self.guard.check(path)?;
+        let dest = re_root(path, &self.guard.root, &self.to)?;
+        if self.options.dry_run {
+            return Ok(dest);
+        }
+
+        let meta = fs::symlink_metadata(path)?;
+        if meta.is_dir() {
+            // its content is already gone (post-order)
+            fs::create_dir_all(&dest)?;
+            fs::set_permissions(&dest, meta.permissions())?;
+            fs::remove_dir(path)?;
+        } else {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match fs::rename(path, &dest) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                    copy_entry(path, &dest)?;
+                    fs::remove_file(path)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(dest)
+    }
+
+    /// Move the root itself once its content is gone.
+    fn move_root(&mut self) -> io::Result<PathBuf> {
+        let root = self.guard.root.clone();
+        if !self.options.dry_run {
+            let meta = fs::symlink_metadata(&root)?;
+            fs::create_dir_all(&self.to)?;
+            fs::set_permissions(&self.to, meta.permissions())?;
+            fs::remove_dir(&root)?;
+        }
+        Ok(self.to.clone())
+    }
+}
+
+impl<I: Iterator<Item = io::Result<DirEntry>>> Iterator for MoveEntries<I> {
+    type Item = Result<PathBuf, OpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.rename_root && !self.options.dry_run {
+            self.rename_root = false;
+            let root = self.guard.root.clone();
+            match fs::rename(&root, &self.to) {
+                Ok(()) => {
+                    self.done = true;
+                    return Some(Ok(self.to.clone()));
+                }
+                // fall back to moving entry by entry
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(OpError { path: root, error }));
+                }
+            }
+        }
+
+        match self.inner.next() {
+            Some(Ok(entry)) => {
+                let path = entry.path();
+                let result = self.move_entry(&path);
+                Some(item(path, result))
+            }
+            Some(Err(error)) => Some(Err(OpError {
+                path: self.guard.root.clone(),
+                error,
+            })),
+            None if self.move_root => {
+                self.done = true;
+                let result = self.move_root();
+                Some(item(self.guard.root.clone(), result))
+            }
+            None => None,
+        }
+    }
+}
+
+/// Changes the permissions of every entry yielded by the inner iterator. The closure receives
+/// the path and metadata of each entry and returns the new permissions, or [None] to leave
+/// them as they are. Only the entries that are changed are yielded.
+///
+/// Symlinks are skipped, since changing their permissions would change those of their
+/// target, unless [OpOptions::follow_symlinks] is set.
+pub struct SetPermissions<I, F>
+where
+    I: Iterator<Item = io::Result<DirEntry>>,
+    F: FnMut(&Path, &fs::Metadata) -> Option<fs::Permissions>,
+{
+    inner: I,
+    permissions: F,
+    options: OpOptions,
+    guard: SymlinkGuard,
+    /// Also process the root, before anything else.
+    include_root: bool,
+}
+
+impl<I, F> SetPermissions<I, F>
+where
+    I: Iterator<Item = io::Result<DirEntry>>,
+    F: FnMut(&Path, &fs::Metadata) -> Option<fs::Permissions>,
+{
+    pub fn new<P: AsRef<Path>>(inner: I, root: P, permissions: F, options: OpOptions) -> Self {
+        SetPermissions {
+            inner,
+            permissions,
+            options,
+            guard: SymlinkGuard::new(root.as_ref(), &options),
+            include_root: false,
+        }
+    }
+
+    /// Returns [None] if the entry was left untouched.
+    fn set(&mut self, path: &Path) -> io::Result<Option<PathBuf>> {
+        self.guard.check(path)?;
+        let meta = match self.options.follow_symlinks {
+            true => fs::metadata(path)?,
+            false => fs::symlink_metadata(path)?,
+        };
+        if meta.is_symlink() {
+            return Ok(None);
+        }
+
+        match (self.permissions)(path, &meta) {
+            Some(permissions) if permissions != meta.permissions() => {
+                if !self.options.dry_run {
+                    fs::set_permissions(path, permissions)?;
+                }
+                Ok(Some(path.to_path_buf()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<I, F> Iterator for SetPermissions<I, F>
+where
+    I: Iterator<Item = io::Result<DirEntry>>,
+    F: FnMut(&Path, &fs::Metadata) -> Option<fs::Permissions>,
+{
+    type Item = Result<PathBuf, OpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = match self.include_root {
+                true => {
+                    self.include_root = false;
+                    self.guard.root.clone()
+                }
+                false => match self.inner.next()? {
+                    Ok(entry) => entry.path(),
+                    Err(error) => {
+                        break Some(Err(OpError {
+                            path: self.guard.root.clone(),
+                            error,
+                        }))
+                    }
+                },
+            };
+
+            match self.set(&path) {
+                Ok(Some(path)) => break Some(Ok(path)),
+                Ok(None) => continue,
+                Err(error) => break Some(Err(OpError { path, error })),
+            }
+        }
+    }
+}
+
+/// Start a walk of `root` for one of the `*_recursive` functions.
+fn traverse(root: &Path, options: &OpOptions) -> Result<Traverse, OpError> {
+    let op_error = |error| OpError {
+        path: root.to_path_buf(),
+        error,
+    };
+
+    // the walk would go through the symlink
+    if !options.follow_symlinks && fs::symlink_metadata(root).map_err(op_error)?.is_symlink() {
+        return Err(op_error(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "refusing to cross a symlink",
+        )));
+    }
+
+    let traverse = Traverse::new(root).map_err(op_error)?;
+    Ok(match options.follow_symlinks {
+        true => traverse.follow_symlinks(),
+        false => traverse,
+    })
+}
+
+/// `rm -r`: remove a directory and all its content, children before their parents.
+pub fn remove_recursive<P: AsRef<Path>>(
+    root: P,
+    options: OpOptions,
+) -> Result<RemoveEntries<Traverse>, OpError> {
+    let root = root.as_ref();
+    let mut remove = RemoveEntries::new(traverse(root, &options)?.post_order(), root, options);
+    remove.remove_root = true;
+    Ok(remove)
+}
+
+/// `cp -r`: copy a directory and all its content to `to`, which is created if needed.
+pub fn copy_recursive<F: AsRef<Path>, T: AsRef<Path>>(
+    from: F,
+    to: T,
+    options: OpOptions,
+) -> Result<CopyEntries<Traverse>, OpError> {
+    let from = from.as_ref();
+    let mut copy = CopyEntries::new(traverse(from, &options)?, from, to, options);
+    copy.create_root = true;
+    Ok(copy)
+}
+
+/// `mv`: move a directory and all its content to `to`. The whole directory is renamed at once
+/// when possible; when `to` is on another filesystem the content is moved entry by entry.
+pub fn move_recursive<F: AsRef<Path>, T: AsRef<Path>>(
+    from: F,
+    to: T,
+    options: OpOptions,
+) -> Result<MoveEntries<Traverse>, OpError> {
+    let from = from.as_ref();
+    let traverse = traverse(from, &options)?.post_order();
+    let mut mv = MoveEntries::new(traverse, from, to, options);
+    mv.rename_root = true;
+    mv.move_root = true;
+    Ok(mv)
+}
+
+/// `chmod -R`: change the permissions of a directory and all its content.
+/// See [SetPermissions].
+pub fn set_permissions_recursive<P, F>(
+    root: P,
+    permissions: F,
+    options: OpOptions,
+) -> Result<SetPermissions<Traverse, F>, OpError>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, &fs::Metadata) -> Option<fs::Permissions>,
+{
+    let root = root.as_ref();
+    let mut set = SetPermissions::new(traverse(root, &options)?, root, permissions, options);
+    set.include_root = true;
+    Ok(set)
+}
+
+/// Copy a file's contents, modification time and permissions.
+pub(crate) fn copy_file(from: &Path, to: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    let mut file = fs::File::create(to)?;
+    io::copy(&mut fs::File::open(from)?, &mut file)?;
+    // keep the modification time so a later comparison by mtime sees them as equal.
+    // Permissions go last, they could make the copy read only.
+    file.set_modified(meta.modified()?)?;
+    drop(file);
+    fs::set_permissions(to, meta.permissions())
+}
+
+/// Copy a file or a symlink (as a symlink).
+pub(crate) fn copy_entry(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    match meta.is_symlink() {
+        true => symlink(&fs::read_link(from)?, to),
+        false => copy_file(from, to, &meta),
+    }
+}
+
+/// Remove a file, a symlink or a directory with all its content. Symlinks are never followed.
+pub(crate) fn remove_entry(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path)?.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+pub(crate) fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let dir = link
+        .parent()
+        .map(|p| p.join(target))
+        .is_some_and(|t| t.is_dir());
+    match dir {
+        true => std::os::windows::fs::symlink_dir(target, link),
+        false => std::os::windows::fs::symlink_file(target, link),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{copy_recursive, move_recursive, remove_recursive, OpOptions};
+    use crate::ErrorCollector;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("itfs-ops-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src/a/b")).unwrap();
+        fs::write(dir.join("src/top.txt"), "top").unwrap();
+        fs::write(dir.join("src/a/b/deep.txt"), "deep").unwrap();
+        dir
+    }
+
+    #[test]
+    fn copy_move_remove() {
+        let dir = scratch("copy-move-remove");
+        let mut errors = vec![];
+
+        // dry run does not touch anything
+        let copy = copy_recursive(dir.join("src"), dir.join("copy"), OpOptions::dry_run());
+        assert_eq!(ErrorCollector(copy.unwrap(), &mut errors).count(), 4);
+        assert!(!dir.join("copy").exists());
+
+        let copy = copy_recursive(dir.join("src"), dir.join("copy"), OpOptions::default());
+        assert_eq!(ErrorCollector(copy.unwrap(), &mut errors).count(), 4);
+        assert_eq!(
+            fs::read_to_string(dir.join("copy/a/b/deep.txt")).unwrap(),
+            "deep"
+        );
+
+        let mv = move_recursive(dir.join("copy"), dir.join("moved"), OpOptions::default());
+        ErrorCollector(mv.unwrap(), &mut errors).for_each(drop);
+        assert!(!dir.join("copy").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("moved/top.txt")).unwrap(),
+            "top"
+        );
+
+        let remove = remove_recursive(dir.join("moved"), OpOptions::default());
+        // 4 entries plus the root itself
+        assert_eq!(ErrorCollector(remove.unwrap(), &mut errors).count(), 5);
+        assert!(!dir.join("moved").exists());
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_cross_symlinks() {
+        use std::{io, os::unix::fs::PermissionsExt};
+
+        use super::{set_permissions_recursive, RemoveEntries};
+
+        let dir = scratch("symlinks");
+        std::os::unix::fs::symlink(dir.join("src"), dir.join("link")).unwrap();
+
+        assert!(remove_recursive(dir.join("link"), OpOptions::default()).is_err());
+        assert!(dir.join("src/a/b/deep.txt").exists());
+
+        // a symlinked directory in the middle of the tree is handled as a link, never entered
+        fs::create_dir(dir.join("outside")).unwrap();
+        fs::write(dir.join("outside/keep.txt"), "keep").unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), dir.join("src/a/inner")).unwrap();
+        let mut errors = vec![];
+
+        let copy = copy_recursive(dir.join("src"), dir.join("copy"), OpOptions::default());
+        ErrorCollector(copy.unwrap(), &mut errors).for_each(drop);
+        let copied = fs::symlink_metadata(dir.join("copy/a/inner")).unwrap();
+        assert!(copied.is_symlink());
+
+        let chmod = set_permissions_recursive(
+            dir.join("src"),
+            |_, _| Some(fs::Permissions::from_mode(0o700)),
+            OpOptions::default(),
+        );
+        ErrorCollector(chmod.unwrap(), &mut errors).for_each(drop);
+        let mode = fs::metadata(dir.join("outside/keep.txt"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_ne!(mode & 0o777, 0o700);
+
+        // entries reached through the link, as a walker following symlinks would yield them
+        let through_link = fs::read_dir(dir.join("src/a/inner")).unwrap();
+        let refused: Vec<_> =
+            RemoveEntries::new(through_link, dir.join("src"), OpOptions::default()).collect();
+        assert_eq!(refused.len(), 1);
+        assert!(refused[0]
+            .as_ref()
+            .is_err_and(|e| e.error.kind() == io::ErrorKind::InvalidInput));
+
+        let remove = remove_recursive(dir.join("src"), OpOptions::default());
+        ErrorCollector(remove.unwrap(), &mut errors).for_each(drop);
+        assert!(!dir.join("src").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("outside/keep.txt")).unwrap(),
+            "keep"
+        );
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! Export the `struct` [`Overlay`]. Iterates several roots as one logical tree, keyed by
+//! relative path, where entries from later roots shadow those of earlier ones.
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{path_reroot::path_re_root, Traverse};
+
+/// An item yielded by [Overlay].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayEntry {
+    /// Path of the entry relative to its root. This is the key used to align the roots.
+    pub relative: PathBuf,
+    /// Index of the root (in the list given to [Overlay::new]) the entry comes from.
+    pub root: usize,
+    /// Full path of the entry.
+    pub path: PathBuf,
+    /// `true` if a later root has an entry (a directory included) with the same relative
+    /// path, or something other than a directory at one of its ancestors. Shadowed entries
+    /// are only yielded when [Overlay::include_shadowed] is used.
+    pub shadowed: bool,
+}
+
+/// Iterates several roots as one logical tree, keyed by relative path. When more than one
+/// root contains the same relative path, the entry of the **last** root wins, the same way
+/// `/etc/app` overrides `/usr/share/app` and `~/.config/app` overrides both.
+///
+/// Only non directory entries are yielded, but directories take part in the shadowing: a
+/// directory hides a file at the same relative path in lower roots, and a file hides a
+/// directory of lower roots together with its whole content. Directories at the same relative
+/// path are merged. Roots that do not exist are skipped silently. Any other error is yielded
+/// as is.
+///
+/// Every root is walked on the first call to `next`, since all of them have to be inspected
+/// before the winner of a relative path is known, and the entries are kept in memory until
+/// they are yielded, sorted by relative path.
+///
+/// ## Example:
+/// ```
+/// use itfs::Overlay;
+///
+/// for entry in Overlay::new(&["./examples", "./src"]) {
+///     let entry = entry.unwrap();
+///     println!("{:?} from root #{}", entry.relative, entry.root);
+/// }
+/// ```
+pub struct Overlay {
+    roots: Vec<PathBuf>,
+    include_shadowed: bool,
+    /// Items left to yield. [None] until the roots are walked.
+    pending: Option<VecDeque<io::Result<OverlayEntry>>>,
+}
+
+impl Overlay {
+    /// Create a new instance of [Overlay] for the given roots, from lowest to highest priority.
+    /// Nothing is read until the first call to `next`, which walks all the roots at once.
+    pub fn new<P: AsRef<Path>>(roots: &[P]) -> Self {
+        Overlay {
+            roots: roots.iter().map(|r| r.as_ref().to_path_buf()).collect(),
+            include_shadowed: false,
+            pending: None,
+        }
+    }
+
+    /// Also yield shadowed entries, with [OverlayEntry::shadowed] set to `true`. They come in
+    /// root order, right before the entry with the same relative path that wins, if any.
+    pub fn include_shadowed(mut self) -> Self {
+        self.include_shadowed = true;
+        self
+    }
+
+    fn walk(&self) -> VecDeque<io::Result<OverlayEntry>> {
+        let mut pending = VecDeque::new();
+        // the roots that have an entry at each relative path, in root order
+        let mut by_relative: BTreeMap<PathBuf, Vec<Layer>> = BTreeMap::new();
+
+        for (index, root) in self.roots.iter().enumerate() {
+            let traverse = match Traverse::new(root) {
+                Ok(traverse) => traverse,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    pending.push_back(Err(e));
+                    continue;
+                }
+            };
+
+            for entry in traverse {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        pending.push_back(Err(e));
+                        continue;
+                    }
+                };
+                let is_dir = match entry.file_type() {
+                    Ok(file_type) => file_type.is_dir(),
+                    Err(e) => {
+                        pending.push_back(Err(e));
+                        continue;
+                    }
+                };
+                let path = entry.path();
+                // paths yielded by the walker always start with the root
+                if let Ok(relative) = path_re_root(path.as_path(), root.as_path(), Path::new("")) {
+                    by_relative.entry(relative).or_default().push(Layer {
+                        root: index,
+                        path,
+                        is_dir,
+                    });
+                }
+            }
+        }
+
+        // the highest root with something other than a directory at each relative path
+        let files: HashMap<&Path, usize> = by_relative
+            .iter()
+            .filter_map(|(relative, layers)| {
+                let top = layers.iter().rev().find(|layer| !layer.is_dir)?;
+                Some((relative.as_path(), top.root))
+            })
+            .collect();
+
+        for (relative, layers) in &by_relative {
+            let top = layers.last().map_or(0, |layer| layer.root);
+            for layer in layers.iter().filter(|layer| !layer.is_dir) {
+                let shadowed = layer.root < top
+                    || relative
+                        .ancestors()
+                        .skip(1)
+                        .any(|ancestor| files.get(ancestor).is_some_and(|&r| r > layer.root));
+                if !shadowed || self.include_shadowed {
+                    pending.push_back(Ok(OverlayEntry {
+                        relative: relative.clone(),
+                        root: layer.root,
+                        path: layer.path.clone(),
+                        shadowed,
+                    }));
+                }
+            }
+        }
+
+        pending
+    }
+}
+
+/// An entry found in one of the roots of an [Overlay].
+struct Layer {
+    root: usize,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl Iterator for Overlay {
+    type Item = io::Result<OverlayEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_none() {
+            self.pending = Some(self.walk());
+        }
+
+        self.pending.as_mut().and_then(VecDeque::pop_front)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Overlay;
+    use crate::Fixture;
+
+    #[test]
+    fn shadowing() {
+        let low = Fixture::from_tree(
+            "
+            a.txt = low
+            conf = a file
+            dir/
+              low.txt
+              x.txt
+            only_low.txt
+            replaced/
+              inner.txt
+            ",
+        )
+        .unwrap();
+        let mid = Fixture::from_tree(
+            "
+            a.txt = mid
+            conf/
+              main.toml
+            dir/x.txt
+            ",
+        )
+        .unwrap();
+        let high = Fixture::from_tree(
+            "
+            a.txt = high
+            dir/high.txt
+            replaced = a file
+            ",
+        )
+        .unwrap();
+        let roots = [low.path(), mid.path(), high.path(), &high.join("missing")];
+
+        let walk = |overlay: Overlay| -> Vec<(String, usize, bool)> {
+            overlay
+                .map(Result::unwrap)
+                .map(|e| {
+                    let relative = e.relative.to_string_lossy().replace('\\', "/");
+                    assert_eq!(e.path, roots[e.root].join(&e.relative));
+                    (relative, e.root, e.shadowed)
+                })
+                .collect()
+        };
+        let expect = |entries: &[(&str, usize, bool)]| -> Vec<(String, usize, bool)> {
+            entries
+                .iter()
+                .map(|&(relative, root, shadowed)| (relative.to_string(), root, shadowed))
+                .collect()
+        };
+
+        assert_eq!(
+            walk(Overlay::new(&roots)),
+            expect(&[
+                ("a.txt", 2, false),
+                ("conf/main.toml", 1, false),
+                ("dir/high.txt", 2, false),
+                ("dir/low.txt", 0, false),
+                ("dir/x.txt", 1, false),
+                ("only_low.txt", 0, false),
+                ("replaced", 2, false),
+            ])
+        );
+
+        // a directory hides a file, a file hides a directory and everything in it
+        assert_eq!(
+            walk(Overlay::new(&roots).include_shadowed()),
+            expect(&[
+                ("a.txt", 0, true),
+                ("a.txt", 1, true),
+                ("a.txt", 2, false),
+                ("conf", 0, true),
+                ("conf/main.toml", 1, false),
+                ("dir/high.txt", 2, false),
+                ("dir/low.txt", 0, false),
+                ("dir/x.txt", 0, true),
+                ("dir/x.txt", 1, false),
+                ("only_low.txt", 0, false),
+                ("replaced", 2, false),
+                ("replaced/inner.txt", 0, true),
+            ])
+        );
+    }
+}
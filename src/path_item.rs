@@ -0,0 +1,194 @@
+//! Export the `traits` [`PathItem`] and [`MaybeOk`]. The types of items the adaptors of this
+//! crate accept: anything with a path, and `Result`s of those.
+use std::{
+    borrow::Cow,
+    fs::{self, DirEntry, Metadata},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::OverlayEntry;
+
+/// An item that points at a file: [DirEntry], [PathBuf], `&Path`, `Box<Path>` or the entries
+/// yielded by the walkers of this crate.
+///
+/// ## Example
+/// ```
+/// use itfs::PathItem;
+/// use std::path::{Path, PathBuf};
+///
+/// fn name<P: PathItem>(item: P) -> String {
+///     item.path().file_name().unwrap().to_string_lossy().into_owned()
+/// }
+///
+/// assert_eq!(name(Path::new("a/b.rs")), "b.rs");
+/// assert_eq!(name(PathBuf::from("a/b.rs")), "b.rs");
+/// ```
+pub trait PathItem {
+    /// The path of the item, borrowed when the item holds one.
+    fn path(&self) -> Cow<'_, Path>;
+
+    /// Consume the item and return its path, without copying it when the item holds one.
+    fn into_path_buf(self) -> PathBuf
+    where
+        Self: Sized,
+    {
+        self.path().into_owned()
+    }
+
+    /// Metadata of the file, without following symlinks.
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        fs::symlink_metadata(self.path())
+    }
+}
+
+impl PathItem for DirEntry {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Owned(DirEntry::path(self))
+    }
+
+    /// `DirEntry::metadata` does not follow symlinks and, on most platforms, does not need
+    /// another system call.
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        self.metadata()
+    }
+}
+
+impl PathItem for PathBuf {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        self
+    }
+}
+
+impl PathItem for Path {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl PathItem for Box<Path> {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        Path::into_path_buf(self)
+    }
+}
+
+impl PathItem for OverlayEntry {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(&self.path)
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        self.path
+    }
+}
+
+/// Covers `&Path`, `&PathBuf`, `&DirEntry`, ...
+impl<P: PathItem + ?Sized> PathItem for &P {
+    fn path(&self) -> Cow<'_, Path> {
+        (**self).path()
+    }
+
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        (**self).symlink_metadata()
+    }
+}
+
+/// A [PathItem] or a `Result` of one. This is what lets the adaptors of this crate accept
+/// `Result`s: they only look at [Ok] values and let any [Err] through untouched.
+///
+/// A plain [PathItem] is always "ok". Its `Error` type is [io::Error], so that it fits the
+/// adaptors that need to yield errors of their own, even though it never carries one.
+pub trait MaybeOk {
+    /// The item when it is "ok".
+    type Inner: PathItem;
+    type Error;
+    /// The type of a `Self` whose inner item was mapped to `U`: `U` for a plain item,
+    /// `Result<U, Self::Error>` for a `Result`.
+    type Map<U>;
+
+    /// The inner item, [None] if this is an error.
+    fn as_inner(&self) -> Option<&Self::Inner>;
+
+    /// Turn `Self` into a `Result`.
+    fn into_result(self) -> Result<Self::Inner, Self::Error>;
+
+    /// Map the inner item, leaving any error untouched.
+    fn map_inner<U, F: FnOnce(Self::Inner) -> U>(self, f: F) -> Self::Map<U>;
+
+    /// `true` if there is no inner item or the given predicate returns `true` for its path.
+    /// This is how the filters of this crate decide what to let through.
+    fn is_err_or<F: FnOnce(&Path) -> bool>(&self, predicate: F) -> bool {
+        self.as_inner().is_none_or(|inner| predicate(&inner.path()))
+    }
+}
+
+impl<P: PathItem> MaybeOk for P {
+    type Inner = P;
+    type Error = io::Error;
+    type Map<U> = U;
+
+    fn as_inner(&self) -> Option<&P> {
+        Some(self)
+    }
+
+    fn into_result(self) -> Result<P, io::Error> {
+        Ok(self)
+    }
+
+    fn map_inner<U, F: FnOnce(P) -> U>(self, f: F) -> U {
+        f(self)
+    }
+}
+
+impl<P: PathItem, E> MaybeOk for Result<P, E> {
+    type Inner = P;
+    type Error = E;
+    type Map<U> = Result<U, E>;
+
+    fn as_inner(&self) -> Option<&P> {
+        self.as_ref().ok()
+    }
+
+    fn into_result(self) -> Result<P, E> {
+        self
+    }
+
+    fn map_inner<U, F: FnOnce(P) -> U>(self, f: F) -> Result<U, E> {
+        self.map(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        path::{Path, PathBuf},
+    };
+
+    use super::{MaybeOk, PathItem};
+
+    #[test]
+    fn items() {
+        let path = Path::new("a/b.rs");
+        let boxed: Box<Path> = path.into();
+        assert_eq!(boxed.path(), path);
+        assert_eq!(boxed.into_path_buf(), path);
+        assert_eq!(PathItem::path(&&path.to_path_buf()), path);
+
+        let ok: io::Result<&Path> = Ok(path);
+        assert!(!ok.is_err_or(|p| p.ends_with("c.rs")));
+        assert_eq!(ok.map_inner(PathItem::into_path_buf).unwrap(), path);
+
+        let err: io::Result<PathBuf> = Err(io::ErrorKind::NotFound.into());
+        assert!(err.is_err_or(|_| false));
+        assert!(err.as_inner().is_none());
+    }
+}
@@ -1,13 +1,20 @@
-//! Export the `struct` [`PathReRoot`]. Given an iterator over items of type [PathBuf]
+//! Export the `struct` [`PathReRoot`]. Given an iterator over paths or directory entries
 //! rewrite the root of those that contains a given prefix, by using another one
-//! given as a replacement.
+//! given as a replacement, or more generally map them with an ordered list of [ReRootRule].
 
-use std::path::{Path, PathBuf, StripPrefixError};
+use std::{
+    error,
+    ffi::OsString,
+    fmt, io,
+    path::{Component, Path, PathBuf, StripPrefixError},
+};
+
+use crate::{glob::glob_captures, MaybeOk, PathItem};
 
 /// function that performs the prefix replacement
 /// can fail if `Path::strip_prefix` fails
 /// `Path::strip_prefix` may fail if `base` is not a prefix of `path`
-fn path_re_root<P>(path: P, base: P, replace_by: P) -> Result<PathBuf, StripPrefixError>
+pub(crate) fn path_re_root<P>(path: P, base: P, replace_by: P) -> Result<PathBuf, StripPrefixError>
 where
     P: AsRef<Path>,
 {
@@ -17,51 +24,358 @@ where
     }
 }
 
-/// Given an iterator over items of type [PathBuf] rewrite the root of those that
+/// A rule used by [PathReRoot] to map a path to a new one.
+///
+/// - [ReRootRule::prefix] replaces a prefix with another one, as in `/a/b/c` to `/x/y/c`.
+/// - [ReRootRule::new] matches the path against a pattern and builds the new path from a
+///   replacement, which makes it possible to change extensions, rename files or insert
+///   directories.
+///
+/// Patterns are matched component by component. A `**` component matches any number of
+/// components (none included), other components may use the same wildcards as
+/// [Marker::glob][crate::Marker::glob]: `*`, `?`, `[abc]`, `[a-z]` and `[!x]`.
+/// In the replacement, each `**` is replaced by what the `**` at the same position of the
+/// pattern matched, and each `*` by what the `*` at the same position matched. Leading `.`
+/// components are ignored when matching.
+///
+/// ## Example
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use itfs::ReRootRule;
+///
+/// let rule = ReRootRule::new("src/**/*.ts", "dist/**/*.js").unwrap();
+/// assert_eq!(rule.apply(Path::new("src/a/b/x.ts")), Some(PathBuf::from("dist/a/b/x.js")));
+/// assert_eq!(rule.apply(Path::new("src/a/b/x.rs")), None);
+///
+/// let rule = ReRootRule::new("assets/*.png", "out/img/*.webp").unwrap();
+/// assert_eq!(rule.apply(Path::new("assets/x.png")), Some(PathBuf::from("out/img/x.webp")));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReRootRule(Rule);
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Prefix {
+        strip: PathBuf,
+        replace: PathBuf,
+    },
+    Pattern {
+        pattern: Vec<Part>,
+        replacement: Vec<Part>,
+    },
+}
+
+/// A component of a pattern or a replacement.
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(OsString),
+    /// A component with wildcards.
+    Glob(String),
+    /// `**`
+    AnyDirs,
+}
+
+impl ReRootRule {
+    /// A rule mapping the paths that match `pattern` as a whole to `replacement`. End the
+    /// pattern with `/**` to match everything under a directory. This operation fails with
+    /// [io::ErrorKind::InvalidInput] if the replacement uses more `*` or `**` than the pattern
+    /// provides.
+    pub fn new<P: AsRef<str>, R: AsRef<str>>(pattern: P, replacement: R) -> io::Result<Self> {
+        let (pattern, replacement) = (pattern.as_ref(), replacement.as_ref());
+        let pattern_parts = parts(pattern, |c| c.contains(['*', '?', '[']));
+        let replacement_parts = parts(replacement, |c| c.contains('*'));
+
+        let count = |parts: &[Part]| {
+            parts.iter().fold((0, 0), |(stars, dirs), part| match part {
+                Part::Glob(g) => (stars + g.matches('*').count(), dirs),
+                Part::AnyDirs => (stars, dirs + 1),
+                Part::Literal(_) => (stars, dirs),
+            })
+        };
+        let (stars, dirs) = count(&pattern_parts);
+        let (used_stars, used_dirs) = count(&replacement_parts);
+        if used_stars > stars || used_dirs > dirs {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "replacement {:?} uses more wildcards than pattern {:?}",
+                    replacement, pattern
+                ),
+            ));
+        }
+
+        Ok(ReRootRule(Rule::Pattern {
+            pattern: pattern_parts,
+            replacement: replacement_parts,
+        }))
+    }
+
+    /// A rule replacing `strip_prefix` with `replace_by` in the paths that start with it.
+    pub fn prefix<P: AsRef<Path>, R: AsRef<Path>>(strip_prefix: P, replace_by: R) -> Self {
+        ReRootRule(Rule::Prefix {
+            strip: strip_prefix.as_ref().to_path_buf(),
+            replace: replace_by.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Map `path` with this rule, or return [None] if the rule does not match it.
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        match &self.0 {
+            Rule::Prefix { strip, replace } => path_re_root(path, strip, replace).ok(),
+            Rule::Pattern {
+                pattern,
+                replacement,
+            } => {
+                let components: Vec<&std::ffi::OsStr> = path
+                    .components()
+                    .skip_while(|c| *c == Component::CurDir)
+                    .map(|c| c.as_os_str())
+                    .collect();
+                let (mut stars, mut dirs) = (vec![], vec![]);
+                if !match_parts(pattern, &components, &mut stars, &mut dirs) {
+                    return None;
+                }
+
+                let (mut stars, mut dirs) = (stars.into_iter(), dirs.into_iter());
+                let mut mapped = PathBuf::new();
+                for part in replacement {
+                    match part {
+                        Part::Literal(literal) => mapped.push(literal),
+                        Part::AnyDirs => {
+                            let matched = dirs.next().unwrap_or_default();
+                            if !matched.as_os_str().is_empty() {
+                                mapped.push(matched);
+                            }
+                        }
+                        Part::Glob(glob) => {
+                            let mut component = String::new();
+                            for (i, piece) in glob.split('*').enumerate() {
+                                if i > 0 {
+                                    component.push_str(&stars.next().unwrap_or_default());
+                                }
+                                component.push_str(piece);
+                            }
+                            mapped.push(component);
+                        }
+                    }
+                }
+                Some(mapped)
+            }
+        }
+    }
+}
+
+/// Map `path` with the first of the `rules` that matches it.
+pub(crate) fn apply_rules(rules: &[ReRootRule], path: &Path) -> Option<PathBuf> {
+    rules.iter().find_map(|rule| rule.apply(path))
+}
+
+/// Split a pattern or a replacement into parts. `is_glob` decides which components
+/// have wildcards.
+fn parts<F: Fn(&str) -> bool>(text: &str, is_glob: F) -> Vec<Part> {
+    Path::new(text)
+        .components()
+        .skip_while(|c| *c == Component::CurDir)
+        .map(|c| match c.as_os_str().to_str() {
+            Some("**") => Part::AnyDirs,
+            Some(s) if is_glob(s) => Part::Glob(s.to_string()),
+            _ => Part::Literal(c.as_os_str().to_os_string()),
+        })
+        .collect()
+}
+
+/// Match path components against pattern parts, collecting what `*` and `**` matched.
+fn match_parts(
+    parts: &[Part],
+    components: &[&std::ffi::OsStr],
+    stars: &mut Vec<String>,
+    dirs: &mut Vec<PathBuf>,
+) -> bool {
+    match parts.first() {
+        None => components.is_empty(),
+        Some(Part::AnyDirs) => {
+            // match as few components as possible
+            for n in 0..=components.len() {
+                let marks = (stars.len(), dirs.len());
+                dirs.push(components[..n].iter().collect());
+                if match_parts(&parts[1..], &components[n..], stars, dirs) {
+                    return true;
+                }
+                stars.truncate(marks.0);
+                dirs.truncate(marks.1);
+            }
+            false
+        }
+        Some(Part::Literal(literal)) => {
+            components.first() == Some(&literal.as_os_str())
+                && match_parts(&parts[1..], &components[1..], stars, dirs)
+        }
+        Some(Part::Glob(glob)) => {
+            let captures = components
+                .first()
+                .and_then(|c| c.to_str())
+                .and_then(|c| glob_captures(glob, c));
+            match captures {
+                Some(captures) => {
+                    let mark = stars.len();
+                    stars.extend(captures);
+                    match match_parts(&parts[1..], &components[1..], stars, dirs) {
+                        true => true,
+                        false => {
+                            stars.truncate(mark);
+                            false
+                        }
+                    }
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+/// What [PathReRoot] does with a path that no rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unmatched {
+    /// Yield the path unchanged, as both the original and the new path.
+    PassThrough,
+    /// Skip the path.
+    Drop,
+    /// Yield an error of kind [io::ErrorKind::InvalidInput] carrying a [NoRuleMatches] with
+    /// the path. This is the default.
+    Error,
+}
+
+/// Payload of the error yielded by [PathReRoot] for a path that no rule matches, when the
+/// [Unmatched::Error] policy is used. Get it back from the [io::Error] with
+/// `error.get_ref().and_then(|e| e.downcast_ref::<NoRuleMatches>())`.
+#[derive(Debug)]
+pub struct NoRuleMatches {
+    /// The path that no rule matches.
+    pub path: PathBuf,
+}
+
+impl fmt::Display for NoRuleMatches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no rule matches {}", self.path.display())
+    }
+}
+
+impl error::Error for NoRuleMatches {}
+
+/// Map an iterator over items of type [PathBuf], `Result<PathBuf>`,
+/// [DirEntry][std::fs::DirEntry], `Result<DirEntry>` or any other [PathItem] into one over
+/// pairs of the original path and a new one, obtained by rewriting the root of those that
 /// contains a given prefix, by using another one given as a replacement.
 ///
-/// **This iterator will yield tuples** where the first element is the original
-/// path ([PathBuf]) and the second is a `Result` with the new [PathBuf].
-pub struct PathReRoot<I, P>
-where
-    P: AsRef<Path>,
-    I: Iterator<Item = PathBuf>,
-{
+/// More generally, every path is mapped with the first of an ordered list of
+/// [ReRootRule] that matches it, see [PathReRoot::with_rules]. Paths that no rule matches
+/// are handled according to an [Unmatched] policy.
+///
+/// **This iterator will yield items of type `Result<(PathBuf, PathBuf)>`** where the first
+/// element of the tuple is the original path and the second is the new one. Any [Err] coming
+/// out of the original iterator is left "as is".
+///
+/// ## Example
+/// ```
+/// use std::path::PathBuf;
+/// use itfs::{PathReRoot, ReRootRule, Unmatched};
+///
+/// let paths = vec![
+///     PathBuf::from("src/a/x.ts"),
+///     PathBuf::from("assets/y.png"),
+///     PathBuf::from("README.md"),
+/// ];
+/// let rules = vec![
+///     ReRootRule::new("src/**/*.ts", "dist/**/*.js").unwrap(),
+///     ReRootRule::new("assets/*.png", "out/*.webp").unwrap(),
+/// ];
+///
+/// let mapped: Vec<_> = PathReRoot::with_rules(paths.into_iter(), rules)
+///     .unmatched(Unmatched::Drop)
+///     .map(|item| item.unwrap().1)
+///     .collect();
+///
+/// assert_eq!(mapped, vec![PathBuf::from("dist/a/x.js"), PathBuf::from("out/y.webp")]);
+/// ```
+pub struct PathReRoot<T, I: Iterator<Item = T>> {
     pub inner_iter: I,
-    pub strip_prefix: P,
-    pub replace_by: P,
+    /// Rules tried in order, the first one that matches wins.
+    pub rules: Vec<ReRootRule>,
+    pub unmatched: Unmatched,
+}
+
+impl<T, I: Iterator<Item = T>> PathReRoot<T, I> {
+    /// Replace `strip_prefix` with `replace_by` in the paths that start with it.
+    pub fn new<P: AsRef<Path>, R: AsRef<Path>>(
+        inner_iter: I,
+        strip_prefix: P,
+        replace_by: R,
+    ) -> Self {
+        Self::with_rules(
+            inner_iter,
+            vec![ReRootRule::prefix(strip_prefix, replace_by)],
+        )
+    }
+
+    /// Map the paths with the first of the `rules` that matches.
+    pub fn with_rules(inner_iter: I, rules: Vec<ReRootRule>) -> Self {
+        PathReRoot {
+            inner_iter,
+            rules,
+            unmatched: Unmatched::Error,
+        }
+    }
+
+    /// Choose what happens to paths that no rule matches.
+    pub fn unmatched(mut self, unmatched: Unmatched) -> Self {
+        self.unmatched = unmatched;
+        self
+    }
+
+    /// Map a single path. Returns [None] if it has to be dropped.
+    fn re_root(&self, path: PathBuf) -> Option<io::Result<(PathBuf, PathBuf)>> {
+        match (apply_rules(&self.rules, &path), self.unmatched) {
+            (Some(new), _) => Some(Ok((path, new))),
+            (None, Unmatched::PassThrough) => Some(Ok((path.clone(), path))),
+            (None, Unmatched::Drop) => None,
+            (None, Unmatched::Error) => Some(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                NoRuleMatches { path },
+            ))),
+        }
+    }
 }
 
-impl<I, P> Iterator for PathReRoot<I, P>
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<T, I> Iterator for PathReRoot<T, I>
 where
-    I: Iterator<Item = PathBuf>,
-    P: AsRef<Path>,
+    T: MaybeOk<Error = io::Error>,
+    I: Iterator<Item = T>,
 {
-    /// Item is a tuple where the first element is the original path
-    /// and the second one is the result of the re root operation
-    type Item = (PathBuf, Result<PathBuf, StripPrefixError>);
+    type Item = io::Result<(PathBuf, PathBuf)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.inner_iter.next() {
-            Some(p) => {
-                let rooted = path_re_root(
-                    p.as_path(),
-                    self.strip_prefix.as_ref(),
-                    self.replace_by.as_ref(),
-                );
-
-                Some((p, rooted))
+        loop {
+            match self.inner_iter.next().map(MaybeOk::into_result) {
+                Some(Ok(item)) => match self.re_root(item.into_path_buf()) {
+                    Some(item) => break Some(item),
+                    None => continue, // dropped
+                },
+                Some(Err(e)) => break Some(Err(e)),
+                None => break None,
             }
-            None => None,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::{Path, PathBuf};
+    use std::{
+        io,
+        path::{Path, PathBuf},
+    };
 
-    use super::path_re_root;
+    use super::{apply_rules, path_re_root, NoRuleMatches, PathReRoot, ReRootRule, Unmatched};
 
     struct Subject<P: AsRef<Path>> {
         path: P,
@@ -119,4 +433,72 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn rules() {
+        let rules = vec![
+            ReRootRule::new("src/**/*.d.ts", "types/**/*.d.ts").unwrap(),
+            ReRootRule::new("src/**/*.ts", "dist/**/*.js").unwrap(),
+            ReRootRule::new("assets/*.png", "out/*.webp").unwrap(),
+            ReRootRule::new("pkg/*/*.rs", "pkg/*/generated/*.rs").unwrap(),
+            ReRootRule::new("docs", "site").unwrap(),
+            ReRootRule::new("static/**", "site/**").unwrap(),
+            ReRootRule::prefix("/a/b", "/x"),
+        ];
+
+        let subjects = [
+            ("src/x.ts", Some("dist/x.js")),
+            ("./src/a/b/x.ts", Some("dist/a/b/x.js")),
+            // the first matching rule wins
+            ("src/a/x.d.ts", Some("types/a/x.d.ts")),
+            ("assets/logo.png", Some("out/logo.webp")),
+            ("assets/deep/logo.png", None),
+            ("pkg/core/lib.rs", Some("pkg/core/generated/lib.rs")),
+            // patterns match whole paths
+            ("docs", Some("site")),
+            ("docs/guide.md", None),
+            ("static/css/main.css", Some("site/css/main.css")),
+            ("/a/b/c", Some("/x/c")),
+            ("other/x.ts", None),
+        ];
+
+        for (path, expect) in subjects {
+            assert_eq!(
+                apply_rules(&rules, path.as_ref()),
+                expect.map(PathBuf::from),
+                "{}",
+                path
+            );
+        }
+
+        assert!(ReRootRule::new("src/*.ts", "dist/**/*.js").is_err());
+        assert!(ReRootRule::new("src/*.ts", "dist/*/*.js").is_err());
+    }
+
+    #[test]
+    fn unmatched_policy() {
+        let paths = || vec![PathBuf::from("/a/b/c"), PathBuf::from("/z")].into_iter();
+        let run = |unmatched| {
+            PathReRoot::new(paths(), "/a", "/x")
+                .unmatched(unmatched)
+                .map(|item| item.map(|(_, new)| new).ok())
+                .collect::<Vec<_>>()
+        };
+
+        let (moved, kept) = (Some(PathBuf::from("/x/b/c")), Some(PathBuf::from("/z")));
+        assert_eq!(run(Unmatched::PassThrough), vec![moved.clone(), kept]);
+        assert_eq!(run(Unmatched::Drop), vec![moved.clone()]);
+        assert_eq!(run(Unmatched::Error), vec![moved, None]);
+
+        // the error carries the path that no rule matches
+        let error = PathReRoot::new(paths(), "/a", "/x")
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "no rule matches /z");
+        let payload = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<NoRuleMatches>());
+        assert_eq!(payload.unwrap().path, PathBuf::from("/z"));
+    }
 }
@@ -0,0 +1,331 @@
+//! Export the `struct` [`PollWatcher`]. Watches a directory tree for changes by walking it
+//! periodically and comparing snapshots, without platform specific notification APIs.
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{contents::inode_key, ReadDirRecursive};
+
+/// A change reported by [PollWatcher].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Created(PathBuf),
+    /// The size, the modification time or the inode of the file changed.
+    Modified(PathBuf),
+    Removed(PathBuf),
+    /// A file disappeared and another one with the same inode appeared. Only detected on
+    /// platforms with inode numbers.
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+/// What a snapshot knows about a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    mtime: Option<SystemTime>,
+    inode: Option<(u64, u64)>,
+}
+
+type Snapshot = HashMap<PathBuf, FileState>;
+
+/// Watches a directory tree by walking it with [ReadDirRecursive] every
+/// [interval][PollWatcher::interval] and comparing the files found (path, size, modification
+/// time and inode) against the previous walk.
+///
+/// As an [Iterator], it blocks until there is something to report and never ends. Use
+/// [PollWatcher::poll] instead to check for changes right away, for example from an event
+/// loop of your own. The first walk only records the initial state of the tree.
+///
+/// With a [debounce][PollWatcher::debounce] duration, a changed file is only reported once it
+/// stayed unchanged for that long, so a file being written in several steps is reported once.
+///
+/// The walk can be wrapped with the filter adaptors of this crate through
+/// [PollWatcher::walk_with]. Errors of the walk are yielded as they are; files under a
+/// directory that could not be read are reported as removed.
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use itfs::{ComponentFilter, ComponentFilterOperationType, PollWatcher};
+///
+/// let watcher = PollWatcher::new(".")
+///     .unwrap()
+///     .interval(Duration::from_millis(500))
+///     .debounce(Duration::from_millis(200))
+///     .walk_with(|walk| ComponentFilter::new(walk, "target", ComponentFilterOperationType::Exclude));
+///
+/// for event in watcher {
+///     println!("{:?}", event.unwrap());
+/// }
+/// ```
+pub struct PollWatcher<F> {
+    root: PathBuf,
+    interval: Duration,
+    debounce: Duration,
+    walk_with: F,
+    /// The state already reported, [None] until the first walk.
+    reported: Option<Snapshot>,
+    /// The state found by the last walk.
+    last_seen: Snapshot,
+    /// Paths whose changes are not reported yet, with the last time they changed.
+    unsettled: HashMap<PathBuf, Instant>,
+    pending: VecDeque<io::Result<WatchEvent>>,
+}
+
+impl PollWatcher<fn(ReadDirRecursive) -> ReadDirRecursive> {
+    /// Create a new instance of [PollWatcher] for the given path, polling every second and
+    /// without debouncing. This operation will fail if the initial call to [fs::read_dir]
+    /// fails.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_dir(&path)?;
+
+        Ok(PollWatcher {
+            root: path.as_ref().to_path_buf(),
+            interval: Duration::from_secs(1),
+            debounce: Duration::ZERO,
+            walk_with: |walk| walk,
+            reported: None,
+            last_seen: HashMap::new(),
+            unsettled: HashMap::new(),
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<F, W> PollWatcher<F>
+where
+    F: FnMut(ReadDirRecursive) -> W,
+    W: Iterator<Item = io::Result<fs::DirEntry>>,
+{
+    /// Time to wait between two walks when used as an [Iterator].
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Only report a file once it stayed unchanged for this long.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Wrap every walk with the given function, typically to filter out part of the tree.
+    /// Filtered out files are never reported.
+    pub fn walk_with<G, V>(self, walk_with: G) -> PollWatcher<G>
+    where
+        G: FnMut(ReadDirRecursive) -> V,
+        V: Iterator<Item = io::Result<fs::DirEntry>>,
+    {
+        PollWatcher {
+            root: self.root,
+            interval: self.interval,
+            debounce: self.debounce,
+            walk_with,
+            reported: self.reported,
+            last_seen: self.last_seen,
+            unsettled: self.unsettled,
+            pending: self.pending,
+        }
+    }
+
+    /// Walk the tree once and return the changes that settled since the last call, sorted by
+    /// path, without waiting. The first call only records the initial state of the tree.
+    pub fn poll(&mut self) -> Vec<io::Result<WatchEvent>> {
+        let (current, mut results) = self.walk();
+        let now = Instant::now();
+
+        let reported = match &mut self.reported {
+            Some(reported) => reported,
+            None => {
+                self.reported = Some(current.clone());
+                self.last_seen = current;
+                return results;
+            }
+        };
+
+        // note the time of every change since the previous walk
+        for (path, state) in &current {
+            if self.last_seen.get(path) != Some(state) {
+                self.unsettled.insert(path.clone(), now);
+            }
+        }
+        for path in self.last_seen.keys() {
+            if !current.contains_key(path) {
+                self.unsettled.insert(path.clone(), now);
+            }
+        }
+        self.last_seen = current;
+
+        // changes old enough to be reported
+        let mut settled: Vec<PathBuf> = self
+            .unsettled
+            .iter()
+            .filter(|(_, changed)| now.duration_since(**changed) >= self.debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        settled.sort();
+
+        let (mut created, mut removed, mut events) = (vec![], vec![], vec![]);
+        for path in settled {
+            self.unsettled.remove(&path);
+            let before = reported.remove(&path);
+            let after = self.last_seen.get(&path).cloned();
+
+            match (&before, &after) {
+                (None, Some(_)) => created.push(path.clone()),
+                (Some(b), None) => removed.push((path.clone(), b.clone())),
+                (Some(b), Some(a)) if a != b => events.push(WatchEvent::Modified(path.clone())),
+                _ => {}
+            }
+            if let Some(after) = after {
+                reported.insert(path, after);
+            }
+        }
+
+        // a removed file whose inode shows up under another path was renamed
+        for (from, state) in removed {
+            let to = created.iter().position(|path| {
+                state.inode.is_some() && reported.get(path).map(|s| s.inode) == Some(state.inode)
+            });
+            match to {
+                Some(i) => events.push(WatchEvent::Renamed {
+                    from,
+                    to: created.remove(i),
+                }),
+                None => events.push(WatchEvent::Removed(from)),
+            }
+        }
+        events.extend(created.into_iter().map(WatchEvent::Created));
+
+        events.sort_by(|a, b| event_path(a).cmp(event_path(b)));
+        results.extend(events.into_iter().map(Ok));
+        results
+    }
+
+    /// Walk the tree, returning the files found and the errors of the walk.
+    fn walk(&mut self) -> (Snapshot, Vec<io::Result<WatchEvent>>) {
+        let mut snapshot = HashMap::new();
+        let mut errors = vec![];
+
+        let walk = match ReadDirRecursive::new(&self.root) {
+            Ok(walk) => walk,
+            // the whole tree is gone
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (snapshot, errors),
+            Err(e) => {
+                errors.push(Err(e));
+                return (self.last_seen.clone(), errors);
+            }
+        };
+
+        for entry in (self.walk_with)(walk) {
+            match entry.and_then(|entry| Ok((entry.path(), entry.metadata()?))) {
+                Ok((path, meta)) => {
+                    let state = FileState {
+                        size: meta.len(),
+                        mtime: meta.modified().ok(),
+                        inode: inode_key(&meta),
+                    };
+                    snapshot.insert(path, state);
+                }
+                Err(e) => errors.push(Err(e)),
+            }
+        }
+
+        (snapshot, errors)
+    }
+}
+
+impl<F, W> Iterator for PollWatcher<F>
+where
+    F: FnMut(ReadDirRecursive) -> W,
+    W: Iterator<Item = io::Result<fs::DirEntry>>,
+{
+    type Item = io::Result<WatchEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                break Some(event);
+            }
+
+            if self.reported.is_some() {
+                thread::sleep(self.interval);
+            }
+            let events = self.poll();
+            self.pending.extend(events);
+        }
+    }
+}
+
+/// Path used to sort events.
+fn event_path(event: &WatchEvent) -> &Path {
+    match event {
+        WatchEvent::Created(path) | WatchEvent::Modified(path) | WatchEvent::Removed(path) => path,
+        WatchEvent::Renamed { to, .. } => to,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, time::Duration};
+
+    use super::{PollWatcher, WatchEvent};
+
+    #[test]
+    fn events() {
+        let dir = std::env::temp_dir().join(format!("itfs-poll-watcher-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("kept"), "kept").unwrap();
+
+        let mut watcher = PollWatcher::new(&dir).unwrap();
+        let mut poll = || {
+            watcher
+                .poll()
+                .into_iter()
+                .map(|e| e.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert!(poll().is_empty());
+
+        fs::write(dir.join("sub/a"), "a").unwrap();
+        assert_eq!(poll(), vec![WatchEvent::Created(dir.join("sub/a"))]);
+        assert!(poll().is_empty());
+
+        fs::write(dir.join("sub/a"), "longer").unwrap();
+        assert_eq!(poll(), vec![WatchEvent::Modified(dir.join("sub/a"))]);
+
+        fs::rename(dir.join("sub/a"), dir.join("b")).unwrap();
+        let expect = match cfg!(unix) {
+            true => vec![WatchEvent::Renamed {
+                from: dir.join("sub/a"),
+                to: dir.join("b"),
+            }],
+            false => vec![
+                WatchEvent::Created(dir.join("b")),
+                WatchEvent::Removed(dir.join("sub/a")),
+            ],
+        };
+        assert_eq!(poll(), expect);
+
+        fs::remove_file(dir.join("b")).unwrap();
+        assert_eq!(poll(), vec![WatchEvent::Removed(dir.join("b"))]);
+
+        // changes are held back until they settle
+        let mut watcher = PollWatcher::new(&dir)
+            .unwrap()
+            .debounce(Duration::from_secs(3600));
+        watcher.poll();
+        fs::write(dir.join("c"), "c").unwrap();
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+//! Everything needed to chain the adaptors of this crate as methods:
+//!
+//! ```
+//! use itfs::prelude::*;
+//! ```
+//!
+//! [FsIteratorExt] replaces the deprecated
+//! [AllowExtensionsExt][crate::ext::allow_extensions_ext::AllowExtensionsExt], whose method it
+//! has under the same name, so do not import both.
+
+pub use crate::ext::fs_iterator_ext::FsIteratorExt;
+pub use crate::{MaybeOk, PathItem};
@@ -1,7 +1,15 @@
-//! Export the `struct` [`ReadDirRecursive`]. Iterator similar to the standard [fs::ReadDir] but recursive.
-use std::{fs, io, path};
+//! Export the `struct` [`ReadDirRecursive`]. Iterator similar to the standard [std::fs::ReadDir] but recursive.
+use std::{io, path};
 
-/// Iterator similar to the standard [fs::ReadDir] but recursive.
+use crate::{FileSystem, FsDirEntry, FsMetadata, PathItem, StdFs, WalkStats};
+
+/// Iterator similar to the standard [std::fs::ReadDir] but recursive.
+///
+/// It walks the disk by default. Use [ReadDirRecursive::with_fs] to walk any other
+/// [FileSystem], in which case the items are the directory entries of that file system.
+///
+/// It can only be built with [ReadDirRecursive::new] or [ReadDirRecursive::with_fs], not
+/// with a struct literal, since some of its fields are private.
 ///
 /// ## Example:
 /// ```
@@ -14,25 +22,30 @@ use std::{fs, io, path};
 ///     println!("Found file: '{:?}'", entry.path());
 /// }
 /// ```
-pub struct ReadDirRecursive {
-    /// This field hods the [fs::ReadDir] instance that is currently being iterated.
+pub struct ReadDirRecursive<F: FileSystem = StdFs> {
+    /// This field hods the [FileSystem::ReadDir] instance that is currently being iterated.
     ///
-    /// At the beginning, it holds the [fs::ReadDir] iterator of the root directory
+    /// At the beginning, it holds the [FileSystem::ReadDir] iterator of the root directory
     /// (given as param) but later, when all entries in the root where consumed (the
-    /// iterator reached the end) it will be replaced by a new instances of [fs::ReadDir]
-    /// as the main iteration continues visiting subdirectories of the root.
-    pub read_dir: fs::ReadDir,
+    /// iterator reached the end) it will be replaced by a new instances of
+    /// [FileSystem::ReadDir] as the main iteration continues visiting subdirectories of the
+    /// root.
+    pub read_dir: F::ReadDir,
     /// Sub Directories are not visited immediately when found. Instead they're
-    /// pushed onto a vector of pending directories/[entries][fs::DirEntry] (this field)
+    /// pushed onto a vector of pending directories/[entries][FileSystem::DirEntry] (this field)
     /// and the iteration of the current directory continues with the next entry.
     /// Once that iteration is done, [ReadDirRecursive] will `pop` one directory from this stack,
-    /// create a new instance of [fs::ReadDir] for it and resume the iteration.
-    pub pending_dirs: Vec<fs::DirEntry>,
+    /// create a new instance of [FileSystem::ReadDir] for it and resume the iteration.
+    pub pending_dirs: Vec<F::DirEntry>,
+    /// Counters updated while iterating. See [ReadDirRecursive::with_stats].
+    stats: Option<WalkStats>,
+    /// The file system being walked.
+    fs: F,
 }
 
 impl ReadDirRecursive {
     /// Create a new instance of [ReadDirRecursive] for the given path. This operation
-    /// will fail if the initial call to [fs::read_dir] fails.
+    /// will fail if the initial call to [std::fs::read_dir] fails.
     ///
     /// ```
     /// use itfs::ReadDirRecursive;
@@ -40,20 +53,65 @@ impl ReadDirRecursive {
     /// let rdr = ReadDirRecursive::new(".").unwrap();
     /// ```
     pub fn new<P: AsRef<path::Path>>(path: P) -> io::Result<Self> {
+        ReadDirRecursive::with_fs(path, StdFs)
+    }
+}
+
+impl<F: FileSystem> ReadDirRecursive<F> {
+    /// Create a new instance of [ReadDirRecursive] for the given path of the given
+    /// [FileSystem]. This operation will fail if the initial call to [FileSystem::read_dir]
+    /// fails.
+    ///
+    /// ```
+    /// use itfs::{ReadDirRecursive, StdFs};
+    ///
+    /// let rdr = ReadDirRecursive::with_fs(".", StdFs).unwrap();
+    /// ```
+    pub fn with_fs<P: AsRef<path::Path>>(path: P, fs: F) -> io::Result<Self> {
         Ok(ReadDirRecursive {
             pending_dirs: vec![],
-            read_dir: fs::read_dir(&path)?,
+            read_dir: fs.read_dir(path.as_ref())?,
+            stats: None,
+            fs,
         })
     }
+
+    /// Keep the given [WalkStats] up to date while iterating. The root directory, already
+    /// read by [ReadDirRecursive::new], is accounted for right away.
+    ///
+    /// ```
+    /// use itfs::{ReadDirRecursive, WalkStats};
+    ///
+    /// let stats = WalkStats::new();
+    /// let rdr = ReadDirRecursive::new(".").unwrap().with_stats(&stats);
+    ///
+    /// let count = rdr.count();
+    /// assert_eq!(stats.entries_yielded() + stats.errors(), count as u64);
+    /// ```
+    pub fn with_stats(mut self, stats: &WalkStats) -> Self {
+        stats.record_dir();
+        self.stats = Some(stats.clone());
+        self
+    }
+
+    fn record(&self, item: &io::Result<F::DirEntry>, bytes: u64) {
+        if let Some(stats) = &self.stats {
+            match item {
+                Ok(_) => stats.record_entry(bytes),
+                Err(e) => stats.record_error(e),
+            }
+        }
+    }
 }
 
 // Implement Iterator for ReadDirRecursive
-impl Iterator for ReadDirRecursive {
+impl<F: FileSystem> Iterator for ReadDirRecursive<F> {
     // our Item is the same as the wrapped iter
-    type Item = io::Result<fs::DirEntry>;
+    type Item = io::Result<F::DirEntry>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
+        let mut bytes = 0;
+        let item = loop {
             match self.read_dir.next() {
                 // entry found
                 Some(Ok(entry)) => match entry.metadata() {
@@ -67,6 +125,7 @@ impl Iterator for ReadDirRecursive {
                         }
 
                         // DirEntry found. Break the loop and yield it
+                        bytes = meta.len();
                         break Some(Ok(entry));
                     }
                     // Error trying to obtain the entry's metadata.
@@ -80,11 +139,15 @@ impl Iterator for ReadDirRecursive {
                     // or finish the iteration completely.
                     if let Some(dir_entry) = self.pending_dirs.pop() {
                         let entry_path = dir_entry.path();
-                        match fs::read_dir(&entry_path) {
+                        match self.fs.read_dir(&entry_path) {
                             Ok(read_dir) => {
                                 // throw away the consumed iterator and put the new one in his place
                                 self.read_dir = read_dir;
 
+                                if let Some(stats) = &self.stats {
+                                    stats.record_dir();
+                                }
+
                                 // skip to the next iteration
                                 continue;
                             }
@@ -97,14 +160,20 @@ impl Iterator for ReadDirRecursive {
                     break None;
                 }
             }
+        };
+
+        if let Some(item) = &item {
+            self.record(item, bytes);
         }
+
+        item
     }
 }
 
 /**
 Create an instance of [ReadDirRecursive] for the given path.
 
-This function is not lazy. Std [fs::read_dir] is called immediately to read from the given path,
+This function is not lazy. Std [std::fs::read_dir] is called immediately to read from the given path,
 so any error coming from that action must be handled to being able start the iteration.
 
 Same behavior as using [ReadDirRecursive::new].
@@ -0,0 +1,480 @@
+//! Export the `struct` [`RenamePlan`]. Computes the target of every path of an iterator from
+//! a name template, and checks the resulting renames for collisions and cycles before anything
+//! touches the disk.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{Transaction, TxOp};
+
+/// A problem that prevents a [RenamePlan] from being applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// Several paths would be renamed to the same target.
+    SameTarget {
+        target: PathBuf,
+        sources: Vec<PathBuf>,
+    },
+    /// The target already exists and is not renamed away by the plan itself.
+    Exists { from: PathBuf, to: PathBuf },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::SameTarget { target, sources } => {
+                write!(
+                    f,
+                    "{} paths would be renamed to {}",
+                    sources.len(),
+                    target.display()
+                )
+            }
+            Conflict::Exists { from, to } => {
+                write!(
+                    f,
+                    "{} -> {}: target already exists",
+                    from.display(),
+                    to.display()
+                )
+            }
+        }
+    }
+}
+
+/// The renames needed to give every path of an iterator the name produced by a template.
+///
+/// The template is a path where the following placeholders are replaced, for each path:
+///
+/// - `{parent}`: the directory of the path (`.` if it has none).
+/// - `{name}`: the file name.
+/// - `{stem}`: the file name without its extension.
+/// - `{ext}`: the extension, without the dot. A `.` right before an `{ext}` placeholder is
+///   dropped when the path has no extension.
+/// - `{n}`: a counter, starting at 1, that follows the order of the iterator. A width can be
+///   given to pad it with zeros: `{n:03}`.
+///
+/// Text placeholders accept a case modifier: `lower`, `upper`, `snake`, `kebab` or `camel`,
+/// as in `{stem:snake}`. Use `{{` and `}}` for literal braces.
+///
+/// Paths whose target is the path itself are left out. Every target is checked against the
+/// others and against the disk, and any [Conflict] found is listed in
+/// [RenamePlan::conflicts]. A plan without conflicts can be turned into a sequence of renames
+/// safe to apply one after the other with [RenamePlan::steps]: renames are ordered so no
+/// target is overwritten before it is moved away, and cycles (`a -> b`, `b -> a`) are broken
+/// with temporary names.
+///
+/// ## Example
+/// ```
+/// use std::path::PathBuf;
+/// use itfs::RenamePlan;
+///
+/// let paths = vec![PathBuf::from("photos/Summer Trip.JPG"), PathBuf::from("photos/Winter.jpeg")];
+/// let plan = RenamePlan::new(paths, "{parent}/{stem:snake}_{n:03}.{ext:lower}").unwrap();
+///
+/// assert_eq!(plan.renames[0].1, PathBuf::from("photos/summer_trip_001.jpg"));
+/// assert_eq!(plan.renames[1].1, PathBuf::from("photos/winter_002.jpeg"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    /// Pairs of `(from, to)`, in the order of the iterator.
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl RenamePlan {
+    /// Compute the target of every path with the `template` and look for conflicts. This
+    /// operation fails with [io::ErrorKind::InvalidInput] if the template is not valid.
+    pub fn new<I, S>(paths: I, template: S) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        S: AsRef<str>,
+    {
+        let template = parse(template.as_ref())?;
+
+        let mut renames = vec![];
+        for (i, from) in paths.into_iter().enumerate() {
+            let to = render(&template, &from, i + 1);
+            if to != from {
+                renames.push((from, to));
+            }
+        }
+
+        let conflicts = find_conflicts(&renames);
+        Ok(RenamePlan { renames, conflicts })
+    }
+
+    /// `true` if the plan has no conflicts.
+    pub fn is_valid(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// The renames in an order that is safe to apply one after the other, including the extra
+    /// steps through temporary names needed to break cycles. Fails with
+    /// [io::ErrorKind::AlreadyExists] if the plan has conflicts.
+    pub fn steps(&self) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        if let Some(conflict) = self.conflicts.first() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                conflict.to_string(),
+            ));
+        }
+
+        let mut pending = self.renames.clone();
+        // paths that still have to be moved away
+        let mut sources: HashSet<PathBuf> = pending.iter().map(|(from, _)| from.clone()).collect();
+        let targets: HashSet<&Path> = self.renames.iter().map(|(_, to)| to.as_path()).collect();
+        let mut steps = Vec::with_capacity(pending.len());
+
+        while !pending.is_empty() {
+            let before = pending.len();
+            pending.retain(|(from, to)| match sources.contains(to) {
+                true => true,
+                false => {
+                    sources.remove(from);
+                    steps.push((from.clone(), to.clone()));
+                    false
+                }
+            });
+
+            if pending.len() == before {
+                // every remaining rename is part of a cycle: move one of them out of the way
+                let (from, _) = &mut pending[0];
+                let temp = temp_name(from, |p| sources.contains(p) || targets.contains(p));
+                sources.remove(from);
+                steps.push((from.clone(), temp.clone()));
+                *from = temp;
+            }
+        }
+
+        Ok(steps)
+    }
+
+    /// Build a [Transaction] with the [steps][RenamePlan::steps] of the plan, so the renames
+    /// are applied all together or not at all.
+    pub fn transaction<P: AsRef<Path>>(&self, journal_dir: P) -> io::Result<Transaction> {
+        let mut tx = Transaction::new(journal_dir)?;
+        for (from, to) in self.steps()? {
+            tx.push(TxOp::Rename { from, to });
+        }
+        Ok(tx)
+    }
+}
+
+fn find_conflicts(renames: &[(PathBuf, PathBuf)]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+
+    let mut by_target: HashMap<&PathBuf, Vec<&PathBuf>> = HashMap::new();
+    for (from, to) in renames {
+        by_target.entry(to).or_default().push(from);
+    }
+    let sources: HashSet<&PathBuf> = renames.iter().map(|(from, _)| from).collect();
+
+    for (from, to) in renames {
+        let same = &by_target[to];
+        if same.len() > 1 {
+            // reported once, for the first of them
+            if same[0] == from {
+                conflicts.push(Conflict::SameTarget {
+                    target: to.clone(),
+                    sources: same.iter().map(|p| p.to_path_buf()).collect(),
+                });
+            }
+        } else if !sources.contains(to) && fs::symlink_metadata(to).is_ok() {
+            conflicts.push(Conflict::Exists {
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+/// A name next to `path` that does not exist and is not `taken`.
+fn temp_name<F: Fn(&Path) -> bool>(path: &Path, taken: F) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (0..)
+        .map(|k| path.with_file_name(format!(".{}.rename-{}", name, k)))
+        .find(|p| !taken(p) && fs::symlink_metadata(p).is_err())
+        .expect("there is always a free name")
+}
+
+/// A piece of a parsed template.
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Parent(Case),
+    Name(Case),
+    Stem(Case),
+    Ext(Case),
+    Counter(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Case {
+    Keep,
+    Lower,
+    Upper,
+    Snake,
+    Kebab,
+    Camel,
+}
+
+fn invalid(template: &str, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid template {:?}: {}", template, reason),
+    )
+}
+
+fn parse(template: &str) -> io::Result<Vec<Piece>> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err(invalid(template, "unmatched `}`")),
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err(invalid(template, "unmatched `{`")),
+                    }
+                }
+
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+
+                let (key, modifier) = match spec.split_once(':') {
+                    Some((key, modifier)) => (key, Some(modifier)),
+                    None => (spec.as_str(), None),
+                };
+
+                if key == "n" {
+                    let width = match modifier {
+                        Some(width) => width
+                            .parse()
+                            .map_err(|_| invalid(template, "the width of `{n}` is not a number"))?,
+                        None => 0,
+                    };
+                    pieces.push(Piece::Counter(width));
+                    continue;
+                }
+
+                let case = match modifier {
+                    None => Case::Keep,
+                    Some("lower") => Case::Lower,
+                    Some("upper") => Case::Upper,
+                    Some("snake") => Case::Snake,
+                    Some("kebab") => Case::Kebab,
+                    Some("camel") => Case::Camel,
+                    Some(other) => {
+                        return Err(invalid(template, &format!("unknown modifier `{}`", other)))
+                    }
+                };
+
+                pieces.push(match key {
+                    "parent" => Piece::Parent(case),
+                    "name" => Piece::Name(case),
+                    "stem" => Piece::Stem(case),
+                    "ext" => Piece::Ext(case),
+                    other => {
+                        return Err(invalid(
+                            template,
+                            &format!("unknown placeholder `{}`", other),
+                        ))
+                    }
+                });
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn render(template: &[Piece], path: &Path, n: usize) -> PathBuf {
+    let lossy = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().into_owned());
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_string_lossy().into_owned(),
+        _ => ".".to_string(),
+    };
+    let name = lossy(path.file_name()).unwrap_or_default();
+    let stem = lossy(path.file_stem()).unwrap_or_default();
+    let ext = lossy(path.extension());
+
+    let mut out = String::new();
+    for piece in template {
+        match piece {
+            Piece::Text(text) => out.push_str(text),
+            Piece::Parent(case) => out.push_str(&convert(&parent, *case)),
+            Piece::Name(case) => out.push_str(&convert(&name, *case)),
+            Piece::Stem(case) => out.push_str(&convert(&stem, *case)),
+            Piece::Ext(case) => match &ext {
+                Some(ext) => out.push_str(&convert(ext, *case)),
+                None => {
+                    if out.ends_with('.') {
+                        out.pop();
+                    }
+                }
+            },
+            Piece::Counter(width) => out.push_str(&format!("{:0width$}", n, width = width)),
+        }
+    }
+
+    // drop the leading `.` introduced by an empty parent, so `x` is not renamed to `./x`
+    let rendered = PathBuf::from(out);
+    let mut components = rendered.components();
+    match (path.components().next(), components.next()) {
+        (Some(first), Some(Component::CurDir))
+            if first != Component::CurDir && components.clone().next().is_some() =>
+        {
+            components.as_path().to_path_buf()
+        }
+        _ => rendered,
+    }
+}
+
+fn convert(text: &str, case: Case) -> String {
+    match case {
+        Case::Keep => text.to_string(),
+        Case::Lower => text.to_lowercase(),
+        Case::Upper => text.to_uppercase(),
+        Case::Snake => words(text).join("_"),
+        Case::Kebab => words(text).join("-"),
+        Case::Camel => words(text)
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                    _ => word.clone(),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Split a text into lowercase words, at non alphanumeric characters and at case changes:
+/// `"HTTPServer v2Beta"` gives `http`, `server`, `v2`, `beta`.
+fn words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && !word.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+
+        word.extend(c.to_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{convert, parse, render, Case, Conflict, RenamePlan};
+
+    #[test]
+    fn templates() {
+        let subjects = [
+            (
+                "a/b/Hello World.TXT",
+                "{parent}/{stem:snake}.{ext:lower}",
+                "a/b/hello_world.txt",
+            ),
+            ("a/Makefile", "{parent}/{stem:kebab}.{ext}", "a/makefile"),
+            ("x.tar.gz", "{parent}/{n:03}-{name}", "007-x.tar.gz"),
+            ("x.rs", "out/{stem:upper}{{{n}}}.{ext}", "out/X{7}.rs"),
+        ];
+
+        for (path, template, expect) in subjects {
+            let template = parse(template).unwrap();
+            assert_eq!(render(&template, path.as_ref(), 7), PathBuf::from(expect));
+        }
+
+        for bad in ["{nope}", "{stem:shout}", "{stem", "stem}", "{n:x}"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn cases() {
+        let text = "HTTPServer v2Beta_final";
+        assert_eq!(convert(text, Case::Snake), "http_server_v2_beta_final");
+        assert_eq!(convert(text, Case::Kebab), "http-server-v2-beta-final");
+        assert_eq!(convert(text, Case::Camel), "httpServerV2BetaFinal");
+    }
+
+    #[test]
+    fn collisions_and_cycles() {
+        let root = std::env::temp_dir().join("itfs-rename-plan-does-not-exist");
+        let p = |name: &str| root.join(name);
+
+        // every file gets the same name
+        let plan = RenamePlan::new(vec![p("a.txt"), p("b.txt")], "{parent}/same.txt").unwrap();
+        assert_eq!(
+            plan.conflicts,
+            vec![Conflict::SameTarget {
+                target: p("same.txt"),
+                sources: vec![p("a.txt"), p("b.txt")],
+            }]
+        );
+        assert!(plan.steps().is_err());
+
+        // 2 -> 1, 1 -> 2 is a cycle, 3 stays and x -> 4 is a plain rename
+        let files = vec![p("2"), p("1"), p("3"), p("x")];
+        let plan = RenamePlan::new(files.clone(), "{parent}/{n}").unwrap();
+        assert!(plan.is_valid());
+        assert_eq!(plan.renames.len(), 3);
+        let steps = plan.steps().unwrap();
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0], (p("x"), p("4")));
+
+        // applying the steps on a simulated file system gives the expected result
+        let mut files = files;
+        for (from, to) in &steps {
+            assert!(!files.contains(to), "{:?} overwritten", to);
+            let i = files.iter().position(|f| f == from).unwrap();
+            files[i] = to.clone();
+        }
+        assert_eq!(files, vec![p("1"), p("2"), p("3"), p("4")]);
+    }
+}
@@ -0,0 +1,257 @@
+//! Export the `struct` [`StaleOutputs`]. Walks a source tree, maps each source to the path
+//! of its generated output and tells whether that output is missing, stale or up to date.
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{self, Path, PathBuf},
+};
+
+use crate::{find_ancestors::normalize, path_reroot::apply_rules, ReRootRule, ReadDirRecursive};
+
+/// State of the output of a source, as decided by [StaleOutputs].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// The output does not exist.
+    Missing,
+    /// The source was modified after the output.
+    Stale,
+    /// The output is at least as recent as the source.
+    UpToDate,
+}
+
+/// An item yielded by [StaleOutputs].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub freshness: Freshness,
+}
+
+impl Verdict {
+    /// `true` if the output has to be generated again.
+    pub fn needs_rebuild(&self) -> bool {
+        self.freshness != Freshness::UpToDate
+    }
+}
+
+/// Walks a source tree with [ReadDirRecursive], maps every file to its output with the first
+/// [ReRootRule] that matches (the same rules used by [PathReRoot][crate::PathReRoot]) and
+/// compares both, the way `make` does: an output that does not exist or that is older than its
+/// source has to be generated again. Sources that no rule matches are skipped.
+///
+/// Once the sources are known, [StaleOutputs::orphans] lists the files of the output tree that
+/// no source maps to.
+///
+/// ## Example
+/// ```
+/// use itfs::{ReRootRule, StaleOutputs};
+///
+/// let rules = vec![ReRootRule::new("src/**/*.rs", "target/docs/**/*.html").unwrap()];
+/// let mut stale = StaleOutputs::new("src", rules).unwrap();
+///
+/// for verdict in stale.by_ref() {
+///     let verdict = verdict.unwrap();
+///     if verdict.needs_rebuild() {
+///         println!("{:?}: {:?}", verdict.output, verdict.freshness);
+///     }
+/// }
+///
+/// for orphan in stale.orphans("target/docs").unwrap() {
+///     println!("orphan: {:?}", orphan);
+/// }
+/// ```
+pub struct StaleOutputs {
+    walk: ReadDirRecursive,
+    rules: Vec<ReRootRule>,
+    /// Outputs of the sources seen so far, to find the orphans.
+    expected: HashSet<PathBuf>,
+}
+
+impl StaleOutputs {
+    /// Create a new instance of [StaleOutputs] for the sources under `source_root`, mapped
+    /// with `rules`. This operation will fail if the initial call to [fs::read_dir] fails.
+    pub fn new<P: AsRef<Path>>(source_root: P, rules: Vec<ReRootRule>) -> io::Result<Self> {
+        Ok(StaleOutputs {
+            walk: ReadDirRecursive::new(source_root)?,
+            rules,
+            expected: HashSet::new(),
+        })
+    }
+
+    /// Files under `output_root` that are not the output of any source, sorted. Sources not
+    /// visited yet are visited first; any error doing so is returned, since an unreadable
+    /// source would make its output look like an orphan. An `output_root` that does not
+    /// exist has no orphans. Paths are compared once made absolute, so `output_root` does not
+    /// need to be written the same way as the replacements of the rules.
+    pub fn orphans<P: AsRef<Path>>(mut self, output_root: P) -> io::Result<Vec<PathBuf>> {
+        for verdict in self.by_ref() {
+            verdict?;
+        }
+
+        let walk = match ReadDirRecursive::new(output_root) {
+            Ok(walk) => walk,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut orphans = vec![];
+        for entry in walk {
+            let path = entry?.path();
+            if !self.expected.contains(&output_key(&path)) {
+                orphans.push(path);
+            }
+        }
+
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    fn check(&mut self, source: PathBuf) -> io::Result<Option<Verdict>> {
+        let output = match apply_rules(&self.rules, &source) {
+            Some(output) => output,
+            None => return Ok(None),
+        };
+        self.expected.insert(output_key(&output));
+
+        let freshness = match fs::metadata(&output) {
+            Ok(output_meta) => {
+                let source_time = fs::metadata(&source)?.modified()?;
+                match output_meta.modified()? < source_time {
+                    true => Freshness::Stale,
+                    false => Freshness::UpToDate,
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Freshness::Missing,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Some(Verdict {
+            source,
+            output,
+            freshness,
+        }))
+    }
+}
+
+impl Iterator for StaleOutputs {
+    type Item = io::Result<Verdict>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.walk.next() {
+                Some(Ok(entry)) => match self.check(entry.path()) {
+                    Ok(Some(verdict)) => break Some(Ok(verdict)),
+                    Ok(None) => continue, // no rule for this source
+                    Err(e) => break Some(Err(e)),
+                },
+                Some(Err(e)) => break Some(Err(e)),
+                None => break None,
+            }
+        }
+    }
+}
+
+/// `path` made absolute with the current directory, without `.` components and with `..`
+/// resolved, so `./out/a`, `out/a`, `out/x/../a` and `/project/out/a` are the same output no
+/// matter how the rules and the output root were written.
+fn output_key(path: &Path) -> PathBuf {
+    match path::absolute(path) {
+        Ok(absolute) => normalize(&absolute),
+        Err(_) => normalize(path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::PathBuf,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{Freshness, StaleOutputs};
+    use crate::{Fixture, MemFs, ReRootRule};
+
+    #[test]
+    fn freshness_and_orphans() {
+        let mut fs = MemFs::from_tree(
+            "
+            src/
+              fresh.ts
+              missing.ts
+              notes.md
+              stale.ts
+            out/
+              fresh.js
+              old.js
+              stale.js
+            ",
+        )
+        .unwrap();
+        fs.modified("src/stale.ts", UNIX_EPOCH + Duration::from_secs(60))
+            .unwrap();
+        let fixture = Fixture::from_fs(&fs).unwrap();
+
+        // the outputs are spelled with `..`, the output root is not
+        let root = fixture.path().to_string_lossy().replace('\\', "/");
+        let rules = vec![ReRootRule::new(
+            format!("{}/src/*.ts", root),
+            format!("{}/src/../out/*.js", root),
+        )
+        .unwrap()];
+
+        let mut stale = StaleOutputs::new(fixture.join("src"), rules).unwrap();
+        let mut verdicts: Vec<(String, Freshness)> = stale
+            .by_ref()
+            .map(Result::unwrap)
+            .map(|v| {
+                let name = v.source.file_name().unwrap().to_string_lossy().into_owned();
+                assert_eq!(v.needs_rebuild(), v.freshness != Freshness::UpToDate);
+                (name, v.freshness)
+            })
+            .collect();
+        verdicts.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            verdicts,
+            vec![
+                ("fresh.ts".to_string(), Freshness::UpToDate),
+                ("missing.ts".to_string(), Freshness::Missing),
+                ("stale.ts".to_string(), Freshness::Stale),
+            ]
+        );
+
+        assert_eq!(
+            stale.orphans(fixture.join("out")).unwrap(),
+            vec![fixture.join("out/old.js")]
+        );
+    }
+
+    // a relative path from the current directory to the fixture, easy to build on unix only
+    #[cfg(unix)]
+    #[test]
+    fn relative_output_root() {
+        let fixture = Fixture::from_tree(
+            "
+            src/a.ts
+            out/
+              a.js
+              b.js
+            ",
+        )
+        .unwrap();
+        let rules = vec![ReRootRule::new(
+            format!("{}/src/*.ts", fixture.path().display()),
+            format!("{}/out/*.js", fixture.path().display()),
+        )
+        .unwrap()];
+
+        let cwd = std::env::current_dir().unwrap();
+        let mut relative: PathBuf = cwd.components().skip(1).map(|_| "..").collect();
+        relative.push(fixture.join("out").strip_prefix("/").unwrap());
+
+        let stale = StaleOutputs::new(fixture.join("src"), rules).unwrap();
+        assert_eq!(
+            stale.orphans(&relative).unwrap(),
+            vec![relative.join("b.js")]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Export the `struct` [`SyncPlan`]. Computes the operations needed to mirror a source tree
+//! onto a destination, prints them (dry run) and applies them.
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ops::{copy_entry, remove_entry},
+    path_reroot::path_re_root,
+    Change, Compare, TreeDiff,
+};
+
+/// Options used by [SyncPlan::new].
+#[derive(Debug, Clone, Copy)]
+pub struct SyncOptions {
+    /// How to decide that a file in the destination is outdated.
+    /// Defaults to [size and modification time][Compare::new].
+    pub compare: Compare,
+    /// Delete entries of the destination that do not exist in the source.
+    /// Defaults to `false`.
+    pub delete: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            compare: Compare::new(),
+            delete: false,
+        }
+    }
+}
+
+/// A single step of a [SyncPlan]. Paths are full paths, not relative ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOp {
+    /// Create a directory with the permissions of `from` (its parents always exist by the time
+    /// this runs). It stays writable by its owner until an [SyncOp::Update] that comes after
+    /// its content restores the permissions of a read only `from`.
+    CreateDir { from: PathBuf, to: PathBuf },
+    /// Copy a file or symlink that does not exist in the destination.
+    Copy { from: PathBuf, to: PathBuf },
+    /// Overwrite an outdated file or symlink, or update the permissions of a directory.
+    Update { from: PathBuf, to: PathBuf },
+    /// Remove a file, a symlink or a directory with all its content.
+    Delete(PathBuf),
+}
+
+/// One line per operation, in the style of `rsync --dry-run`.
+impl fmt::Display for SyncOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncOp::CreateDir { to, .. } => write!(f, "mkdir   {}", to.display()),
+            SyncOp::Copy { from, to } => {
+                write!(f, "copy    {} -> {}", from.display(), to.display())
+            }
+            SyncOp::Update { from, to } => {
+                write!(f, "update  {} -> {}", from.display(), to.display())
+            }
+            SyncOp::Delete(path) => write!(f, "delete  {}", path.display()),
+        }
+    }
+}
+
+/// The ordered list of operations needed to make a destination tree mirror a source tree.
+///
+/// The source is walked with [TreeDiff] against the destination, and each source path is
+/// mapped onto the destination with the same prefix replacement performed by
+/// [PathReRoot][crate::PathReRoot]. Operations are ordered so they can be applied one after
+/// the other: directories are created before their content, and a destination entry whose
+/// type changed is deleted before it is replaced. Deletions of extraneous entries (only with
+/// [SyncOptions::delete]) come next, and the permissions of the read only directories that
+/// were created are restored at the very end.
+///
+/// Building the plan stops at the first error: a plan made from a partial view of the trees
+/// could delete or overwrite the wrong things.
+///
+/// ## Example
+/// ```
+/// use itfs::{SyncOptions, SyncPlan};
+///
+/// let plan = SyncPlan::new("./src", "./target/mirror-of-src", SyncOptions::default()).unwrap();
+///
+/// // print what would be done, without touching the disk
+/// plan.dry_run(std::io::stdout()).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub ops: Vec<SyncOp>,
+}
+
+impl SyncPlan {
+    /// Compare the `source` tree against the `destination` and compute the plan.
+    /// The destination does not need to exist.
+    pub fn new<S: AsRef<Path>, D: AsRef<Path>>(
+        source: S,
+        destination: D,
+        options: SyncOptions,
+    ) -> io::Result<Self> {
+        let (source, destination) = (source.as_ref(), destination.as_ref());
+        let mut ops = vec![];
+        let mut deletes = vec![];
+        // updates that make read only directories read only again, once their content is there
+        let mut restores = vec![];
+
+        match fs::symlink_metadata(destination) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let (from, to) = (source.to_path_buf(), destination.to_path_buf());
+                if fs::metadata(source)?.permissions().readonly() {
+                    restores.push(SyncOp::Update {
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                }
+                ops.push(SyncOp::CreateDir { from, to });
+            }
+            Err(e) => return Err(e),
+        }
+
+        // the destination is the "old" tree and the source the "new" one
+        let diff = TreeDiff::new(destination, source)?.compare(options.compare);
+        // destination paths deleted with all their content, nothing under them needs an operation
+        let mut gone: Vec<PathBuf> = vec![];
+
+        for entry in diff {
+            let entry = entry?;
+            let from = source.join(&entry.relative);
+            let to = path_re_root(from.as_path(), source, destination)
+                .expect("paths built from the source always start with it");
+
+            // removed together with a parent that is deleted with all its content
+            if entry.change == Change::Removed && gone.iter().any(|g| to.starts_with(g)) {
+                continue;
+            }
+
+            let source_is_dir = entry.right.as_ref().is_some_and(|m| m.is_dir());
+            let source_is_readonly = entry
+                .right
+                .as_ref()
+                .is_some_and(|m| m.permissions().readonly());
+            let mut create = |from: PathBuf, to: PathBuf| match source_is_dir {
+                true => {
+                    if source_is_readonly {
+                        restores.push(SyncOp::Update {
+                            from: from.clone(),
+                            to: to.clone(),
+                        });
+                    }
+                    SyncOp::CreateDir { from, to }
+                }
+                false => SyncOp::Copy { from, to },
+            };
+
+            match entry.change {
+                Change::Added => ops.push(create(from, to)),
+                Change::Removed if options.delete => {
+                    gone.push(to.clone());
+                    deletes.push(SyncOp::Delete(to));
+                }
+                Change::Removed => {}
+                Change::TypeChanged => {
+                    gone.push(to.clone());
+                    ops.push(SyncOp::Delete(to.clone()));
+                    ops.push(create(from, to));
+                }
+                // a directory made read only can not receive its content any more
+                Change::Modified(_) if source_is_dir && source_is_readonly => {
+                    restores.push(SyncOp::Update { from, to })
+                }
+                Change::Modified(_) => ops.push(SyncOp::Update { from, to }),
+            }
+        }
+
+        ops.extend(deletes);
+        ops.extend(restores);
+
+        Ok(SyncPlan {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            ops,
+        })
+    }
+
+    /// `true` if the destination is already in sync.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Write every operation of the plan, one per line, without applying any of them.
+    pub fn dry_run<W: io::Write>(&self, mut out: W) -> io::Result<()> {
+        for op in &self.ops {
+            writeln!(out, "{}", op)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator that applies the operations of the plan, in order, one per call
+    /// to `next`, yielding each operation together with its result. A failed operation does
+    /// not stop the iteration.
+    pub fn execute(&self) -> SyncExecute<'_> {
+        SyncExecute(self.ops.iter())
+    }
+}
+
+/// Iterator returned by [SyncPlan::execute].
+pub struct SyncExecute<'a>(std::slice::Iter<'a, SyncOp>);
+
+impl<'a> Iterator for SyncExecute<'a> {
+    type Item = (&'a SyncOp, io::Result<()>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let op = self.0.next()?;
+        Some((op, apply(op)))
+    }
+}
+
+fn apply(op: &SyncOp) -> io::Result<()> {
+    match op {
+        SyncOp::CreateDir { from, to } => {
+            let meta = fs::metadata(from)?;
+            fs::create_dir(to)?;
+            fs::set_permissions(to, writable(meta.permissions()))
+        }
+        SyncOp::Copy { from, to } => copy_entry(from, to),
+        SyncOp::Update { from, to } => {
+            let meta = fs::symlink_metadata(from)?;
+            if meta.is_dir() {
+                fs::set_permissions(to, meta.permissions())
+            } else {
+                remove_entry(to)?;
+                copy_entry(from, to)
+            }
+        }
+        SyncOp::Delete(path) => remove_entry(path),
+    }
+}
+
+/// `permissions` with the write permission of the owner, so the content of a directory can be
+/// created before its permissions are restored.
+#[cfg(unix)]
+fn writable(permissions: fs::Permissions) -> fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    fs::Permissions::from_mode(permissions.mode() | 0o200)
+}
+
+#[cfg(not(unix))]
+fn writable(mut permissions: fs::Permissions) -> fs::Permissions {
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    permissions
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{SyncOp, SyncOptions, SyncPlan};
+    use crate::{Compare, Fixture};
+
+    #[test]
+    fn mirror() {
+        let source = Fixture::from_tree(
+            "
+            changed.txt = new contents
+            new.txt = new
+            same.txt = same
+            sub/
+              deep.txt
+            was_dir = now a file
+            was_file/
+              inside.txt
+            ",
+        )
+        .unwrap();
+        let destination = Fixture::from_tree(
+            "
+            changed.txt = old
+            extra/
+              gone.txt
+            same.txt = same
+            was_dir/
+              inside.txt
+            was_file = file
+            ",
+        )
+        .unwrap();
+        let from = |path: &str| source.join(path);
+        let to = |path: &str| destination.join(path);
+        let copy = |path: &str| SyncOp::Copy {
+            from: from(path),
+            to: to(path),
+        };
+
+        let options = SyncOptions {
+            delete: true,
+            ..Default::default()
+        };
+        let plan = SyncPlan::new(source.path(), destination.path(), options).unwrap();
+        assert_eq!(
+            plan.ops,
+            vec![
+                SyncOp::Update {
+                    from: from("changed.txt"),
+                    to: to("changed.txt"),
+                },
+                copy("new.txt"),
+                SyncOp::CreateDir {
+                    from: from("sub"),
+                    to: to("sub"),
+                },
+                copy("sub/deep.txt"),
+                SyncOp::Delete(to("was_dir")),
+                copy("was_dir"),
+                SyncOp::Delete(to("was_file")),
+                SyncOp::CreateDir {
+                    from: from("was_file"),
+                    to: to("was_file"),
+                },
+                copy("was_file/inside.txt"),
+                SyncOp::Delete(to("extra")),
+            ]
+        );
+
+        for (op, result) in plan.execute() {
+            result.unwrap_or_else(|e| panic!("{}: {}", op, e));
+        }
+        destination.assert_listing(&[
+            "changed.txt",
+            "new.txt",
+            "same.txt",
+            "sub/deep.txt",
+            "was_dir",
+            "was_file/inside.txt",
+        ]);
+        assert_eq!(
+            std::fs::read_to_string(to("changed.txt")).unwrap(),
+            "new contents"
+        );
+        assert!(SyncPlan::new(source.path(), destination.path(), options)
+            .unwrap()
+            .is_empty());
+
+        // without `delete` extraneous entries are kept
+        std::fs::write(to("extra.txt"), "").unwrap();
+        let plan = SyncPlan::new(source.path(), destination.path(), SyncOptions::default());
+        assert_eq!(plan.unwrap().ops, vec![]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn directory_modes() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let source = Fixture::from_tree(
+            "
+            locked/ [mode=555]
+              a.txt
+            private/ [mode=750]
+            ",
+        )
+        .unwrap();
+        let destination = Fixture::new().unwrap();
+        let copy = destination.join("copy");
+        let mode = |path: PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+
+        let plan = SyncPlan::new(source.path(), &copy, SyncOptions::default()).unwrap();
+        // the read only directory gets its mode back after its content is copied
+        assert_eq!(
+            plan.ops.last(),
+            Some(&SyncOp::Update {
+                from: source.join("locked"),
+                to: copy.join("locked"),
+            })
+        );
+        for (op, result) in plan.execute() {
+            result.unwrap_or_else(|e| panic!("{}: {}", op, e));
+        }
+
+        assert_eq!(mode(copy.clone()), mode(source.path().to_path_buf()));
+        assert_eq!(mode(copy.join("locked")), 0o555);
+        assert_eq!(mode(copy.join("private")), 0o750);
+        assert!(copy.join("locked/a.txt").is_file());
+        assert!(SyncPlan::new(source.path(), &copy, SyncOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_directory_modes() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let source = Fixture::from_tree(
+            "
+            locked/ [mode=555]
+              a.txt = new contents
+            open/ [mode=755]
+              b.txt = new contents
+            ",
+        )
+        .unwrap();
+        let destination = Fixture::from_tree(
+            "
+            locked/ [mode=755]
+              a.txt = old
+            open/ [mode=555]
+              b.txt = old
+            ",
+        )
+        .unwrap();
+        let update = |path: &str| SyncOp::Update {
+            from: source.join(path),
+            to: destination.join(path),
+        };
+        let mode = |path: &str| {
+            fs::metadata(destination.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        let options = SyncOptions {
+            compare: Compare::all(),
+            ..Default::default()
+        };
+
+        // a directory becomes read only after its content is updated and writable before
+        let plan = SyncPlan::new(source.path(), destination.path(), options).unwrap();
+        assert_eq!(
+            plan.ops,
+            vec![
+                update("locked/a.txt"),
+                update("open"),
+                update("open/b.txt"),
+                update("locked"),
+            ]
+        );
+        for (op, result) in plan.execute() {
+            result.unwrap_or_else(|e| panic!("{}: {}", op, e));
+        }
+
+        assert_eq!(mode("locked") & 0o7777, 0o555);
+        assert_eq!(mode("open") & 0o7777, 0o755);
+        for file in ["locked/a.txt", "open/b.txt"] {
+            let contents = fs::read_to_string(destination.join(file)).unwrap();
+            assert_eq!(contents, "new contents");
+        }
+    }
+}
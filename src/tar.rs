@@ -0,0 +1,612 @@
+//! Export the `struct` [`TarReader`]. Reads a tar archive (ustar, with GNU long names and PAX
+//! headers) and yields its members as [TarEntry] items, with their path, size, mode and
+//! modification time.
+use std::{
+    borrow::Cow,
+    fs::Metadata,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::PathItem;
+
+/// Size of the headers and the unit the data of the members is padded to.
+const BLOCK: usize = 512;
+
+/// The type of a member of a tar archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarEntryType {
+    File,
+    Dir,
+    Symlink,
+    /// A hard link to a member that came earlier in the archive, named by
+    /// [TarEntry::link_name].
+    HardLink,
+    /// Devices, fifos and other types, with their type flag.
+    Other(u8),
+}
+
+/// A member of a tar archive, yielded by [TarReader].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarEntry {
+    /// Path of the member as stored in the archive, without the trailing `/` of directories.
+    pub path: PathBuf,
+    pub entry_type: TarEntryType,
+    /// Size of the data of the member in bytes.
+    pub size: u64,
+    /// Unix permission bits.
+    pub mode: u32,
+    pub modified: SystemTime,
+    /// Target of a symlink or a hard link.
+    pub link_name: Option<PathBuf>,
+    /// The data of the member, if the reader was asked for it with [TarReader::contents].
+    pub contents: Option<Vec<u8>>,
+}
+
+impl PathItem for TarEntry {
+    fn path(&self) -> Cow<'_, Path> {
+        Cow::Borrowed(&self.path)
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        self.path
+    }
+
+    /// A member of an archive has no [std::fs::Metadata], so this always fails with
+    /// [io::ErrorKind::Unsupported]. Use the fields of the entry instead.
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: is a member of an archive", self.path.display()),
+        ))
+    }
+}
+
+/// Values of the extended headers that apply to the next member, or to every following member
+/// for the global ones.
+#[derive(Debug, Clone, Default)]
+struct Overrides {
+    path: Option<PathBuf>,
+    link_name: Option<PathBuf>,
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl Overrides {
+    /// Keep the values of `other` and the ones of `self` that `other` does not have.
+    fn or(&self, other: Overrides) -> Overrides {
+        Overrides {
+            path: other.path.or_else(|| self.path.clone()),
+            link_name: other.link_name.or_else(|| self.link_name.clone()),
+            size: other.size.or(self.size),
+            modified: other.modified.or(self.modified),
+        }
+    }
+}
+
+/// An iterator over the members of a tar archive read from `R`.
+///
+/// Understands the ustar format, including the prefix of long paths, the GNU long name and
+/// long link records (`L` and `K`) and the numbers in base 256 of GNU tar, and PAX extended
+/// headers (`x` and `g`), from which it takes `path`, `linkpath`, `size` and `mtime`. The
+/// headers that only describe other members are not yielded.
+///
+/// The data of the members is skipped unless [TarReader::contents] is set. The iterator
+/// stops at the end of the archive, marked by a zero block or by the end of the input, and
+/// after the first error. Headers that fail their checksum are an
+/// [io::ErrorKind::InvalidData] error.
+///
+/// The items are `io::Result<TarEntry>`, so the adaptors of this crate can filter them like
+/// the entries of a directory walk.
+///
+/// ## Example
+/// ```no_run
+/// use itfs::{prelude::*, TarReader};
+/// use std::{ffi::OsStr, fs::File, io::BufReader};
+///
+/// let archive = BufReader::new(File::open("sources.tar").unwrap());
+/// let extensions = vec![OsStr::new("rs")];
+///
+/// for entry in TarReader::new(archive)
+///     .exclude_component("target")
+///     .allow_extensions(&extensions)
+/// {
+///     let entry = entry.unwrap();
+///     println!("{} {} bytes", entry.path.display(), entry.size);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TarReader<R> {
+    reader: R,
+    contents: bool,
+    globals: Overrides,
+    done: bool,
+}
+
+impl<R: Read> TarReader<R> {
+    pub fn new(reader: R) -> Self {
+        TarReader {
+            reader,
+            contents: false,
+            globals: Overrides::default(),
+            done: false,
+        }
+    }
+
+    /// Read the data of the members into [TarEntry::contents] instead of skipping it.
+    pub fn contents(mut self, contents: bool) -> Self {
+        self.contents = contents;
+        self
+    }
+
+    /// Read the next header block, `false` at the end of the archive.
+    fn read_header(&mut self, header: &mut [u8; BLOCK]) -> io::Result<bool> {
+        let mut read = 0;
+        while read < BLOCK {
+            match self.reader.read(&mut header[read..]) {
+                Ok(0) if read == 0 => return Ok(false),
+                Ok(0) => return Err(invalid("the archive ends in the middle of a header")),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(header.iter().any(|b| *b != 0))
+    }
+
+    /// Read the `size` bytes of data of a member and the padding after them.
+    fn read_data(&mut self, size: u64) -> io::Result<Vec<u8>> {
+        let mut data = vec![];
+        (&mut self.reader).take(size).read_to_end(&mut data)?;
+        if (data.len() as u64) < size {
+            return Err(invalid("the archive ends in the middle of a member"));
+        }
+        self.skip(padding(size))?;
+        Ok(data)
+    }
+
+    /// Skip `size` bytes of data and the padding after them.
+    fn skip_data(&mut self, size: u64) -> io::Result<()> {
+        self.skip(size + padding(size))
+    }
+
+    fn skip(&mut self, size: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.reader).take(size), &mut io::sink())?;
+        if skipped < size {
+            return Err(invalid("the archive ends in the middle of a member"));
+        }
+        Ok(())
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<TarEntry>> {
+        let mut header = [0; BLOCK];
+        // values of the extended headers that precede the member
+        let mut local = Overrides::default();
+
+        loop {
+            if !self.read_header(&mut header)? {
+                return Ok(None);
+            }
+            check_checksum(&header)?;
+
+            let size = number(&header[124..136])?;
+            match header[156] {
+                b'L' => local.path = Some(bytes_to_path(until_nul(&self.read_data(size)?))),
+                b'K' => local.link_name = Some(bytes_to_path(until_nul(&self.read_data(size)?))),
+                b'x' => local = local.or(pax(&self.read_data(size)?)?),
+                b'g' => {
+                    let globals = pax(&self.read_data(size)?)?;
+                    self.globals = self.globals.or(globals);
+                }
+                flag => {
+                    let overrides = self.globals.or(local);
+                    let mut name = match overrides.path {
+                        Some(path) => path.into_os_string(),
+                        None => bytes_to_path(&header_name(&header)).into_os_string(),
+                    };
+                    let entry_type = match flag {
+                        b'0' | b'\0' | b'7' if name.to_string_lossy().ends_with('/') => {
+                            TarEntryType::Dir
+                        }
+                        b'0' | b'\0' | b'7' => TarEntryType::File,
+                        b'1' => TarEntryType::HardLink,
+                        b'2' => TarEntryType::Symlink,
+                        b'5' => TarEntryType::Dir,
+                        other => TarEntryType::Other(other),
+                    };
+                    if name.is_empty() {
+                        name = ".".into();
+                    }
+                    let size = overrides.size.unwrap_or(size);
+                    let modified = match overrides.modified {
+                        Some(modified) => modified,
+                        None => seconds(number(&header[136..148])?)?,
+                    };
+                    let link_name = match entry_type {
+                        TarEntryType::Symlink | TarEntryType::HardLink => Some(
+                            overrides
+                                .link_name
+                                .unwrap_or_else(|| bytes_to_path(until_nul(&header[157..257]))),
+                        ),
+                        _ => None,
+                    };
+
+                    // links and directories have no data, whatever the size says
+                    let data_size = match entry_type {
+                        TarEntryType::File | TarEntryType::Other(_) => size,
+                        _ => 0,
+                    };
+                    let contents = match self.contents {
+                        true => Some(self.read_data(data_size)?),
+                        false => {
+                            self.skip_data(data_size)?;
+                            None
+                        }
+                    };
+
+                    return Ok(Some(TarEntry {
+                        path: PathBuf::from(name).components().collect(),
+                        entry_type,
+                        size,
+                        mode: number(&header[100..108])? as u32 & 0o7777,
+                        modified,
+                        link_name,
+                        contents,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for TarReader<R> {
+    type Item = io::Result<TarEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The name of the member of a header, joined to the ustar prefix if there is one.
+fn header_name(header: &[u8; BLOCK]) -> Vec<u8> {
+    let name = until_nul(&header[0..100]);
+    // GNU tar uses the space of the prefix for other things, and says so with its magic
+    if &header[257..263] != b"ustar\0" {
+        return name.to_vec();
+    }
+    match until_nul(&header[345..500]) {
+        [] => name.to_vec(),
+        prefix => [prefix, b"/", name].concat(),
+    }
+}
+
+/// Verify the checksum of a header, the sum of its bytes with the checksum field taken as
+/// spaces. Some old archivers summed signed bytes, so that is accepted too.
+fn check_checksum(header: &[u8; BLOCK]) -> io::Result<()> {
+    let expected = number(&header[148..156])?;
+    let (mut unsigned, mut signed) = (0u64, 0i64);
+    for (i, b) in header.iter().enumerate() {
+        let b = if (148..156).contains(&i) { b' ' } else { *b };
+        unsigned += b as u64;
+        signed += b as i8 as i64;
+    }
+    if unsigned == expected || signed == expected as i64 {
+        Ok(())
+    } else {
+        Err(invalid("bad header checksum"))
+    }
+}
+
+/// A numeric field of a header: octal text ended by a space or a NUL, or a big endian
+/// number in base 256 if the high bit of the first byte is set.
+fn number(field: &[u8]) -> io::Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let mut n: u64 = (field[0] & 0x7f) as u64;
+        for b in &field[1..] {
+            n = n
+                .checked_mul(256)
+                .and_then(|n| n.checked_add(*b as u64))
+                .ok_or_else(|| invalid("number out of range"))?;
+        }
+        return Ok(n);
+    }
+
+    let text = String::from_utf8_lossy(until_nul(field));
+    let text = text.trim_matches(|c| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid("bad number"))
+}
+
+/// The records of a PAX extended header, `<length> <key>=<value>\n` each, with the length
+/// counting the whole record.
+fn pax(data: &[u8]) -> io::Result<Overrides> {
+    let mut overrides = Overrides::default();
+    let mut rest = data;
+
+    while !rest.iter().all(|b| *b == 0) {
+        let space = rest
+            .iter()
+            .position(|b| *b == b' ')
+            .ok_or_else(|| invalid("bad PAX record"))?;
+        let len: usize = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .filter(|len| *len > space && *len <= rest.len())
+            .ok_or_else(|| invalid("bad PAX record length"))?;
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        rest = &rest[len..];
+
+        let equals = record
+            .iter()
+            .position(|b| *b == b'=')
+            .ok_or_else(|| invalid("bad PAX record"))?;
+        let (key, value) = (&record[..equals], &record[equals + 1..]);
+        match key {
+            b"path" => overrides.path = Some(bytes_to_path(value)),
+            b"linkpath" => overrides.link_name = Some(bytes_to_path(value)),
+            b"size" => {
+                let size = String::from_utf8_lossy(value);
+                overrides.size = Some(size.parse().map_err(|_| invalid("bad PAX size"))?);
+            }
+            b"mtime" => overrides.modified = Some(pax_time(value)?),
+            _ => {}
+        }
+    }
+
+    Ok(overrides)
+}
+
+/// A PAX time, seconds since the epoch with an optional fraction.
+fn pax_time(value: &[u8]) -> io::Result<SystemTime> {
+    let bad = || invalid("bad PAX mtime");
+    let value = String::from_utf8_lossy(value);
+    // the sign applies to the fraction too, `-0.5` is half a second before the epoch
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, &*value),
+    };
+    let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if ![secs, fraction]
+        .iter()
+        .all(|s| s.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Err(bad());
+    }
+    let secs: u64 = secs.parse().map_err(|_| bad())?;
+    let digits = &fraction[..fraction.len().min(9)];
+    let nanos = match digits.is_empty() {
+        true => 0,
+        false => {
+            let n: u32 = digits.parse().map_err(|_| bad())?;
+            n * 10u32.pow(9 - digits.len() as u32)
+        }
+    };
+    let offset = Duration::new(secs, nanos);
+    match negative {
+        true => UNIX_EPOCH.checked_sub(offset),
+        false => UNIX_EPOCH.checked_add(offset),
+    }
+    .ok_or_else(bad)
+}
+
+/// A header time, seconds since the epoch.
+fn seconds(secs: u64) -> io::Result<SystemTime> {
+    UNIX_EPOCH
+        .checked_add(Duration::from_secs(secs))
+        .ok_or_else(|| invalid("bad mtime"))
+}
+
+/// Bytes needed after `size` bytes of data to complete a block.
+fn padding(size: u64) -> u64 {
+    (BLOCK as u64 - size % BLOCK as u64) % BLOCK as u64
+}
+
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|b| *b == 0) {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    }
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("tar: {}", message))
+}
+
+/// Builds archives for the tests of this module and of [crate::TarFs].
+#[cfg(test)]
+pub(crate) mod build {
+    use super::BLOCK;
+
+    /// A header block with a valid checksum.
+    pub(crate) fn header(name: &str, flag: u8, size: usize, mode: u32, mtime: u64) -> Vec<u8> {
+        let mut header = vec![0; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(format!("{:07o}", mode).as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[136..147].copy_from_slice(format!("{:011o}", mtime).as_bytes());
+        header[156] = flag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        checksum(&mut header);
+        header
+    }
+
+    pub(crate) fn checksum(header: &mut [u8]) {
+        header[148..156].copy_from_slice(b"        ");
+        let sum: u32 = header.iter().map(|b| *b as u32).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    }
+
+    /// A header followed by its data, padded.
+    pub(crate) fn member(name: &str, flag: u8, data: &[u8]) -> Vec<u8> {
+        let mut member = header(name, flag, data.len(), 0o644, 0);
+        member.extend_from_slice(data);
+        member.resize(member.len().div_ceil(BLOCK) * BLOCK, 0);
+        member
+    }
+
+    /// A symlink (`b'2'`) or hard link (`b'1'`) to `target`.
+    pub(crate) fn link(name: &str, flag: u8, target: &str) -> Vec<u8> {
+        let mut header = header(name, flag, 0, 0o777, 0);
+        header[157..157 + target.len()].copy_from_slice(target.as_bytes());
+        checksum(&mut header);
+        header
+    }
+
+    pub(crate) fn file(name: &str, data: &str) -> Vec<u8> {
+        member(name, b'0', data.as_bytes())
+    }
+
+    pub(crate) fn end() -> Vec<u8> {
+        vec![0; 2 * BLOCK]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        ffi::OsStr,
+        io,
+        path::PathBuf,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{
+        build::{checksum, end, file, header, link, member},
+        pax_time, TarEntryType, TarReader,
+    };
+    use crate::prelude::*;
+
+    #[test]
+    fn formats() {
+        let long = format!("{}/{}.txt", "d".repeat(80), "n".repeat(80));
+        let mut ustar = header("lib.rs", b'0', 4, 0o600, 0);
+        ustar[345..348].copy_from_slice(b"src");
+        checksum(&mut ustar);
+        ustar.extend_from_slice(b"mod\n");
+        ustar.resize(2 * 512, 0);
+
+        let archive = [
+            header("src/", b'5', 0, 0o755, 60),
+            ustar,
+            // GNU long name
+            member("././@LongLink", b'L', format!("{}\0", long).as_bytes()),
+            file("truncated", "long"),
+            // PAX, with a size and time of its own
+            member("PaxHeader", b'x', b"19 path=pax/a.json\n14 mtime=90.5\n"),
+            file("ignored", "{}"),
+            link("link", b'2', "x"),
+            end(),
+        ]
+        .concat();
+
+        let entries: Vec<_> = TarReader::new(&archive[..])
+            .contents(true)
+            .map(Result::unwrap)
+            .collect();
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.path.to_string_lossy().into_owned(), e.entry_type, e.size))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src".to_string(), TarEntryType::Dir, 0),
+                ("src/lib.rs".to_string(), TarEntryType::File, 4),
+                (long.clone(), TarEntryType::File, 4),
+                ("pax/a.json".to_string(), TarEntryType::File, 2),
+                ("link".to_string(), TarEntryType::Symlink, 0),
+            ]
+        );
+        assert_eq!(entries[0].modified, UNIX_EPOCH + Duration::from_secs(60));
+        assert_eq!(entries[1].mode, 0o600);
+        assert_eq!(entries[1].contents.as_deref(), Some(&b"mod\n"[..]));
+        assert_eq!(entries[2].contents.as_deref(), Some(&b"long"[..]));
+        assert_eq!(
+            entries[3].modified,
+            UNIX_EPOCH + Duration::from_millis(90_500)
+        );
+        assert_eq!(entries[4].link_name, Some(PathBuf::from("x")));
+
+        // adaptors work on the members
+        let extensions = vec![OsStr::new("rs"), OsStr::new("json")];
+        let paths: Vec<_> = TarReader::new(&archive[..])
+            .allow_extensions(&extensions)
+            .exclude_component("pax")
+            .to_paths()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(paths, vec![PathBuf::from("src/lib.rs")]);
+
+        // corrupt and truncated archives
+        let mut corrupt = file("a", "x");
+        corrupt[0] = b'b';
+        let error = TarReader::new(&corrupt[..]).next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let truncated = &file("a", "x")[..600];
+        let mut reader = TarReader::new(truncated);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn pax_times() {
+        let before = |millis| UNIX_EPOCH - Duration::from_millis(millis);
+        let subjects = [
+            ("90", Some(UNIX_EPOCH + Duration::from_secs(90))),
+            ("90.25", Some(UNIX_EPOCH + Duration::from_millis(90_250))),
+            ("1.0000000019", Some(UNIX_EPOCH + Duration::new(1, 1))),
+            ("-1.5", Some(before(1_500))),
+            ("-0.5", Some(before(500))),
+            ("-2", Some(before(2_000))),
+            ("18446744073709551615.5", None),
+            ("-18446744073709551615", None),
+            ("-9223372036854775808.5", None),
+            ("18446744073709551616", None),
+            ("1.+5", None),
+            ("--1", None),
+            ("", None),
+        ];
+        for (value, expect) in subjects {
+            assert_eq!(pax_time(value.as_bytes()).ok(), expect, "{}", value);
+        }
+
+        // a time out of range is an error of the member, not a panic
+        let archive = [
+            member("PaxHeader", b'x', b"30 mtime=18446744073709551615\n"),
+            file("a", "x"),
+            end(),
+        ]
+        .concat();
+        let error = TarReader::new(&archive[..]).next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
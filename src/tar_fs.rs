@@ -0,0 +1,465 @@
+//! Export the `struct` [`TarFs`]. Wraps a [FileSystem] and shows the tar archives in it as
+//! directories, so walks descend into them transparently.
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+    vec,
+};
+
+use crate::{FileSystem, FsDirEntry, FsMetadata, MemEntry, MemFileType, MemFs, MemMetadata};
+use crate::{PathItem, StdFs};
+
+/// A [FileSystem] where the `.tar` files of the wrapped one are directories with the members
+/// of the archive inside. Walking it with [ReadDirRecursive][crate::ReadDirRecursive] goes
+/// through the archives as if they had been extracted in place, so the same adaptors filter
+/// the files on disk and the ones in archives.
+///
+/// Members have paths below the path of their archive, such as `dist/app.tar/src/main.rs`,
+/// and the size, mode and modification time stored in the archive. They can be opened, and
+/// their metadata is given by [FsDirEntry::metadata] and [FileSystem::metadata]. Like the
+/// entries of [MemFs], they have no [std::fs::Metadata], so [PathItem::symlink_metadata]
+/// fails on them.
+///
+/// An archive is read whole, with [MemFs::from_tar], the first time something inside it is
+/// needed, and kept until another archive is needed. Archives that can not be read fail to
+/// be listed, with [io::ErrorKind::InvalidData] when they are corrupt. Compressed archives,
+/// and archives inside archives, are left as files.
+///
+/// ## Example
+/// ```no_run
+/// use itfs::{prelude::*, ReadDirRecursive, StdFs, TarFs};
+/// use std::ffi::OsStr;
+///
+/// let extensions = vec![OsStr::new("rs")];
+/// for path in ReadDirRecursive::with_fs("./releases", TarFs::new(StdFs))
+///     .unwrap()
+///     .exclude_component("target")
+///     .allow_extensions(&extensions)
+///     .to_paths()
+///     .drop_errors()
+/// {
+///     println!("{}", path.display());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TarFs<F: FileSystem = StdFs> {
+    inner: F,
+    /// The last archive read.
+    cache: Mutex<Option<(PathBuf, Arc<MemFs>)>>,
+}
+
+impl<F: FileSystem> TarFs<F> {
+    pub fn new(inner: F) -> Self {
+        TarFs {
+            inner,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// The wrapped file system.
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// The path of the archive `path` is in, or is, and the rest of `path` inside it.
+    fn split(&self, path: &Path) -> Option<(PathBuf, PathBuf)> {
+        let mut archive = PathBuf::new();
+        let mut components = path.components();
+        while let Some(component) = components.next() {
+            archive.push(component);
+            if is_archive(component.as_os_str())
+                && self.inner.metadata(&archive).is_ok_and(|m| m.is_file())
+            {
+                return Some((archive, components.as_path().to_path_buf()));
+            }
+        }
+        None
+    }
+
+    /// The members of the archive at `archive`.
+    fn load(&self, archive: &Path) -> io::Result<Arc<MemFs>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((path, fs)) = &*cache {
+            if path == archive {
+                return Ok(fs.clone());
+            }
+        }
+
+        let file = io::BufReader::new(self.inner.open(archive)?);
+        let fs = Arc::new(MemFs::from_tar(file).map_err(|e| in_archive(archive, e))?);
+        *cache = Some((archive.to_path_buf(), fs.clone()));
+        Ok(fs)
+    }
+}
+
+impl<F: FileSystem> FileSystem for TarFs<F> {
+    type Metadata = TarFsMetadata<F::Metadata>;
+    type DirEntry = TarFsEntry<F::DirEntry>;
+    type ReadDir = TarFsReadDir<F::ReadDir, F::DirEntry>;
+    type File = TarFsFile<F::File>;
+
+    fn read_dir(&self, path: &Path) -> io::Result<Self::ReadDir> {
+        let (archive, rest) = match self.split(path) {
+            Some(split) => split,
+            None => return Ok(TarFsReadDir::Disk(self.inner.read_dir(path)?)),
+        };
+        let fs = self.load(&archive)?;
+        let entries: Vec<_> = fs
+            .read_dir(&rest)
+            .map_err(|e| in_archive(&archive, e))?
+            .map(|entry| {
+                entry.map(|entry| TarFsEntry::Member {
+                    path: archive.join(entry.path()),
+                    entry,
+                })
+            })
+            .collect();
+        Ok(TarFsReadDir::Archive(entries.into_iter()))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Self::Metadata> {
+        match self.split(path) {
+            None => Ok(TarFsMetadata::Disk(self.inner.metadata(path)?)),
+            Some((archive, rest)) if rest.as_os_str().is_empty() => {
+                Ok(archive_metadata(&self.inner.metadata(&archive)?))
+            }
+            Some((archive, rest)) => {
+                let fs = self.load(&archive)?;
+                let metadata = fs.metadata(&rest).map_err(|e| in_archive(&archive, e))?;
+                Ok(TarFsMetadata::Member(metadata))
+            }
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Self::Metadata> {
+        match self.split(path) {
+            None => Ok(TarFsMetadata::Disk(self.inner.symlink_metadata(path)?)),
+            Some((archive, rest)) if rest.as_os_str().is_empty() => {
+                let metadata = self.inner.symlink_metadata(&archive)?;
+                match metadata.is_file() {
+                    true => Ok(archive_metadata(&metadata)),
+                    // a symlink to an archive
+                    false => Ok(TarFsMetadata::Disk(metadata)),
+                }
+            }
+            Some((archive, rest)) => {
+                let fs = self.load(&archive)?;
+                let metadata = fs
+                    .symlink_metadata(&rest)
+                    .map_err(|e| in_archive(&archive, e))?;
+                Ok(TarFsMetadata::Member(metadata))
+            }
+        }
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.split(path) {
+            Some((archive, rest)) if !rest.as_os_str().is_empty() => {
+                let fs = self.load(&archive)?;
+                fs.read_link(&rest).map_err(|e| in_archive(&archive, e))
+            }
+            _ => self.inner.read_link(path),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Self::File> {
+        match self.split(path) {
+            None => Ok(TarFsFile::Disk(self.inner.open(path)?)),
+            Some((archive, rest)) => {
+                let fs = self.load(&archive)?;
+                let file = fs.open(&rest).map_err(|e| in_archive(&archive, e))?;
+                Ok(TarFsFile::Member(file))
+            }
+        }
+    }
+}
+
+/// Metadata of a file of a [TarFs]: of the wrapped file system, or of an archive or one of
+/// its members.
+#[derive(Debug, Clone)]
+pub enum TarFsMetadata<M> {
+    Disk(M),
+    /// An archive, as a directory, or a member of one.
+    Member(MemMetadata),
+}
+
+impl<M: FsMetadata> FsMetadata for TarFsMetadata<M> {
+    fn is_dir(&self) -> bool {
+        match self {
+            TarFsMetadata::Disk(m) => m.is_dir(),
+            TarFsMetadata::Member(m) => m.is_dir(),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        match self {
+            TarFsMetadata::Disk(m) => m.is_file(),
+            TarFsMetadata::Member(m) => m.is_file(),
+        }
+    }
+
+    fn is_symlink(&self) -> bool {
+        match self {
+            TarFsMetadata::Disk(m) => m.is_symlink(),
+            TarFsMetadata::Member(m) => m.is_symlink(),
+        }
+    }
+
+    fn len(&self) -> u64 {
+        match self {
+            TarFsMetadata::Disk(m) => m.len(),
+            TarFsMetadata::Member(m) => m.len(),
+        }
+    }
+
+    fn modified(&self) -> io::Result<SystemTime> {
+        match self {
+            TarFsMetadata::Disk(m) => m.modified(),
+            TarFsMetadata::Member(m) => m.modified(),
+        }
+    }
+
+    fn readonly(&self) -> bool {
+        match self {
+            TarFsMetadata::Disk(m) => m.readonly(),
+            TarFsMetadata::Member(m) => m.readonly(),
+        }
+    }
+
+    fn mode(&self) -> Option<u32> {
+        match self {
+            TarFsMetadata::Disk(m) => m.mode(),
+            TarFsMetadata::Member(m) => m.mode(),
+        }
+    }
+
+    fn accessed(&self) -> io::Result<SystemTime> {
+        match self {
+            TarFsMetadata::Disk(m) => m.accessed(),
+            TarFsMetadata::Member(m) => m.accessed(),
+        }
+    }
+
+    fn allocated(&self) -> u64 {
+        match self {
+            TarFsMetadata::Disk(m) => m.allocated(),
+            TarFsMetadata::Member(m) => m.allocated(),
+        }
+    }
+
+    fn inode(&self) -> Option<(u64, u64)> {
+        match self {
+            TarFsMetadata::Disk(m) => m.inode(),
+            TarFsMetadata::Member(m) => m.inode(),
+        }
+    }
+
+    fn uid(&self) -> Option<u32> {
+        match self {
+            TarFsMetadata::Disk(m) => m.uid(),
+            TarFsMetadata::Member(m) => m.uid(),
+        }
+    }
+}
+
+/// An entry of a directory of a [TarFs].
+#[derive(Debug)]
+pub enum TarFsEntry<E> {
+    /// An entry of the wrapped file system.
+    Disk(E),
+    /// An archive of the wrapped file system, which is a directory here.
+    Archive(E),
+    /// A member of an archive, with its path below the one of the archive.
+    Member { path: PathBuf, entry: MemEntry },
+}
+
+impl<E: PathItem> PathItem for TarFsEntry<E> {
+    fn path(&self) -> Cow<'_, Path> {
+        match self {
+            TarFsEntry::Disk(e) | TarFsEntry::Archive(e) => e.path(),
+            TarFsEntry::Member { path, .. } => Cow::Borrowed(path),
+        }
+    }
+
+    fn into_path_buf(self) -> PathBuf {
+        match self {
+            TarFsEntry::Disk(e) | TarFsEntry::Archive(e) => e.into_path_buf(),
+            TarFsEntry::Member { path, .. } => path,
+        }
+    }
+
+    /// The metadata of the file for entries of the wrapped file system, archives included.
+    /// Members of archives have none, and fail with [io::ErrorKind::Unsupported].
+    fn symlink_metadata(&self) -> io::Result<Metadata> {
+        match self {
+            TarFsEntry::Disk(e) | TarFsEntry::Archive(e) => e.symlink_metadata(),
+            TarFsEntry::Member { path, .. } => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{}: is a member of an archive", path.display()),
+            )),
+        }
+    }
+}
+
+impl<E: FsDirEntry> FsDirEntry for TarFsEntry<E> {
+    type Metadata = TarFsMetadata<E::Metadata>;
+
+    fn file_name(&self) -> OsString {
+        match self {
+            TarFsEntry::Disk(e) | TarFsEntry::Archive(e) => e.file_name(),
+            TarFsEntry::Member { entry, .. } => entry.file_name(),
+        }
+    }
+
+    fn metadata(&self) -> io::Result<Self::Metadata> {
+        match self {
+            TarFsEntry::Disk(e) => Ok(TarFsMetadata::Disk(e.metadata()?)),
+            TarFsEntry::Archive(e) => Ok(archive_metadata(&e.metadata()?)),
+            TarFsEntry::Member { entry, .. } => Ok(TarFsMetadata::Member(entry.metadata()?)),
+        }
+    }
+}
+
+/// The entries of a directory of a [TarFs].
+#[derive(Debug)]
+pub enum TarFsReadDir<R, E> {
+    /// A directory of the wrapped file system.
+    Disk(R),
+    /// A directory inside an archive.
+    Archive(vec::IntoIter<io::Result<TarFsEntry<E>>>),
+}
+
+impl<E: FsDirEntry, R: Iterator<Item = io::Result<E>>> Iterator for TarFsReadDir<R, E> {
+    type Item = io::Result<TarFsEntry<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TarFsReadDir::Disk(read_dir) => Some(read_dir.next()?.map(|entry| {
+                let archive =
+                    is_archive(&entry.file_name()) && entry.metadata().is_ok_and(|m| m.is_file());
+                match archive {
+                    true => TarFsEntry::Archive(entry),
+                    false => TarFsEntry::Disk(entry),
+                }
+            })),
+            TarFsReadDir::Archive(entries) => entries.next(),
+        }
+    }
+}
+
+/// A file of a [TarFs] open for reading.
+#[derive(Debug)]
+pub enum TarFsFile<R> {
+    Disk(R),
+    Member(io::Cursor<Vec<u8>>),
+}
+
+impl<R: io::Read> io::Read for TarFsFile<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            TarFsFile::Disk(file) => file.read(buf),
+            TarFsFile::Member(file) => file.read(buf),
+        }
+    }
+}
+
+/// `true` for the names of the files shown as directories.
+fn is_archive(name: &OsStr) -> bool {
+    name.to_str()
+        .is_some_and(|name| name.to_ascii_lowercase().ends_with(".tar"))
+}
+
+/// Metadata of an archive as a directory: the permissions and time of the file, with the
+/// search permission wherever it can be read.
+fn archive_metadata<M, D: FsMetadata>(file: &D) -> TarFsMetadata<M> {
+    let mode = file.mode().unwrap_or(0o644) & 0o777;
+    TarFsMetadata::Member(MemMetadata {
+        file_type: MemFileType::Dir,
+        len: 0,
+        mode: mode | (mode & 0o444) >> 2,
+        modified: file.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+    })
+}
+
+/// `error`, which happened inside `archive`, naming it.
+fn in_archive(archive: &Path, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", archive.display(), error))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{ffi::OsStr, io::Read, path::Path};
+
+    use crate::{
+        prelude::*,
+        tar::build::{end, file, link, member},
+        FileSystem, FsDirEntry, FsMetadata, MemFs, ReadDirRecursive,
+    };
+
+    use super::{TarFs, TarFsEntry};
+
+    #[test]
+    fn descend() {
+        let archive = [
+            member("./src/", b'5', b""),
+            file("./src/main.rs", "fn main() {}"),
+            file("./README.md", "# App"),
+            link("./src/lib.rs", b'1', "./src/main.rs"),
+            end(),
+        ]
+        .concat();
+
+        let mut disk = MemFs::new();
+        disk.file("dist/app.tar", &archive).unwrap();
+        disk.file("dist/bad.tar", "not an archive").unwrap();
+        disk.file("dist/notes.rs", "").unwrap();
+        let fs = TarFs::new(&disk);
+
+        let extensions = vec![OsStr::new("rs")];
+        let mut errors = vec![];
+        let mut paths: Vec<_> = ReadDirRecursive::with_fs("dist", &fs)
+            .unwrap()
+            .allow_extensions(&extensions)
+            .to_paths()
+            .collect_errors(&mut errors)
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("dist/app.tar/src/lib.rs"),
+                Path::new("dist/app.tar/src/main.rs"),
+                Path::new("dist/notes.rs"),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().starts_with("dist/bad.tar: tar: "));
+
+        let mut contents = String::new();
+        fs.open(Path::new("dist/app.tar/src/lib.rs"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "fn main() {}");
+        let metadata = fs.metadata(Path::new("dist/app.tar/README.md")).unwrap();
+        assert_eq!((metadata.is_file(), metadata.len()), (true, 5));
+        assert!(fs.metadata(Path::new("dist/app.tar")).unwrap().is_dir());
+
+        // archives are listed as such, and are plain files without the wrapper
+        let entry = fs
+            .read_dir(Path::new("dist"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert!(matches!(entry, TarFsEntry::Archive(_)));
+        assert!(entry.metadata().unwrap().is_dir());
+        let walked = ReadDirRecursive::with_fs("dist", &disk).unwrap().count();
+        assert_eq!(walked, 3);
+    }
+}
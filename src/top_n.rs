@@ -0,0 +1,270 @@
+//! Export the `struct` [`TopN`]. Consumes the output of a walker and keeps the N files with
+//! the highest key (largest, newest, oldest, ...) using a bounded amount of memory.
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{FileSystem, FsMetadata, MaybeOk, PathItem, StdFs};
+
+/// A file kept by [TopN], with the key it was ranked by.
+#[derive(Debug, Clone)]
+pub struct Ranked<K, M = Metadata> {
+    pub key: K,
+    pub path: PathBuf,
+    pub metadata: M,
+}
+
+/// Entry of the heap. Ordered by key and, for equal keys, the file seen first is the greater,
+/// so it is kept over the ones that come after it.
+struct Slot<K, M> {
+    seq: u64,
+    ranked: Ranked<K, M>,
+}
+
+impl<K: Ord, M> Ord for Slot<K, M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ranked
+            .key
+            .cmp(&other.ranked.key)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl<K: Ord, M> PartialOrd for Slot<K, M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, M> PartialEq for Slot<K, M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, M> Eq for Slot<K, M> {}
+
+/// Key function of the [TopN] built by the provided constructors.
+pub type KeyFn<K> = fn(&Path, &dyn FsMetadata) -> Option<K>;
+
+/// Keeps the `n` files with the highest key out of everything it is fed, in a single pass and
+/// never holding more than `n` of them, thanks to a bounded heap.
+///
+/// Feed it with [Extend::extend] from an iterator over items of type
+/// [DirEntry][std::fs::DirEntry], `Result<DirEntry>`, [PathBuf], `Result<PathBuf>` or any
+/// other [PathItem] (errors are kept aside, see
+/// [TopN::errors]), then get the result with [TopN::into_sorted_vec]. Metadata never follows
+/// symlinks. Files for which the key function returns [None] are skipped, and among files with
+/// the same key the first one seen wins.
+///
+/// The metadata of the files is read from the disk by default. Use [TopN::with_fs] to read
+/// it from any other [FileSystem], such as the one the walker runs over.
+///
+/// ## Example
+/// ```
+/// use itfs::{ReadDirRecursive, TopN};
+///
+/// let mut largest = TopN::largest(5);
+/// largest.extend(ReadDirRecursive::new("./src").unwrap());
+///
+/// for file in largest.into_sorted_vec() {
+///     println!("{:>8} {:?}", file.key, file.path);
+/// }
+/// ```
+pub struct TopN<K, F, S: FileSystem = StdFs> {
+    n: usize,
+    key: F,
+    /// The file system the metadata of the files is read from.
+    fs: S,
+    /// The files kept so far, the lowest one on top.
+    heap: BinaryHeap<Reverse<Slot<K, S::Metadata>>>,
+    seq: u64,
+    errors: Vec<io::Error>,
+}
+
+impl TopN<u64, KeyFn<u64>> {
+    /// The `n` largest files.
+    pub fn largest(n: usize) -> Self {
+        TopN::by_key(n, |_, meta| Some(meta.len()))
+    }
+}
+
+impl TopN<SystemTime, KeyFn<SystemTime>> {
+    /// The `n` most recently modified files.
+    pub fn newest(n: usize) -> Self {
+        TopN::by_key(n, |_, meta| meta.modified().ok())
+    }
+
+    /// The `n` most recently accessed files. Access times may not be updated at all,
+    /// depending on the file system and its mount options.
+    pub fn recently_accessed(n: usize) -> Self {
+        TopN::by_key(n, |_, meta| meta.accessed().ok())
+    }
+}
+
+impl TopN<Reverse<SystemTime>, KeyFn<Reverse<SystemTime>>> {
+    /// The `n` least recently modified files.
+    pub fn oldest(n: usize) -> Self {
+        TopN::by_key(n, |_, meta| meta.modified().ok().map(Reverse))
+    }
+}
+
+impl<K, F> TopN<K, F>
+where
+    K: Ord,
+    F: FnMut(&Path, &dyn FsMetadata) -> Option<K>,
+{
+    /// The `n` files with the highest key, as computed by the `key` function. Wrap the key in
+    /// [Reverse] to keep the lowest ones instead.
+    pub fn by_key(n: usize, key: F) -> Self {
+        TopN {
+            n,
+            key,
+            fs: StdFs,
+            heap: BinaryHeap::with_capacity(n.saturating_add(1).min(1024)),
+            seq: 0,
+            errors: vec![],
+        }
+    }
+}
+
+impl<K, F, S> TopN<K, F, S>
+where
+    K: Ord,
+    F: FnMut(&Path, &dyn FsMetadata) -> Option<K>,
+    S: FileSystem,
+{
+    /// Read the metadata of the files from the given [FileSystem] instead of the disk. Use it
+    /// before adding files: the files kept so far are dropped, the errors are kept.
+    pub fn with_fs<G: FileSystem>(self, fs: G) -> TopN<K, F, G> {
+        TopN {
+            n: self.n,
+            key: self.key,
+            fs,
+            heap: BinaryHeap::with_capacity(self.n.saturating_add(1).min(1024)),
+            seq: 0,
+            errors: self.errors,
+        }
+    }
+
+    /// Consider a single file given its metadata.
+    pub fn add(&mut self, path: PathBuf, metadata: S::Metadata) {
+        let key = match (self.key)(&path, &metadata) {
+            Some(key) => key,
+            None => return,
+        };
+
+        self.seq += 1;
+        self.heap.push(Reverse(Slot {
+            seq: self.seq,
+            ranked: Ranked {
+                key,
+                path,
+                metadata,
+            },
+        }));
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
+    }
+
+    /// Errors found while adding files: errors yielded by the walker and files whose
+    /// metadata could not be read.
+    pub fn errors(&self) -> &[io::Error] {
+        &self.errors
+    }
+
+    /// The files kept, highest key first.
+    pub fn into_sorted_vec(self) -> Vec<Ranked<K, S::Metadata>> {
+        // ascending order of `Reverse` is descending order of the slots
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(slot)| slot.ranked)
+            .collect()
+    }
+
+    fn add_result(&mut self, item: io::Result<(PathBuf, S::Metadata)>) {
+        match item {
+            Ok((path, meta)) => self.add(path, meta),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+/// Supports iterators over any [MaybeOk] items whose errors are [io::Error]s
+impl<K, F, S, T> Extend<T> for TopN<K, F, S>
+where
+    K: Ord,
+    F: FnMut(&Path, &dyn FsMetadata) -> Option<K>,
+    S: FileSystem,
+    T: MaybeOk<Error = io::Error>,
+{
+    fn extend<J: IntoIterator<Item = T>>(&mut self, iter: J) {
+        for item in iter {
+            let item = item.into_result().and_then(|item| {
+                let meta = self.fs.item_metadata(&item)?;
+                Ok((item.into_path_buf(), meta))
+            });
+            self.add_result(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cmp::Reverse, path::PathBuf};
+
+    use super::TopN;
+    use crate::{EntryToPath, MemFs, ReadDirRecursive};
+
+    #[test]
+    fn same_as_sorting_everything() {
+        let paths: Vec<PathBuf> = EntryToPath(ReadDirRecursive::new("./src").unwrap())
+            .map(|p| p.unwrap())
+            .collect();
+
+        // the longest paths, ties broken by the order of the walk
+        let mut sorted: Vec<(usize, PathBuf)> = paths
+            .iter()
+            .map(|p| (p.as_os_str().len(), p.clone()))
+            .collect();
+        sorted.sort_by_key(|(len, _)| Reverse(*len));
+
+        for n in [0, 1, 3, paths.len(), paths.len() + 5] {
+            let mut top = TopN::by_key(n, |path, _| Some(path.as_os_str().len()));
+            top.extend(paths.clone());
+            let top: Vec<(usize, PathBuf)> = top
+                .into_sorted_vec()
+                .into_iter()
+                .map(|r| (r.key, r.path))
+                .collect();
+            assert_eq!(top, sorted[..n.min(sorted.len())]);
+        }
+    }
+
+    #[test]
+    fn other_file_system() {
+        let mut fs = MemFs::new();
+        fs.file("a", [0; 3]).unwrap();
+        fs.file("b", [0; 30]).unwrap();
+        fs.file("c", [0; 10]).unwrap();
+
+        let mut largest = TopN::largest(2).with_fs(&fs);
+        largest.extend(ReadDirRecursive::with_fs("", &fs).unwrap());
+        let kept: Vec<_> = largest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|r| (r.key, r.path))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![(30, PathBuf::from("b")), (10, PathBuf::from("c"))]
+        );
+    }
+}
@@ -0,0 +1,670 @@
+//! Export the `struct` [`Transaction`]. Applies a batch of file operations while recording
+//! them in an on-disk journal, so they can be rolled back on failure or recovered after an
+//! interruption.
+use std::{
+    error, fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::ops::copy_entry;
+
+/// Name of the journal file inside the journal directory.
+const JOURNAL: &str = "journal";
+/// Name of the directory, inside the journal directory, where replaced or removed entries
+/// are kept until the transaction is committed.
+const BACKUP: &str = "backup";
+/// First line of every journal.
+const HEADER: &str = "itfs-journal 1";
+/// Line that follows the list of operations, so a list cut short by a crash is recognized.
+const END_OF_PLAN: &str = "end";
+
+/// What the journal records when an operation starts, to know how to undo it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Begin {
+    /// Nothing was there before the operation.
+    Clean,
+    /// The entry the operation replaces or removes was set aside.
+    Backup,
+    /// The directory to create already existed, so it is not the operation's to remove.
+    Existing,
+}
+
+impl Begin {
+    fn as_str(self) -> &'static str {
+        match self {
+            Begin::Clean => "-",
+            Begin::Backup => "backup",
+            Begin::Existing => "existing",
+        }
+    }
+}
+
+/// A single operation of a [Transaction].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxOp {
+    /// Rename (move) a file, symlink or directory. An existing `to` is replaced.
+    Rename { from: PathBuf, to: PathBuf },
+    /// Copy a file or symlink. An existing `to` is replaced.
+    Copy { from: PathBuf, to: PathBuf },
+    /// Create a directory. Its parent must exist. A directory that already exists is left
+    /// alone, and is not removed by a rollback.
+    CreateDir(PathBuf),
+    /// Remove a file, symlink or directory (with all its content).
+    Remove(PathBuf),
+}
+
+impl fmt::Display for TxOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxOp::Rename { from, to } => {
+                write!(f, "rename {} -> {}", from.display(), to.display())
+            }
+            TxOp::Copy { from, to } => write!(f, "copy {} -> {}", from.display(), to.display()),
+            TxOp::CreateDir(path) => write!(f, "mkdir {}", path.display()),
+            TxOp::Remove(path) => write!(f, "remove {}", path.display()),
+        }
+    }
+}
+
+/// Error returned when an operation of a [Transaction] fails.
+#[derive(Debug)]
+pub struct TxError {
+    /// Index of the operation that failed.
+    pub index: usize,
+    /// The operation that failed, or [None] if it was the journal itself.
+    pub op: Option<TxOp>,
+    pub error: io::Error,
+    /// Result of the automatic rollback that followed the failure. If it failed too, the
+    /// journal is kept so the rollback can be retried with [Transaction::recover].
+    pub rollback: io::Result<()>,
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.op {
+            Some(op) => write!(
+                f,
+                "operation #{} ({}) failed: {}",
+                self.index, op, self.error
+            )?,
+            None => write!(f, "journal failed: {}", self.error)?,
+        }
+        match &self.rollback {
+            Ok(()) => write!(f, " (rolled back)"),
+            Err(e) => write!(f, " (rollback failed: {})", e),
+        }
+    }
+}
+
+impl error::Error for TxError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A batch of file operations applied all together or not at all.
+///
+/// Before anything is touched, the whole list of operations is written to a journal inside a
+/// directory of your choice. Then, for each operation, the journal records when it starts and
+/// when it is done, and every entry the operation would replace or remove is moved aside into
+/// the journal directory instead of being deleted. That makes it possible to:
+///
+/// - roll back every applied step when an operation fails ([Transaction::commit] does it
+///   automatically),
+/// - pick up an interrupted transaction on the next run with [Transaction::recover], and then
+///   either [roll it back][Transaction::rollback] or [finish it][Transaction::resume].
+///
+/// Once every operation succeeded, the journal and the entries set aside are deleted.
+///
+/// The journal directory should be on the same filesystem as the files being operated on,
+/// since entries are moved into it with [fs::rename].
+///
+/// ## Example
+/// ```
+/// use itfs::{Transaction, TxOp};
+///
+/// let dir = std::env::temp_dir().join("itfs-transaction-doc");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// std::fs::write(dir.join("a.txt"), "a").unwrap();
+///
+/// // finish (or undo) whatever a previous run left behind
+/// if let Some(pending) = Transaction::recover(dir.join(".journal")).unwrap() {
+///     pending.rollback().unwrap();
+/// }
+///
+/// let mut tx = Transaction::new(dir.join(".journal")).unwrap();
+/// tx.push(TxOp::Rename { from: dir.join("a.txt"), to: dir.join("b.txt") });
+/// tx.push(TxOp::Rename { from: dir.join("missing.txt"), to: dir.join("c.txt") });
+///
+/// // the second rename fails, so the first one is undone
+/// assert!(tx.commit().is_err());
+/// assert!(dir.join("a.txt").exists());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct Transaction {
+    dir: PathBuf,
+    ops: Vec<TxOp>,
+    /// For every operation that was started, what was there before it.
+    started: Vec<Begin>,
+    /// Number of operations known to be done.
+    done: usize,
+    journal: Option<fs::File>,
+}
+
+impl Transaction {
+    /// Create an empty transaction that will keep its journal in `journal_dir`, which is
+    /// created if needed. This operation fails with [io::ErrorKind::AlreadyExists] if the
+    /// directory holds the journal of an unfinished transaction: see [Transaction::recover].
+    pub fn new<P: AsRef<Path>>(journal_dir: P) -> io::Result<Self> {
+        let dir = journal_dir.as_ref().to_path_buf();
+        if dir.join(JOURNAL).exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("unfinished transaction in {}", dir.display()),
+            ));
+        }
+
+        Ok(Transaction {
+            dir,
+            ops: vec![],
+            started: vec![],
+            done: 0,
+            journal: None,
+        })
+    }
+
+    /// Add an operation at the end of the transaction.
+    pub fn push(&mut self, op: TxOp) -> &mut Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// The operations of the transaction.
+    pub fn ops(&self) -> &[TxOp] {
+        &self.ops
+    }
+
+    /// Number of operations already applied. Only meaningful for a transaction returned by
+    /// [Transaction::recover].
+    pub fn completed(&self) -> usize {
+        self.done
+    }
+
+    /// Apply every operation. If one fails, every step applied so far is rolled back and the
+    /// error is returned.
+    pub fn commit(mut self) -> Result<(), TxError> {
+        if let Err(error) = self.write_plan() {
+            // never clean up the journal of another transaction
+            let rollback = match error.kind() {
+                io::ErrorKind::AlreadyExists => Ok(()),
+                _ => self.cleanup(),
+            };
+            return Err(TxError {
+                index: 0,
+                op: None,
+                error,
+                rollback,
+            });
+        }
+        self.run()
+    }
+
+    /// Look for the journal of an unfinished transaction in `journal_dir`. Returns [None] if
+    /// there is nothing to recover, including a journal whose list of operations was cut short
+    /// (nothing was applied yet, so it is just deleted), otherwise the transaction, which must
+    /// then be either [rolled back][Transaction::rollback] or [finished][Transaction::resume].
+    pub fn recover<P: AsRef<Path>>(journal_dir: P) -> io::Result<Option<Self>> {
+        let dir = journal_dir.as_ref().to_path_buf();
+        let file = match fs::File::open(dir.join(JOURNAL)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut tx = Transaction {
+            dir,
+            ops: vec![],
+            started: vec![],
+            done: 0,
+            journal: None,
+        };
+        let mut planned = false;
+        let mut committed = false;
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                [HEADER] if i == 0 => {}
+                _ if i == 0 => return Err(invalid(format!("not a journal: {:?}", line))),
+                ["op", "rename", from, to] if !planned => tx.ops.push(TxOp::Rename {
+                    from: decode_path(from)?,
+                    to: decode_path(to)?,
+                }),
+                ["op", "copy", from, to] if !planned => tx.ops.push(TxOp::Copy {
+                    from: decode_path(from)?,
+                    to: decode_path(to)?,
+                }),
+                ["op", "mkdir", path] if !planned => {
+                    tx.ops.push(TxOp::CreateDir(decode_path(path)?))
+                }
+                ["op", "remove", path] if !planned => tx.ops.push(TxOp::Remove(decode_path(path)?)),
+                [END_OF_PLAN] => planned = true,
+                // the plan was cut short
+                _ if !planned => break,
+                ["begin", "-"] => tx.started.push(Begin::Clean),
+                ["begin", "backup"] => tx.started.push(Begin::Backup),
+                ["begin", "existing"] => tx.started.push(Begin::Existing),
+                ["done"] => tx.done += 1,
+                ["commit"] => committed = true,
+                // a line cut short by a crash can only be the last one
+                _ => break,
+            }
+        }
+
+        if committed || !planned {
+            // only the cleanup was left, or the crash happened while writing the plan, before
+            // any operation was started
+            tx.cleanup()?;
+            return Ok(None);
+        }
+
+        tx.journal = Some(OpenOptions::new().append(true).open(tx.dir.join(JOURNAL))?);
+        Ok(Some(tx))
+    }
+
+    /// Undo every step applied so far, including a step that may have been interrupted
+    /// halfway, and delete the journal.
+    pub fn rollback(mut self) -> io::Result<()> {
+        self.undo()
+    }
+
+    /// Apply the operations that were not applied yet, redoing the one that may have been
+    /// interrupted halfway. If one fails, the whole transaction is rolled back.
+    pub fn resume(self) -> Result<(), TxError> {
+        self.run()
+    }
+
+    fn journal_line(&mut self, line: &str) -> io::Result<()> {
+        let journal = self.journal.as_mut().expect("the journal is open");
+        writeln!(journal, "{}", line)?;
+        journal.sync_data()
+    }
+
+    fn backup_path(&self, index: usize) -> PathBuf {
+        self.dir.join(BACKUP).join(index.to_string())
+    }
+
+    /// Create the journal and write the list of operations into it.
+    fn write_plan(&mut self) -> io::Result<()> {
+        fs::create_dir_all(self.dir.join(BACKUP))?;
+        let mut plan = vec![HEADER.to_string()];
+        for op in &self.ops {
+            plan.push(match op {
+                TxOp::Rename { from, to } => {
+                    format!("op\trename\t{}\t{}", encode_path(from)?, encode_path(to)?)
+                }
+                TxOp::Copy { from, to } => {
+                    format!("op\tcopy\t{}\t{}", encode_path(from)?, encode_path(to)?)
+                }
+                TxOp::CreateDir(path) => format!("op\tmkdir\t{}", encode_path(path)?),
+                TxOp::Remove(path) => format!("op\tremove\t{}", encode_path(path)?),
+            });
+        }
+        plan.push(END_OF_PLAN.to_string());
+
+        let mut journal = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.dir.join(JOURNAL))?;
+        journal.write_all((plan.join("\n") + "\n").as_bytes())?;
+        journal.sync_all()?;
+        self.journal = Some(journal);
+        Ok(())
+    }
+
+    /// Apply the operations from the first one not done yet.
+    fn run(mut self) -> Result<(), TxError> {
+        for index in self.done..self.ops.len() {
+            if let Err(error) = self.step(index) {
+                let op = Some(self.ops[index].clone());
+                let rollback = self.undo();
+                return Err(TxError {
+                    index,
+                    op,
+                    error,
+                    rollback,
+                });
+            }
+        }
+
+        let finish = self.journal_line("commit").and_then(|_| self.cleanup());
+        finish.map_err(|error| TxError {
+            index: self.ops.len(),
+            op: None,
+            error,
+            rollback: Ok(()),
+        })
+    }
+
+    /// Apply (or redo, after an interruption) a single operation.
+    fn step(&mut self, index: usize) -> io::Result<()> {
+        let op = self.ops[index].clone();
+        let backup = self.backup_path(index);
+
+        // the entry the operation replaces or removes, if any
+        let replaced = match &op {
+            TxOp::Rename { to, .. } | TxOp::Copy { to, .. } => Some(to),
+            TxOp::Remove(path) => Some(path),
+            TxOp::CreateDir(_) => None,
+        };
+
+        if self.started.len() == index {
+            let begin = match &op {
+                TxOp::CreateDir(path) if path.is_dir() => Begin::Existing,
+                _ if replaced.is_some_and(|p| exists(p)) => Begin::Backup,
+                _ => Begin::Clean,
+            };
+            self.journal_line(&format!("begin\t{}", begin.as_str()))?;
+            self.started.push(begin);
+        }
+
+        // set aside the entry that is going to be replaced or removed
+        if self.started[index] == Begin::Backup && !exists(&backup) {
+            if let Some(replaced) = replaced.filter(|p| exists(p)) {
+                fs::rename(replaced, &backup)?;
+            }
+        }
+
+        match &op {
+            TxOp::Rename { from, to } => {
+                if exists(from) {
+                    fs::rename(from, to)?;
+                } else if !exists(to) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} does not exist", from.display()),
+                    ));
+                }
+            }
+            TxOp::Copy { from, to } => {
+                if exists(to) {
+                    fs::remove_file(to)?; // partial copy of an interrupted run
+                }
+                copy_entry(from, to)?;
+            }
+            TxOp::CreateDir(path) => match fs::create_dir(path) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => {}
+                result => result?,
+            },
+            TxOp::Remove(path) => {
+                if self.started[index] != Begin::Backup {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} does not exist", path.display()),
+                    ));
+                }
+            }
+        }
+
+        self.journal_line("done")?;
+        self.done = index + 1;
+        Ok(())
+    }
+
+    /// Undo every started operation, last to first, then delete the journal.
+    fn undo(&mut self) -> io::Result<()> {
+        for index in (0..self.started.len()).rev() {
+            let backup = self.backup_path(index);
+
+            match &self.ops[index] {
+                TxOp::Rename { from, to } => {
+                    // the rename happened if `to` is there: with a backup it was set aside
+                    // first, without one it did not exist before
+                    if exists(to) && !exists(from) {
+                        fs::rename(to, from)?;
+                    }
+                }
+                TxOp::Copy { to, .. } => {
+                    if exists(to) && (exists(&backup) || self.started[index] == Begin::Clean) {
+                        fs::remove_file(to)?;
+                    }
+                }
+                TxOp::CreateDir(path) => {
+                    // only remove the directory if this operation created it
+                    if self.started[index] == Begin::Clean && path.is_dir() {
+                        fs::remove_dir(path)?;
+                    }
+                }
+                TxOp::Remove(_) => {}
+            }
+
+            // bring back whatever was set aside
+            if exists(&backup) {
+                let original = match &self.ops[index] {
+                    TxOp::Rename { to, .. } | TxOp::Copy { to, .. } => to,
+                    TxOp::Remove(path) | TxOp::CreateDir(path) => path,
+                };
+                fs::rename(&backup, original)?;
+            }
+        }
+
+        self.started.clear();
+        self.done = 0;
+        self.cleanup()
+    }
+
+    /// Delete the journal and whatever is left in the backup directory.
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.journal = None;
+        match fs::remove_dir_all(self.dir.join(BACKUP)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        match fs::remove_file(self.dir.join(JOURNAL)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => {
+                // only succeeds if nothing else lives there
+                let _ = fs::remove_dir(&self.dir);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether something (possibly a dangling symlink) exists at the path.
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Raw bytes of a path. Only unix paths can hold bytes that are not valid unicode.
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(path.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    match path.to_str() {
+        Some(s) => Ok(s.as_bytes().to_vec()),
+        None => Err(invalid(format!("path is not valid unicode: {:?}", path))),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> io::Result<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> io::Result<PathBuf> {
+    String::from_utf8(bytes)
+        .map(PathBuf::from)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// Encode a path so it fits in a single journal field: bytes outside of printable ascii,
+/// as well as `%`, are written as `%XX`.
+pub(crate) fn encode_path(path: &Path) -> io::Result<String> {
+    let mut encoded = String::new();
+    for byte in path_bytes(path)? {
+        match byte {
+            b'%' => encoded.push_str("%25"),
+            b' '..=b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Ok(encoded)
+}
+
+pub(crate) fn decode_path(encoded: &str) -> io::Result<PathBuf> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| invalid(format!("bad escape in {:?}", encoded)))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    path_from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use super::{decode_path, encode_path, Transaction, TxOp};
+
+    #[test]
+    fn path_encoding() {
+        for path in [
+            "/a/b",
+            "with space",
+            "tab\there",
+            "new\nline",
+            "100%",
+            "ñandú",
+        ] {
+            let encoded = encode_path(path.as_ref()).unwrap();
+            assert!(!encoded.contains(['\t', '\n']));
+            assert_eq!(decode_path(&encoded).unwrap(), PathBuf::from(path));
+        }
+    }
+
+    /// Leave `dir` as a run of "rename `a` over `b`, then remove `b`" interrupted right
+    /// after the rename: the journal, the original `b` set aside and `a` moved over it.
+    fn crash_after_rename(dir: &Path, journal: &Path) {
+        fs::create_dir_all(journal.join("backup")).unwrap();
+        fs::rename(dir.join("b"), journal.join("backup").join("0")).unwrap();
+        fs::rename(dir.join("a"), dir.join("b")).unwrap();
+        let (a, b) = (
+            encode_path(&dir.join("a")).unwrap(),
+            encode_path(&dir.join("b")).unwrap(),
+        );
+        let lines = [
+            "itfs-journal 1".to_string(),
+            format!("op\trename\t{}\t{}", a, b),
+            format!("op\tremove\t{}", b),
+            "end".to_string(),
+            "begin\tbackup".to_string(),
+            "done".to_string(),
+        ];
+        fs::write(journal.join("journal"), lines.join("\n") + "\n").unwrap();
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("itfs-tx-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn recover_interrupted() {
+        let dir = temp_dir("recover");
+        let journal = dir.join(".journal");
+        let reset = || {
+            fs::write(dir.join("a"), "a").unwrap();
+            fs::write(dir.join("b"), "b").unwrap();
+        };
+
+        reset();
+        crash_after_rename(&dir, &journal);
+        assert!(Transaction::new(&journal).is_err());
+
+        // roll it back: `b` is back to its original content
+        let tx = Transaction::recover(&journal).unwrap().unwrap();
+        assert_eq!(tx.completed(), 1);
+        assert_eq!(tx.ops().len(), 2);
+        tx.rollback().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+        assert!(Transaction::recover(&journal).unwrap().is_none());
+
+        // crash again, and finish it this time
+        crash_after_rename(&dir, &journal);
+        Transaction::recover(&journal)
+            .unwrap()
+            .unwrap()
+            .resume()
+            .unwrap();
+        assert!(!dir.join("a").exists());
+        assert!(!dir.join("b").exists());
+        assert!(!journal.exists());
+
+        // a plan cut short is discarded: no operation could have started
+        reset();
+        crash_after_rename(&dir, &journal);
+        fs::rename(journal.join("backup").join("0"), dir.join("a")).unwrap();
+        let text = fs::read_to_string(journal.join("journal")).unwrap();
+        let truncated: Vec<&str> = text.lines().take(2).collect();
+        fs::write(journal.join("journal"), truncated.join("\n")).unwrap();
+        assert!(Transaction::recover(&journal).unwrap().is_none());
+        assert!(!journal.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn existing_dir_survives_rollback() {
+        let dir = temp_dir("mkdir");
+        fs::create_dir(dir.join("full")).unwrap();
+        fs::write(dir.join("full").join("kept"), "").unwrap();
+        fs::create_dir(dir.join("empty")).unwrap();
+
+        let mut tx = Transaction::new(dir.join(".journal")).unwrap();
+        tx.push(TxOp::CreateDir(dir.join("full")));
+        tx.push(TxOp::CreateDir(dir.join("empty")));
+        tx.push(TxOp::CreateDir(dir.join("new")));
+        tx.push(TxOp::Remove(dir.join("missing")));
+
+        let error = tx.commit().unwrap_err();
+        assert_eq!(error.index, 3);
+        assert!(error.rollback.is_ok());
+        assert!(dir.join("full").join("kept").exists());
+        assert!(dir.join("empty").is_dir());
+        assert!(!dir.join("new").exists());
+        assert!(!dir.join(".journal").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
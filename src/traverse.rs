@@ -0,0 +1,193 @@
+//! Export the `struct` [`Traverse`]. Recursive iterator similar to
+//! [ReadDirRecursive][crate::ReadDirRecursive] that also yields directories, either before
+//! (pre-order) or after (post-order) their content.
+use std::{
+    collections::{HashSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::contents::{inode_key, with_path};
+
+/// When [Traverse] yields a directory relative to its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// A directory is yielded before its content. Handy to create directories before
+    /// copying anything into them.
+    Pre,
+    /// A directory is yielded after its content. Handy to delete a directory once it is empty.
+    Post,
+}
+
+/// Recursive iterator over the entries of a directory, directories included. Unlike
+/// [ReadDirRecursive][crate::ReadDirRecursive], the content of a directory is visited right
+/// after (or right before, with [Order::Post]) the directory itself.
+///
+/// The root directory is not yielded. Symlinks are yielded but never followed unless
+/// [Traverse::follow_symlinks] is used. Errors reading a directory carry its path in
+/// their message.
+///
+/// ## Example:
+/// ```
+/// use itfs::Traverse;
+///
+/// for entry in Traverse::new("./src").unwrap().post_order() {
+///     let entry = entry.unwrap();
+///     println!("{:?} (dir: {})", entry.path(), entry.file_type().unwrap().is_dir());
+/// }
+/// ```
+pub struct Traverse {
+    order: Order,
+    follow_symlinks: bool,
+    /// Directories being read, the innermost last. Each one has the entry of the directory,
+    /// to be yielded once it is done in post-order ([None] for the root).
+    stack: Vec<(fs::ReadDir, Option<fs::DirEntry>)>,
+    /// Items to yield before reading any further.
+    pending: VecDeque<io::Result<fs::DirEntry>>,
+    /// Directories already visited when following symlinks, real ones included, so none is
+    /// visited twice.
+    visited: HashSet<(u64, u64)>,
+}
+
+impl Traverse {
+    /// Create a new instance of [Traverse] (pre-order, not following symlinks) for the given
+    /// path. This operation will fail if the initial call to [fs::read_dir] fails.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut visited = HashSet::new();
+        if let Some(key) = inode_key(&fs::metadata(path)?) {
+            visited.insert(key);
+        }
+
+        Ok(Traverse {
+            order: Order::Pre,
+            follow_symlinks: false,
+            stack: vec![(fs::read_dir(path).map_err(|e| with_path(path, e))?, None)],
+            pending: VecDeque::new(),
+            visited,
+        })
+    }
+
+    /// Yield directories after their content.
+    pub fn post_order(mut self) -> Self {
+        self.order = Order::Post;
+        self
+    }
+
+    /// Descend into symlinks to directories. A directory already visited, through a symlink
+    /// or not, is not visited again, so symlink cycles are harmless.
+    pub fn follow_symlinks(mut self) -> Self {
+        self.follow_symlinks = true;
+        self
+    }
+
+    /// Returns the content of the entry if it is a directory that has to be visited.
+    fn open_dir(&mut self, entry: &fs::DirEntry) -> io::Result<Option<fs::ReadDir>> {
+        let file_type = entry.file_type()?;
+        let path: PathBuf = entry.path();
+
+        let is_dir = match file_type.is_symlink() {
+            true if self.follow_symlinks => match fs::metadata(&path) {
+                Ok(meta) if meta.is_dir() => self.first_visit(&meta),
+                // dangling links are yielded as they are
+                _ => false,
+            },
+            true => false,
+            // a symlink followed earlier may have led here already
+            false if file_type.is_dir() && self.follow_symlinks => {
+                self.first_visit(&entry.metadata()?)
+            }
+            false => file_type.is_dir(),
+        };
+
+        match is_dir {
+            true => fs::read_dir(&path)
+                .map(Some)
+                .map_err(|e| with_path(&path, e)),
+            false => Ok(None),
+        }
+    }
+
+    /// Record a directory as visited. Returns `false` if it already was.
+    fn first_visit(&mut self, meta: &fs::Metadata) -> bool {
+        match inode_key(meta) {
+            Some(key) => self.visited.insert(key),
+            None => true,
+        }
+    }
+}
+
+impl Iterator for Traverse {
+    type Item = io::Result<fs::DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                break Some(item);
+            }
+
+            let (read_dir, _) = self.stack.last_mut()?;
+            match read_dir.next() {
+                Some(Ok(entry)) => match (self.open_dir(&entry), self.order) {
+                    (Ok(Some(read_dir)), Order::Pre) => {
+                        self.stack.push((read_dir, None));
+                        break Some(Ok(entry));
+                    }
+                    (Ok(Some(read_dir)), Order::Post) => {
+                        self.stack.push((read_dir, Some(entry)));
+                        continue;
+                    }
+                    (Ok(None), _) => break Some(Ok(entry)),
+                    // the directory can not be read, yield it anyway along with the error
+                    (Err(e), Order::Pre) => {
+                        self.pending.push_back(Err(e));
+                        break Some(Ok(entry));
+                    }
+                    (Err(e), Order::Post) => {
+                        self.pending.push_back(Ok(entry));
+                        break Some(Err(e));
+                    }
+                },
+                Some(Err(e)) => break Some(Err(e)),
+                None => {
+                    // this directory is done
+                    if let Some((_, Some(dir_entry))) = self.stack.pop() {
+                        break Some(Ok(dir_entry));
+                    }
+                    continue;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Traverse;
+    use crate::Fixture;
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_symlinks() {
+        let fixture = Fixture::from_tree(
+            "
+            a/
+              x
+            b/
+              to_a -> ../a
+              up -> ..
+            ",
+        )
+        .unwrap();
+
+        let paths: Vec<_> = Traverse::new(fixture.path())
+            .unwrap()
+            .follow_symlinks()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        // `a` is reached both directly and through `b/to_a`, in any order, and walked once
+        let files: Vec<_> = paths.iter().filter(|p| p.ends_with("x")).collect();
+        assert_eq!(files.len(), 1, "{:?}", paths);
+        assert_eq!(paths.len(), 5, "{:?}", paths);
+    }
+}
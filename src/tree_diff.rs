@@ -0,0 +1,438 @@
+//! Export the `struct` [`TreeDiff`]. Walks two directory trees in lockstep and yields the
+//! differences between them.
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::contents::same_contents;
+
+/// Which aspects of two entries with the same relative path are compared by [TreeDiff].
+///
+/// Size, modification time and content are only compared for files (and for the target of
+/// symlinks, in the case of content). Permissions are compared for every kind of entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Compare {
+    pub size: bool,
+    pub mtime: bool,
+    pub permissions: bool,
+    /// Byte by byte comparison. Much slower than the rest, only done when the sizes match.
+    pub content: bool,
+}
+
+impl Compare {
+    /// Compare size and modification time, a quick check that catches most modifications.
+    pub fn new() -> Self {
+        Compare {
+            size: true,
+            mtime: true,
+            permissions: false,
+            content: false,
+        }
+    }
+
+    /// Compare everything.
+    pub fn all() -> Self {
+        Compare {
+            size: true,
+            mtime: true,
+            permissions: true,
+            content: true,
+        }
+    }
+
+    fn any(&self) -> bool {
+        self.size || self.mtime || self.permissions || self.content
+    }
+}
+
+/// Kind of difference found by [TreeDiff] for a relative path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The path only exists in the right tree.
+    Added,
+    /// The path only exists in the left tree.
+    Removed,
+    /// The path exists in both trees but with a different type (file, directory, symlink).
+    TypeChanged,
+    /// The path exists in both trees, with the same type, but they differ. The [Compare]
+    /// has a `true` for every aspect that differs.
+    Modified(Compare),
+}
+
+/// An item yielded by [TreeDiff].
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    /// Path of the entry relative to the roots.
+    pub relative: PathBuf,
+    pub change: Change,
+    /// Metadata of the entry in the left tree, if it exists there.
+    pub left: Option<fs::Metadata>,
+    /// Metadata of the entry in the right tree, if it exists there.
+    pub right: Option<fs::Metadata>,
+}
+
+/// One side of a pair of entries with the same name.
+struct Side {
+    path: PathBuf,
+    meta: fs::Metadata,
+}
+
+/// An entry present in at least one of the trees.
+struct Pair {
+    relative: PathBuf,
+    left: Option<Side>,
+    right: Option<Side>,
+}
+
+/// Walks two directory trees in lockstep, aligning their entries by relative path, and
+/// yields a [DiffEntry] for every path that was added, removed, changed its type or was
+/// modified from the left (old) tree to the right (new) one.
+///
+/// Entries of each directory are visited sorted by name and every directory is visited
+/// right after its own entry, so the output is sorted by relative path with parents always
+/// coming before their content. The content of a directory that only exists on one side is
+/// reported entry by entry. Symlinks are never followed.
+///
+/// A root that does not exist is treated as an empty tree.
+///
+/// ## Example
+/// ```
+/// use itfs::{Compare, TreeDiff};
+///
+/// let diff = TreeDiff::new("./src", "./src")
+///     .unwrap()
+///     .compare(Compare::all());
+///
+/// assert_eq!(diff.count(), 0);
+/// ```
+pub struct TreeDiff {
+    left_root: PathBuf,
+    right_root: PathBuf,
+    compare: Compare,
+    /// Directories being visited. Each one holds the entries that remain to be visited.
+    stack: Vec<VecDeque<Pair>>,
+    /// Error reading a directory, to be yielded after the entry of the directory itself.
+    error: Option<io::Error>,
+}
+
+impl TreeDiff {
+    /// Create a new instance of [TreeDiff] between the `left` (old) and the `right` (new)
+    /// trees, comparing [size and modification time][Compare::new]. This operation will fail
+    /// if reading any of the roots fails for any other reason than it not existing.
+    pub fn new<L: AsRef<Path>, R: AsRef<Path>>(left: L, right: R) -> io::Result<Self> {
+        let (left, right) = (left.as_ref(), right.as_ref());
+        let listing = merge(list(left, Path::new(""))?, list(right, Path::new(""))?);
+
+        Ok(TreeDiff {
+            left_root: left.to_path_buf(),
+            right_root: right.to_path_buf(),
+            compare: Compare::new(),
+            stack: vec![listing],
+            error: None,
+        })
+    }
+
+    /// Choose which aspects are compared to decide whether an entry was modified.
+    pub fn compare(mut self, compare: Compare) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Inspect a pair of entries and, if they are directories, push their content onto the stack.
+    fn visit(&mut self, pair: Pair) -> io::Result<Option<DiffEntry>> {
+        let is_dir = |side: &Option<Side>| side.as_ref().is_some_and(|s| s.meta.is_dir());
+
+        let change = match (&pair.left, &pair.right) {
+            (Some(_), None) => Some(Change::Removed),
+            (None, Some(_)) => Some(Change::Added),
+            (Some(l), Some(r)) if kind(&l.meta) != kind(&r.meta) => Some(Change::TypeChanged),
+            (Some(l), Some(r)) => self.modified(l, r)?.map(Change::Modified),
+            (None, None) => None,
+        };
+
+        // descend into directories, on whichever side they are
+        if is_dir(&pair.left) || is_dir(&pair.right) {
+            let listing = |root: &Path, side: &Option<Side>| match is_dir(side) {
+                true => list(root, &pair.relative),
+                false => Ok(vec![]),
+            };
+            match (
+                listing(&self.left_root, &pair.left),
+                listing(&self.right_root, &pair.right),
+            ) {
+                (Ok(left), Ok(right)) => self.stack.push(merge(left, right)),
+                (Err(e), _) | (_, Err(e)) => self.error = Some(e),
+            }
+        }
+
+        Ok(change.map(|change| DiffEntry {
+            relative: pair.relative,
+            change,
+            left: pair.left.map(|s| s.meta),
+            right: pair.right.map(|s| s.meta),
+        }))
+    }
+
+    /// Compare two entries of the same kind. Returns the aspects that differ, if any.
+    fn modified(&self, left: &Side, right: &Side) -> io::Result<Option<Compare>> {
+        let (l, r) = (&left.meta, &right.meta);
+        let mut diff = Compare::default();
+
+        if self.compare.permissions {
+            diff.permissions = l.permissions() != r.permissions();
+        }
+
+        if l.is_file() {
+            diff.size = self.compare.size && l.len() != r.len();
+            if self.compare.mtime {
+                diff.mtime = match (l.modified(), r.modified()) {
+                    (Ok(lt), Ok(rt)) => lt != rt,
+                    _ => false,
+                };
+            }
+            if self.compare.content {
+                diff.content = l.len() != r.len() || !same_contents(&left.path, &right.path)?;
+            }
+        } else if l.is_symlink() && self.compare.content {
+            diff.content = fs::read_link(&left.path)? != fs::read_link(&right.path)?;
+        }
+
+        Ok(diff.any().then_some(diff))
+    }
+}
+
+impl Iterator for TreeDiff {
+    type Item = io::Result<DiffEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.error.take() {
+                break Some(Err(e));
+            }
+
+            let pair = match self.stack.last_mut()?.pop_front() {
+                Some(pair) => pair,
+                None => {
+                    // this directory is done, resume the parent
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            match self.visit(pair) {
+                Ok(Some(entry)) => break Some(Ok(entry)),
+                Ok(None) => continue,
+                Err(e) => break Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Kind of entry, used to detect [Change::TypeChanged].
+fn kind(meta: &fs::Metadata) -> u8 {
+    let file_type = meta.file_type();
+    if file_type.is_dir() {
+        0
+    } else if file_type.is_file() {
+        1
+    } else if file_type.is_symlink() {
+        2
+    } else {
+        3
+    }
+}
+
+/// Read the entries of `root/relative`, sorted by name. A directory that does not exist
+/// has no entries.
+fn list(root: &Path, relative: &Path) -> io::Result<Vec<(OsString, PathBuf, Side)>> {
+    let read_dir = match fs::read_dir(root.join(relative)) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut entries = vec![];
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry.file_name();
+        entries.push((
+            name.clone(),
+            relative.join(&name),
+            Side {
+                path: entry.path(),
+                // `DirEntry::metadata` does not follow symlinks
+                meta: entry.metadata()?,
+            },
+        ));
+    }
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+/// Align two sorted listings by name.
+fn merge(
+    left: Vec<(OsString, PathBuf, Side)>,
+    right: Vec<(OsString, PathBuf, Side)>,
+) -> VecDeque<Pair> {
+    let mut merged = VecDeque::with_capacity(left.len().max(right.len()));
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+
+    loop {
+        let pair = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if l.0 == r.0 => {
+                let (_, relative, l) = left.next().unwrap();
+                let (_, _, r) = right.next().unwrap();
+                Pair {
+                    relative,
+                    left: Some(l),
+                    right: Some(r),
+                }
+            }
+            (Some(l), Some(r)) if l.0 < r.0 => {
+                let (_, relative, l) = left.next().unwrap();
+                Pair {
+                    relative,
+                    left: Some(l),
+                    right: None,
+                }
+            }
+            (Some(_), None) => {
+                let (_, relative, l) = left.next().unwrap();
+                Pair {
+                    relative,
+                    left: Some(l),
+                    right: None,
+                }
+            }
+            (_, Some(_)) => {
+                let (_, relative, r) = right.next().unwrap();
+                Pair {
+                    relative,
+                    left: None,
+                    right: Some(r),
+                }
+            }
+            (None, None) => break merged,
+        };
+        merged.push_back(pair);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Compare, TreeDiff};
+    use crate::Fixture;
+
+    // symlinks and permissions
+    #[cfg(unix)]
+    #[test]
+    fn changes() {
+        let left = Fixture::from_tree(
+            "
+            content.txt = abc
+            kind = a file
+            link -> same.txt
+            perm.txt [mode=644]
+            removed/
+              inner.txt
+            same.txt = same
+            size.txt = short
+            ",
+        )
+        .unwrap();
+        let right = Fixture::from_tree(
+            "
+            added.txt
+            content.txt = xyz
+            kind/
+              nested.txt
+            link -> size.txt
+            perm.txt [mode=600]
+            same.txt = same
+            size.txt = longer
+            ",
+        )
+        .unwrap();
+        let diff = |compare| -> Vec<(String, Change)> {
+            TreeDiff::new(left.path(), right.path())
+                .unwrap()
+                .compare(compare)
+                .map(Result::unwrap)
+                .map(|e| (e.relative.to_string_lossy().into_owned(), e.change))
+                .collect()
+        };
+        let expect = |changes: &[(&str, Change)]| -> Vec<(String, Change)> {
+            changes.iter().map(|(p, c)| (p.to_string(), *c)).collect()
+        };
+        let modified = |compare| Change::Modified(compare);
+        let content = Compare {
+            content: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            diff(Compare::all()),
+            expect(&[
+                ("added.txt", Change::Added),
+                ("content.txt", modified(content)),
+                ("kind", Change::TypeChanged),
+                ("kind/nested.txt", Change::Added),
+                ("link", modified(content)),
+                (
+                    "perm.txt",
+                    modified(Compare {
+                        permissions: true,
+                        ..Default::default()
+                    })
+                ),
+                ("removed", Change::Removed),
+                ("removed/inner.txt", Change::Removed),
+                (
+                    "size.txt",
+                    modified(Compare {
+                        size: true,
+                        content: true,
+                        ..Default::default()
+                    })
+                ),
+            ])
+        );
+
+        // size and modification time only
+        assert_eq!(
+            diff(Compare::new()),
+            expect(&[
+                ("added.txt", Change::Added),
+                ("kind", Change::TypeChanged),
+                ("kind/nested.txt", Change::Added),
+                ("removed", Change::Removed),
+                ("removed/inner.txt", Change::Removed),
+                (
+                    "size.txt",
+                    modified(Compare {
+                        size: true,
+                        ..Default::default()
+                    })
+                ),
+            ])
+        );
+
+        // the metadata of each side comes along
+        let entry = TreeDiff::new(left.path(), right.path())
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|e| e.change == Change::TypeChanged)
+            .unwrap();
+        assert!(entry.left.unwrap().is_file() && entry.right.unwrap().is_dir());
+
+        // a missing root is an empty tree
+        let all_added = TreeDiff::new(left.join("missing"), right.path()).unwrap();
+        assert!(all_added
+            .map(Result::unwrap)
+            .all(|e| e.change == Change::Added));
+    }
+}
@@ -0,0 +1,316 @@
+//! Export the `struct` [`WalkStats`]. Shared counters that walkers keep up to date while
+//! they iterate, plus an optional progress callback invoked at a configurable interval.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Callback invoked by [WalkStats] to report progress.
+type ProgressCallback = Box<dyn FnMut(&WalkStats) + Send>;
+
+/// Shared handle to the counters maintained by a walker such as
+/// [ReadDirRecursive][crate::ReadDirRecursive] or
+/// [FindDirsWithComponent][crate::FindDirsWithComponent].
+///
+/// Cloning a [WalkStats] is cheap and every clone looks at the same counters, so a copy can be
+/// kept outside of the iterator chain and read at any point during (or after) the iteration,
+/// no matter how many adaptors are wrapped around the walker.
+///
+/// The walker only knows about the entries it yields. To also know how many of them were
+/// dropped by the adaptors down the chain, wrap the end of the chain with [WalkStats::track].
+///
+/// ## Example
+/// ```
+/// use itfs::{ReadDirRecursive, ResultFilter, WalkStats};
+/// use std::time::Duration;
+///
+/// let stats = WalkStats::new().with_progress(Duration::from_millis(100), |s| {
+///     eprintln!("{}", s);
+/// });
+///
+/// let rdr = ReadDirRecursive::new(".").unwrap().with_stats(&stats);
+///
+/// for entry in stats.track(ResultFilter(rdr)) {
+///     let _ = entry.path();
+/// }
+///
+/// println!("directories read: {}", stats.dirs_read());
+/// ```
+#[derive(Clone)]
+pub struct WalkStats(Arc<Inner>);
+
+struct Inner {
+    started: Instant,
+    dirs_read: AtomicU64,
+    entries_yielded: AtomicU64,
+    entries_passed: AtomicU64,
+    tracked: AtomicBool,
+    bytes_seen: AtomicU64,
+    errors: Mutex<HashMap<io::ErrorKind, u64>>,
+    progress: Mutex<Option<Progress>>,
+}
+
+struct Progress {
+    interval: Duration,
+    last: Instant,
+    callback: ProgressCallback,
+}
+
+impl WalkStats {
+    /// Create a new set of counters, all set to zero. The elapsed time is measured from here.
+    pub fn new() -> Self {
+        WalkStats(Arc::new(Inner {
+            started: Instant::now(),
+            dirs_read: AtomicU64::new(0),
+            entries_yielded: AtomicU64::new(0),
+            entries_passed: AtomicU64::new(0),
+            tracked: AtomicBool::new(false),
+            bytes_seen: AtomicU64::new(0),
+            errors: Mutex::new(HashMap::new()),
+            progress: Mutex::new(None),
+        }))
+    }
+
+    /// Register a callback that will be invoked, at most once per `interval`, every time one
+    /// of the counters changes. The callback receives the stats themselves so it can read
+    /// any counter it needs.
+    ///
+    /// The callback must not call [WalkStats::report] (it would be ignored).
+    pub fn with_progress<F>(self, interval: Duration, callback: F) -> Self
+    where
+        F: FnMut(&WalkStats) + Send + 'static,
+    {
+        *self.0.progress.lock().unwrap() = Some(Progress {
+            interval,
+            last: Instant::now(),
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Number of directories whose content has been read so far (the root included).
+    pub fn dirs_read(&self) -> u64 {
+        self.0.dirs_read.load(Ordering::Relaxed)
+    }
+
+    /// Number of `Ok` entries yielded by the walker.
+    pub fn entries_yielded(&self) -> u64 {
+        self.0.entries_yielded.load(Ordering::Relaxed)
+    }
+
+    /// Number of items, entries and errors alike, that reached the end of the iterator chain
+    /// wrapped with [WalkStats::track].
+    pub fn entries_passed(&self) -> u64 {
+        self.0.entries_passed.load(Ordering::Relaxed)
+    }
+
+    /// Number of items yielded by the walker, entries and errors alike, that did not reach the
+    /// end of the chain wrapped with [WalkStats::track]. Always zero if [WalkStats::track] is
+    /// not used.
+    pub fn entries_filtered(&self) -> u64 {
+        match self.0.tracked.load(Ordering::Relaxed) {
+            true => (self.entries_yielded() + self.errors()).saturating_sub(self.entries_passed()),
+            false => 0,
+        }
+    }
+
+    /// Total number of errors yielded by the walker.
+    pub fn errors(&self) -> u64 {
+        self.0.errors.lock().unwrap().values().sum()
+    }
+
+    /// Number of errors yielded by the walker, grouped by their [io::ErrorKind].
+    pub fn errors_by_kind(&self) -> HashMap<io::ErrorKind, u64> {
+        self.0.errors.lock().unwrap().clone()
+    }
+
+    /// Sum of the sizes (as reported by the metadata) of the entries yielded by the walker.
+    pub fn bytes_seen(&self) -> u64 {
+        self.0.bytes_seen.load(Ordering::Relaxed)
+    }
+
+    /// Time elapsed since the stats were created.
+    pub fn elapsed(&self) -> Duration {
+        self.0.started.elapsed()
+    }
+
+    /// Invoke the progress callback right away, regardless of the configured interval.
+    ///
+    /// Useful to print a last update once the iteration is over. [Tracked] calls this
+    /// automatically, once, when the inner iterator reaches its end.
+    pub fn report(&self) {
+        self.notify(true)
+    }
+
+    /// Wrap the last iterator of a chain so every item coming out of it is counted as
+    /// "passed". See [WalkStats::entries_filtered].
+    pub fn track<I: Iterator>(&self, iter: I) -> Tracked<I> {
+        self.0.tracked.store(true, Ordering::Relaxed);
+        Tracked(iter, self.clone(), false)
+    }
+
+    pub(crate) fn record_dir(&self) {
+        self.0.dirs_read.fetch_add(1, Ordering::Relaxed);
+        self.notify(false);
+    }
+
+    pub(crate) fn record_entry(&self, bytes: u64) {
+        self.0.entries_yielded.fetch_add(1, Ordering::Relaxed);
+        self.0.bytes_seen.fetch_add(bytes, Ordering::Relaxed);
+        self.notify(false);
+    }
+
+    pub(crate) fn record_error(&self, error: &io::Error) {
+        *self
+            .0
+            .errors
+            .lock()
+            .unwrap()
+            .entry(error.kind())
+            .or_insert(0) += 1;
+        self.notify(false);
+    }
+
+    fn record_passed(&self) {
+        self.0.entries_passed.fetch_add(1, Ordering::Relaxed);
+        self.notify(false);
+    }
+
+    fn notify(&self, force: bool) {
+        // `try_lock` so a callback touching the stats can never deadlock the walker
+        if let Ok(mut guard) = self.0.progress.try_lock() {
+            if let Some(progress) = guard.as_mut() {
+                if force || progress.last.elapsed() >= progress.interval {
+                    progress.last = Instant::now();
+                    (progress.callback)(self);
+                }
+            }
+        }
+    }
+}
+
+impl Default for WalkStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Short, one line summary of the counters. Handy as an end-of-run report.
+impl fmt::Display for WalkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} dirs, {} entries ({} filtered), {} bytes, {} errors in {:?}",
+            self.dirs_read(),
+            self.entries_yielded(),
+            self.entries_filtered(),
+            self.bytes_seen(),
+            self.errors(),
+            self.elapsed()
+        )
+    }
+}
+
+impl fmt::Debug for WalkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkStats")
+            .field("dirs_read", &self.dirs_read())
+            .field("entries_yielded", &self.entries_yielded())
+            .field("entries_passed", &self.entries_passed())
+            .field("bytes_seen", &self.bytes_seen())
+            .field("errors", &self.errors_by_kind())
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+/// Iterator returned by [WalkStats::track]. Yields the same items as the inner iterator
+/// while counting them. The last field tells whether the end was already reported.
+pub struct Tracked<I: Iterator>(pub I, pub WalkStats, bool);
+
+impl<I: Iterator> Iterator for Tracked<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0.next() {
+            Some(item) => {
+                self.1.record_passed();
+                Some(item)
+            }
+            None => {
+                if !self.2 {
+                    self.2 = true;
+                    self.1.report();
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::ErrorKind,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::WalkStats;
+    use crate::{prelude::*, FsOp, MemFs, ReadDirRecursive};
+
+    #[test]
+    fn counters() {
+        let mut fs = MemFs::from_tree("a.rs\nb.txt\nsub/\n  c.rs\n  d.rs").unwrap();
+        fs.fail("sub/d.rs", FsOp::SymlinkMetadata, ErrorKind::Other);
+        let walk = |stats: &WalkStats| {
+            ReadDirRecursive::with_fs("", &fs)
+                .unwrap()
+                .with_stats(stats)
+        };
+
+        // errors flowing to the end are not filtered
+        let stats = WalkStats::new();
+        assert_eq!(stats.track(walk(&stats)).count(), 4);
+        assert_eq!((stats.entries_yielded(), stats.errors()), (3, 1));
+        assert_eq!((stats.dirs_read(), stats.bytes_seen()), (2, 0));
+        assert_eq!((stats.entries_passed(), stats.entries_filtered()), (4, 0));
+
+        // entries and errors dropped down the chain are
+        let stats = WalkStats::new();
+        let extensions = vec![std::ffi::OsStr::new("rs")];
+        let tracked = walk(&stats).allow_extensions(&extensions).drop_errors();
+        assert_eq!(stats.track(tracked).count(), 2);
+        assert_eq!((stats.entries_passed(), stats.entries_filtered()), (2, 2));
+        assert_eq!(stats.errors_by_kind().get(&ErrorKind::Other), Some(&1));
+
+        // without tracking nothing counts as filtered
+        let stats = WalkStats::new();
+        walk(&stats).for_each(drop);
+        assert_eq!(stats.entries_filtered(), 0);
+    }
+
+    #[test]
+    fn final_report_once() {
+        let reports = Arc::new(AtomicUsize::new(0));
+        let counter = reports.clone();
+        let stats = WalkStats::new().with_progress(Duration::from_secs(3600), move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let mut tracked = stats.track(vec![1, 2].into_iter());
+        assert_eq!(tracked.by_ref().count(), 2);
+        assert_eq!(tracked.next(), None);
+        assert_eq!(tracked.next(), None);
+        assert_eq!(reports.load(Ordering::Relaxed), 1);
+    }
+}